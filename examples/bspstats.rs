@@ -57,7 +57,7 @@ fn main() {
                     }
                 })
                 .next()
-                .map(Clone::clone)
+                .cloned()
         } else {
             None
         };
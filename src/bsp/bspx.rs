@@ -0,0 +1,277 @@
+use crate::common::LeReader;
+use crate::{slice_to_cstring, BinParseError, BinParseResult};
+use std::ffi::{CString, IntoStringError};
+use std::string::String;
+use std::vec::Vec;
+
+pub const BSPX_MAGIC: [u8; 4] = *b"BSPX";
+
+pub(crate) const BSPX_NAME_SZ: usize = 24;
+pub(crate) const BSPX_ENTRY_SZ: usize = BSPX_NAME_SZ + 8;
+
+const DECOUPLED_LM_SZ: usize = 40;
+const BRUSH_MODEL_SZ: usize = 16;
+const BRUSH_SZ: usize = 28;
+const BRUSH_FACE_SZ: usize = 16;
+
+/// Names of the BSPX lumps written by common compilers
+pub mod bspx_lumps {
+    /// RGB lighting samples, laid out like the standard light lump
+    pub const RGBLIGHTING: &str = "RGBLIGHTING";
+
+    /// Light direction for each lighting sample, used for deluxemapping
+    pub const LIGHTINGDIR: &str = "LIGHTINGDIR";
+
+    /// Per-face lightmap scale as a power of 2
+    pub const LMSHIFT: &str = "LMSHIFT";
+
+    /// Per-face lightmap projections independent of texture alignment
+    pub const DECOUPLED_LM: &str = "DECOUPLED_LM";
+
+    /// Brushes for each model, used for collision by some engines
+    pub const BRUSHLIST: &str = "BRUSHLIST";
+}
+
+/// Location of a named lump within the BSPX directory.  Offsets are relative
+/// to the start of the BSP.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BspxEntry {
+    name: [u8; BSPX_NAME_SZ],
+    offset: u32,
+    length: u32,
+}
+
+impl BspxEntry {
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Self {
+        let mut reader = LeReader::new(bytes);
+
+        Self {
+            name: reader.bytes(),
+            offset: reader.u32(),
+            length: reader.u32(),
+        }
+    }
+
+    /// Obtain the name as a C string.  If the name is not already
+    /// null-terminated (in which case the entry is not well-formed) a null byte
+    /// is appended to make a valid C string.
+    pub fn name_to_cstring(&self) -> CString {
+        slice_to_cstring(&self.name)
+    }
+
+    /// Attempt to interpret the name as UTF-8 encoded string
+    pub fn name_to_string(&self) -> Result<String, IntoStringError> {
+        self.name_to_cstring().into_string()
+    }
+
+    /// Name in raw bytes
+    pub fn name(&self) -> [u8; BSPX_NAME_SZ] {
+        self.name
+    }
+
+    /// BSP offset of lump
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// Length of lump in bytes
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+}
+
+/// Lightmap placement for a face when lightmaps are decoupled from texture
+/// alignment
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DecoupledLightmap {
+    /// Width and height in luxels
+    pub size: [u16; 2],
+
+    /// Offset into the light lump, or -1 if the face is not lightmapped
+    pub light_offset: i32,
+
+    /// Projects a world position to lightmap coordinates, in luxels
+    pub world_to_lightmap: [[f32; 4]; 2],
+}
+
+/// Non-axial face of a BSPX brush
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BspxBrushFace {
+    pub normal: [f32; 3],
+    pub dist: f32,
+}
+
+/// Convex brush bounded by its axial box and any extra faces
+#[derive(Clone, PartialEq, Debug)]
+pub struct BspxBrush {
+    pub mins: [f32; 3],
+    pub maxs: [f32; 3],
+    pub contents: i32,
+    pub faces: Vec<BspxBrushFace>,
+}
+
+/// Brushes belonging to a single model
+#[derive(Clone, PartialEq, Debug)]
+pub struct BspxModelBrushes {
+    pub model: u32,
+    pub brushes: Vec<BspxBrush>,
+}
+
+/// Decoded BSPX lump.  Lumps which are not well-known are kept as raw bytes.
+#[derive(Clone, PartialEq, Debug)]
+pub enum BspxLump {
+    /// RGB samples, indexed like the bytes of the standard light lump
+    RgbLighting(Vec<[u8; 3]>),
+
+    /// Light direction samples, indexed like the bytes of the standard light
+    /// lump
+    LightingDir(Vec<[u8; 3]>),
+
+    /// Lightmap scale shift for each face
+    LmShift(Vec<u8>),
+
+    /// Lightmap placement for each face
+    DecoupledLm(Vec<DecoupledLightmap>),
+
+    BrushList(Vec<BspxModelBrushes>),
+
+    Other(Vec<u8>),
+}
+
+impl BspxLump {
+    /// Decode a lump based on its name
+    pub fn decode(name: &str, bytes: Vec<u8>) -> BinParseResult<Self> {
+        match name {
+            bspx_lumps::RGBLIGHTING => {
+                decode_samples(name, &bytes).map(Self::RgbLighting)
+            }
+            bspx_lumps::LIGHTINGDIR => {
+                decode_samples(name, &bytes).map(Self::LightingDir)
+            }
+            bspx_lumps::LMSHIFT => Ok(Self::LmShift(bytes)),
+            bspx_lumps::DECOUPLED_LM => {
+                decode_decoupled_lm(&bytes).map(Self::DecoupledLm)
+            }
+            bspx_lumps::BRUSHLIST => {
+                decode_brush_list(&bytes).map(Self::BrushList)
+            }
+            _ => Ok(Self::Other(bytes)),
+        }
+    }
+}
+
+fn check_multiple(name: &str, bytes: &[u8], size: usize) -> BinParseResult<()> {
+    if !bytes.len().is_multiple_of(size) {
+        return Err(BinParseError::Parse(format!(
+            "{name} lump length {} is not a multiple of {size}",
+            bytes.len(),
+        )));
+    }
+
+    Ok(())
+}
+
+fn decode_samples(name: &str, bytes: &[u8]) -> BinParseResult<Vec<[u8; 3]>> {
+    check_multiple(name, bytes, 3)?;
+
+    Ok(bytes
+        .chunks_exact(3)
+        .map(|chunk| chunk.try_into().unwrap())
+        .collect())
+}
+
+fn decode_decoupled_lm(bytes: &[u8]) -> BinParseResult<Vec<DecoupledLightmap>> {
+    check_multiple(bspx_lumps::DECOUPLED_LM, bytes, DECOUPLED_LM_SZ)?;
+
+    Ok(bytes
+        .chunks_exact(DECOUPLED_LM_SZ)
+        .map(|chunk| {
+            let mut reader = LeReader::new(chunk);
+
+            DecoupledLightmap {
+                size: [reader.u16(), reader.u16()],
+                light_offset: reader.i32(),
+                world_to_lightmap: [reader.f32s(), reader.f32s()],
+            }
+        })
+        .collect())
+}
+
+fn decode_brush_list(bytes: &[u8]) -> BinParseResult<Vec<BspxModelBrushes>> {
+    let mut rest = bytes;
+    let mut models = Vec::new();
+
+    while !rest.is_empty() {
+        let mut reader = LeReader::new(take(&mut rest, BRUSH_MODEL_SZ)?);
+        let version = reader.u32();
+
+        if version != 1 {
+            return Err(BinParseError::Parse(format!(
+                "Unsupported {} version {version}",
+                bspx_lumps::BRUSHLIST,
+            )));
+        }
+
+        let model = reader.u32();
+        let brush_count = reader.u32();
+        let mut face_count = reader.u32();
+        let mut brushes = Vec::new();
+
+        for _ in 0..brush_count {
+            let mut reader = LeReader::new(take(&mut rest, BRUSH_SZ)?);
+            let mins = reader.f32s();
+            let maxs = reader.f32s();
+            let contents = reader.i16().into();
+            let brush_face_count = reader.u16();
+
+            face_count = face_count
+                .checked_sub(brush_face_count.into())
+                .ok_or_else(face_count_error)?;
+
+            let face_bytes =
+                take(&mut rest, usize::from(brush_face_count) * BRUSH_FACE_SZ)?;
+
+            let faces = face_bytes
+                .chunks_exact(BRUSH_FACE_SZ)
+                .map(|chunk| {
+                    let mut reader = LeReader::new(chunk);
+
+                    BspxBrushFace {
+                        normal: reader.f32s(),
+                        dist: reader.f32(),
+                    }
+                })
+                .collect();
+
+            brushes.push(BspxBrush {
+                mins,
+                maxs,
+                contents,
+                faces,
+            });
+        }
+
+        if face_count != 0 {
+            return Err(face_count_error());
+        }
+
+        models.push(BspxModelBrushes { model, brushes });
+    }
+
+    Ok(models)
+}
+
+fn take<'a>(rest: &mut &'a [u8], size: usize) -> BinParseResult<&'a [u8]> {
+    let (head, tail) = rest.split_at_checked(size).ok_or_else(|| {
+        BinParseError::Parse(format!("{} lump cut off", bspx_lumps::BRUSHLIST))
+    })?;
+    *rest = tail;
+    Ok(head)
+}
+
+fn face_count_error() -> BinParseError {
+    BinParseError::Parse(format!(
+        "{} face count does not match brushes",
+        bspx_lumps::BRUSHLIST,
+    ))
+}
@@ -0,0 +1,226 @@
+use crate::{bsp, BinParseError, WriteError};
+use bsp::{
+    bspx_lumps, BspxBrush, BspxBrushFace, BspxLump, BspxModelBrushes,
+    DecoupledLightmap, EntryOffset, Writer, BSP_VERSION,
+};
+use std::io::Cursor;
+use std::vec::Vec;
+
+fn brush_list_bytes() -> Vec<u8> {
+    let mut bytes = Vec::new();
+
+    for word in [1u32, 0, 1, 1] {
+        bytes.extend(word.to_le_bytes());
+    }
+
+    for float in [-8.0f32, -8.0, -8.0, 8.0, 8.0, 8.0] {
+        bytes.extend(float.to_le_bytes());
+    }
+
+    bytes.extend(bsp::contents::SOLID.to_le_bytes()[..2].iter());
+    bytes.extend(1u16.to_le_bytes());
+
+    for float in [0.6f32, 0.8, 0.0, 4.0] {
+        bytes.extend(float.to_le_bytes());
+    }
+
+    bytes
+}
+
+fn decoupled_lm_bytes() -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend(4u16.to_le_bytes());
+    bytes.extend(3u16.to_le_bytes());
+    bytes.extend(12i32.to_le_bytes());
+
+    for float in [0.0625f32, 0.0, 0.0, 1.0, 0.0, 0.0625, 0.0, 2.0] {
+        bytes.extend(float.to_le_bytes());
+    }
+
+    bytes
+}
+
+fn bspx_bsp() -> Vec<u8> {
+    let mut writer = Writer::new(BSP_VERSION);
+    writer.set_lump(EntryOffset::Light, vec![10, 20]);
+    writer.set_lump(EntryOffset::Entities, b"{\n}\n\0".to_vec());
+    writer
+        .set_bspx_lump(bspx_lumps::RGBLIGHTING, vec![1, 2, 3, 4, 5, 6])
+        .unwrap();
+    writer
+        .set_bspx_lump(bspx_lumps::LIGHTINGDIR, vec![128, 128, 255, 0, 0, 255])
+        .unwrap();
+    writer
+        .set_bspx_lump(bspx_lumps::LMSHIFT, vec![4, 3, 5])
+        .unwrap();
+    writer
+        .set_bspx_lump(bspx_lumps::DECOUPLED_LM, decoupled_lm_bytes())
+        .unwrap();
+    writer
+        .set_bspx_lump(bspx_lumps::BRUSHLIST, brush_list_bytes())
+        .unwrap();
+    writer.set_bspx_lump("MYLUMP", vec![9]).unwrap();
+
+    let mut bytes = Vec::new();
+    writer.write_to(&mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn list_bspx_entries() {
+    let bytes = bspx_bsp();
+    let mut cursor = Cursor::new(&bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let entries = parser.bspx_entries().unwrap();
+    let names: Vec<_> = entries
+        .iter()
+        .map(|entry| entry.name_to_string().unwrap())
+        .collect();
+
+    assert_eq!(
+        names,
+        [
+            "RGBLIGHTING",
+            "LIGHTINGDIR",
+            "LMSHIFT",
+            "DECOUPLED_LM",
+            "BRUSHLIST",
+            "MYLUMP"
+        ]
+    );
+
+    assert_eq!(&bytes[136..140], b"BSPX");
+    assert_eq!(entries[0].offset(), 136 + 8 + 32 * 6);
+    assert_eq!(entries[0].length(), 6);
+    assert_eq!(entries[1].offset(), entries[0].offset() + 8);
+    assert_eq!(entries[2].offset(), entries[1].offset() + 8);
+    assert_eq!(entries[3].offset(), entries[2].offset() + 4);
+    assert_eq!(entries[5].length(), 1);
+
+    let mut buf = Vec::new();
+    std::io::Read::read_to_end(
+        &mut parser.bspx_lump_reader(&entries[2]).unwrap(),
+        &mut buf,
+    )
+    .unwrap();
+    assert_eq!(buf, [4, 3, 5]);
+}
+
+#[test]
+fn parse_bspx_lumps() {
+    let bytes = bspx_bsp();
+    let mut cursor = Cursor::new(&bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+
+    let mut parse = |name| parser.parse_bspx_lump_named(name).unwrap();
+
+    assert_eq!(
+        parse(bspx_lumps::RGBLIGHTING),
+        Some(BspxLump::RgbLighting(vec![[1, 2, 3], [4, 5, 6]]))
+    );
+    assert_eq!(
+        parse(bspx_lumps::LIGHTINGDIR),
+        Some(BspxLump::LightingDir(vec![[128, 128, 255], [0, 0, 255]]))
+    );
+    assert_eq!(
+        parse(bspx_lumps::LMSHIFT),
+        Some(BspxLump::LmShift(vec![4, 3, 5]))
+    );
+    assert_eq!(
+        parse(bspx_lumps::DECOUPLED_LM),
+        Some(BspxLump::DecoupledLm(vec![DecoupledLightmap {
+            size: [4, 3],
+            light_offset: 12,
+            world_to_lightmap: [
+                [0.0625, 0.0, 0.0, 1.0],
+                [0.0, 0.0625, 0.0, 2.0]
+            ],
+        }]))
+    );
+    assert_eq!(
+        parse(bspx_lumps::BRUSHLIST),
+        Some(BspxLump::BrushList(vec![BspxModelBrushes {
+            model: 0,
+            brushes: vec![BspxBrush {
+                mins: [-8.0; 3],
+                maxs: [8.0; 3],
+                contents: bsp::contents::SOLID,
+                faces: vec![BspxBrushFace {
+                    normal: [0.6, 0.8, 0.0],
+                    dist: 4.0,
+                }],
+            }],
+        }]))
+    );
+    assert_eq!(parse("MYLUMP"), Some(BspxLump::Other(vec![9])));
+    assert_eq!(parse("FACENORMALS"), None);
+}
+
+#[test]
+fn parse_no_bspx() {
+    let mut writer = Writer::new(BSP_VERSION);
+    writer.set_lump(EntryOffset::Light, vec![10, 20]);
+    let mut bytes = Vec::new();
+    writer.write_to(&mut bytes).unwrap();
+    bytes.extend(b"JUNK");
+
+    let mut cursor = Cursor::new(&bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+
+    assert!(parser.bspx_entries().unwrap().is_empty());
+}
+
+#[test]
+fn parse_bspx_truncated() {
+    let mut bytes = bspx_bsp();
+    bytes.truncate(136 + 8 + 32 * 3);
+
+    let mut cursor = Cursor::new(&bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+
+    assert!(matches!(parser.bspx_entries(), Err(BinParseError::Io(_))));
+}
+
+#[test]
+fn decode_bad_rgb_lighting() {
+    let err = BspxLump::decode(bspx_lumps::RGBLIGHTING, vec![0; 4]);
+    assert!(matches!(err, Err(BinParseError::Parse(_))));
+}
+
+#[test]
+fn decode_bad_brush_list() {
+    let mut bytes = brush_list_bytes();
+    bytes[12] = 2;
+    let err = BspxLump::decode(bspx_lumps::BRUSHLIST, bytes).unwrap_err();
+    assert!(matches!(err, BinParseError::Parse(_)));
+
+    let mut bytes = brush_list_bytes();
+    bytes.pop();
+    let err = BspxLump::decode(bspx_lumps::BRUSHLIST, bytes).unwrap_err();
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn copy_bspx_lumps_verbatim() {
+    let bytes = bspx_bsp();
+    let mut cursor = Cursor::new(&bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let mut writer = parser.parse_lumps().unwrap();
+    let mut copied = Vec::new();
+    writer.write_to(&mut copied).unwrap();
+
+    assert_eq!(copied, bytes);
+    assert_eq!(writer.bspx_lump(bspx_lumps::LMSHIFT), Some(&[4, 3, 5][..]));
+    assert_eq!(writer.remove_bspx_lump("MYLUMP"), Some(vec![9]));
+    assert_eq!(writer.bspx_lump("MYLUMP"), None);
+}
+
+#[test]
+fn set_bad_bspx_name() {
+    let mut writer = Writer::new(BSP_VERSION);
+
+    for name in ["ABCDEFGHIJKLMNOPQRSTUVWX", "A\0B"] {
+        let err = writer.set_bspx_lump(name, Vec::new()).unwrap_err();
+        assert!(matches!(err, WriteError::Validation(_)));
+    }
+}
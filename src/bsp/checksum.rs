@@ -0,0 +1,152 @@
+use super::writer::LUMP_ORDER;
+use super::{EntryOffset, Parser};
+use crate::BinParseResult;
+use std::io::{Read, Seek};
+
+/// Map checksums as computed by QuakeWorld-derived engines and compared
+/// between clients and servers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MapChecksums {
+    /// Covers every lump except the entities
+    pub checksum: u32,
+
+    /// Also excludes the visibility, leaves, and nodes lumps, so that maps
+    /// with recomputed visibility still match
+    pub checksum2: u32,
+}
+
+impl<Reader: Seek + Read> Parser<'_, Reader> {
+    /// Computes the map checksums the way the engine does: each lump is
+    /// checksummed with `block_checksum` and the results are XORed together
+    pub fn map_checksums(&mut self) -> BinParseResult<MapChecksums> {
+        let mut checksums = MapChecksums {
+            checksum: 0,
+            checksum2: 0,
+        };
+
+        for entry_offset in LUMP_ORDER {
+            if entry_offset == EntryOffset::Entities {
+                continue;
+            }
+
+            let lump_checksum = block_checksum(&self.read_lump(entry_offset)?);
+            checksums.checksum ^= lump_checksum;
+
+            if !matches!(
+                entry_offset,
+                EntryOffset::Vis | EntryOffset::Leaves | EntryOffset::Nodes
+            ) {
+                checksums.checksum2 ^= lump_checksum;
+            }
+        }
+
+        Ok(checksums)
+    }
+}
+
+/// Equivalent of the engine's `Com_BlockChecksum`: the MD4 digest of `bytes`
+/// read as 4 little-endian words which are XORed together
+pub fn block_checksum(bytes: &[u8]) -> u32 {
+    md4(bytes)
+        .chunks_exact(4)
+        .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+        .fold(0, |checksum, word| checksum ^ word)
+}
+
+const MD4_BLOCK_SZ: usize = 64;
+
+// MD4 as specified by RFC 1320
+fn md4(bytes: &[u8]) -> [u8; 16] {
+    let mut state = [0x67452301u32, 0xefcdab89, 0x98badcfe, 0x10325476];
+    let mut blocks = bytes.chunks_exact(MD4_BLOCK_SZ);
+
+    for block in &mut blocks {
+        md4_block(&mut state, block);
+    }
+
+    // Message is terminated by a 1 bit and padded with 0 bits up to the
+    // bit length, which fills the last 8 bytes of the final block
+    let rest = blocks.remainder();
+    let mut tail = [0u8; MD4_BLOCK_SZ * 2];
+    tail[..rest.len()].copy_from_slice(rest);
+    tail[rest.len()] = 0x80;
+
+    let tail_length = if rest.len() < MD4_BLOCK_SZ - 8 {
+        MD4_BLOCK_SZ
+    } else {
+        MD4_BLOCK_SZ * 2
+    };
+
+    let bit_length = (bytes.len() as u64).wrapping_mul(8);
+    tail[(tail_length - 8)..tail_length]
+        .copy_from_slice(&bit_length.to_le_bytes());
+
+    for block in tail[..tail_length].chunks_exact(MD4_BLOCK_SZ) {
+        md4_block(&mut state, block);
+    }
+
+    let mut digest = [0u8; 16];
+
+    for (bytes, word) in digest.chunks_exact_mut(4).zip(state) {
+        bytes.copy_from_slice(&word.to_le_bytes());
+    }
+
+    digest
+}
+
+// Round function, additive constant, order of words, and shift for each step
+// within a group of 4
+type Md4Round = (fn(u32, u32, u32) -> u32, u32, [usize; 16], [u32; 4]);
+
+fn md4_block(state: &mut [u32; 4], block: &[u8]) {
+    let mut words = [0u32; 16];
+
+    for (word, bytes) in words.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_le_bytes(bytes.try_into().unwrap());
+    }
+
+    let f = |x: u32, y: u32, z: u32| (x & y) | (!x & z);
+    let g = |x: u32, y: u32, z: u32| (x & y) | (x & z) | (y & z);
+    let h = |x: u32, y: u32, z: u32| x ^ y ^ z;
+
+    let rounds: [Md4Round; 3] = [
+        (
+            f,
+            0,
+            [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+            [3, 7, 11, 19],
+        ),
+        (
+            g,
+            0x5a827999,
+            [0, 4, 8, 12, 1, 5, 9, 13, 2, 6, 10, 14, 3, 7, 11, 15],
+            [3, 5, 9, 13],
+        ),
+        (
+            h,
+            0x6ed9eba1,
+            [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15],
+            [3, 9, 11, 15],
+        ),
+    ];
+
+    let mut vars = *state;
+
+    for (function, constant, order, shifts) in rounds {
+        for (step, word_idx) in order.into_iter().enumerate() {
+            let [a, b, c, d] = vars;
+            let a = a
+                .wrapping_add(function(b, c, d))
+                .wrapping_add(words[word_idx])
+                .wrapping_add(constant)
+                .rotate_left(shifts[step % 4]);
+
+            // Each step updates the next variable in the cycle a, d, c, b
+            vars = [d, a, b, c];
+        }
+    }
+
+    for (word, var) in state.iter_mut().zip(vars) {
+        *word = word.wrapping_add(var);
+    }
+}
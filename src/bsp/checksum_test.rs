@@ -0,0 +1,65 @@
+use crate::bsp;
+use bsp::{block_checksum, EntryOffset, MapChecksums, Writer, BSP_VERSION};
+use std::io::Cursor;
+use std::vec::Vec;
+
+// Expected values are the RFC 1320 MD4 test suite digests, read as
+// little-endian words and XORed together
+#[test]
+fn block_checksum_md4_vectors() {
+    // 31d6cfe0d16ae931b73c59d7e0c089c0
+    assert_eq!(block_checksum(b""), 0xc6f640b7);
+
+    // a448017aaf21d8525fc10ae87aa6729d
+    assert_eq!(block_checksum(b"abc"), 0x5da10e2e);
+
+    // d9130a8164549fe818874806e1c7014b
+    assert_eq!(block_checksum(b"message digest"), 0x24dc0744);
+
+    // 043f8582f241db351ce627e153e7f0e4
+    assert_eq!(
+        block_checksum(
+            b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789"
+        ),
+        0xb2897fb9
+    );
+
+    // e33b4ddc9c38f2199c3e7b164fcc0536
+    assert_eq!(
+        block_checksum("1234567890".repeat(8).as_bytes()),
+        0xe5c1f1ac
+    );
+}
+
+fn checksums(writer: &Writer) -> MapChecksums {
+    let mut bytes = Vec::new();
+    writer.write_to(&mut bytes).unwrap();
+    let mut cursor = Cursor::new(&bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    parser.map_checksums().unwrap()
+}
+
+#[test]
+fn map_checksums() {
+    let empty = block_checksum(b"");
+    let mut writer = Writer::new(BSP_VERSION);
+    writer.set_lump(EntryOffset::Planes, b"abc".to_vec());
+
+    // Every lump but the entities is checksummed, including empty ones.  The
+    // other 13 lumps are empty for checksum, and 10 are for checksum2.
+    assert_eq!(
+        checksums(&writer),
+        MapChecksums {
+            checksum: 0x5da10e2e ^ empty,
+            checksum2: 0x5da10e2e,
+        }
+    );
+
+    writer.set_lump(EntryOffset::Entities, b"message digest".to_vec());
+    assert_eq!(checksums(&writer).checksum, 0x5da10e2e ^ empty);
+
+    writer.set_lump(EntryOffset::Vis, b"message digest".to_vec());
+    let changed = checksums(&writer);
+    assert_eq!(changed.checksum, 0x5da10e2e ^ 0x24dc0744);
+    assert_eq!(changed.checksum2, 0x5da10e2e);
+}
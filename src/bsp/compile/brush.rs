@@ -0,0 +1,441 @@
+use super::super::{contents, tex_flags, Hull, TexInfo};
+use super::winding::{
+    base_winding, dot, scale, split, PlaneEq, Planes, Vec3, Winding, ON_EPSILON,
+};
+use crate::qmap::{Brush, Surface};
+use std::string::String;
+use std::vec::Vec;
+
+// Base axis for the texture of faces closest to facing each axis, followed
+// by the texture's S and T axes, as in id's legacy alignment
+const BASE_AXES: [[Vec3; 3]; 6] = [
+    [[0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, -1.0, 0.0]],
+    [[0.0, 0.0, -1.0], [1.0, 0.0, 0.0], [0.0, -1.0, 0.0]],
+    [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, -1.0]],
+    [[-1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, -1.0]],
+    [[0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]],
+    [[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]],
+];
+
+/// Texture names and texture info referenced by brush faces
+#[derive(Clone, Debug, Default)]
+pub(super) struct Textures {
+    pub names: Vec<String>,
+    pub tex_info: Vec<TexInfo>,
+}
+
+impl Textures {
+    // Index of the texture info for a surface, adding it if needed
+    fn tex_info(&mut self, surface: &Surface, normal: Vec3) -> usize {
+        let name = surface.texture.to_string_lossy();
+
+        let mip_texture = match self.names.iter().position(|n| *n == name) {
+            Some(idx) => idx,
+            None => {
+                self.names.push(String::from(name.as_ref()));
+                self.names.len() - 1
+            }
+        };
+
+        let alignment = &surface.alignment;
+        let axes = alignment
+            .axes
+            .unwrap_or_else(|| legacy_axes(normal, alignment.rotation));
+        let mut vecs = [[0.0f32; 4]; 2];
+
+        for (i, axis) in axes.into_iter().enumerate() {
+            let scale = if alignment.scale[i] == 0.0 {
+                1.0
+            } else {
+                alignment.scale[i]
+            };
+
+            for (j, component) in axis.into_iter().enumerate() {
+                vecs[i][j] = (component / scale) as f32;
+            }
+
+            vecs[i][3] = alignment.offset[i] as f32;
+        }
+
+        let lower_name = name.to_ascii_lowercase();
+        let flags = if name.starts_with('*') || lower_name.starts_with("sky") {
+            tex_flags::SPECIAL
+        } else {
+            0
+        };
+
+        let tex_info = TexInfo {
+            vecs,
+            mip_texture: mip_texture as u32,
+            flags,
+        };
+
+        match self.tex_info.iter().position(|t| *t == tex_info) {
+            Some(idx) => idx,
+            None => {
+                self.tex_info.push(tex_info);
+                self.tex_info.len() - 1
+            }
+        }
+    }
+}
+
+// Texture axes for the legacy alignment format, rotated in the texture plane
+fn legacy_axes(normal: Vec3, rotation: f64) -> [Vec3; 2] {
+    let mut best = 0;
+    let mut best_dot = f64::NEG_INFINITY;
+
+    for (idx, axes) in BASE_AXES.iter().enumerate() {
+        let d = dot(normal, axes[0]);
+
+        if d > best_dot {
+            best = idx;
+            best_dot = d;
+        }
+    }
+
+    let [_, mut s, mut t] = BASE_AXES[best];
+
+    let (sin, cos) = if rotation == 0.0 {
+        (0.0, 1.0)
+    } else if rotation == 90.0 {
+        (1.0, 0.0)
+    } else if rotation == 180.0 {
+        (0.0, -1.0)
+    } else if rotation == 270.0 {
+        (-1.0, 0.0)
+    } else {
+        let radians = rotation.to_radians();
+        (radians.sin(), radians.cos())
+    };
+
+    let s_axis = s.iter().position(|&c| c != 0.0).unwrap();
+    let t_axis = t.iter().position(|&c| c != 0.0).unwrap();
+
+    for axis in [&mut s, &mut t] {
+        let (a, b) = (axis[s_axis], axis[t_axis]);
+        axis[s_axis] = cos * a - sin * b;
+        axis[t_axis] = sin * a + cos * b;
+    }
+
+    [s, t]
+}
+
+/// Contents of a brush as determined by its texture
+pub(super) fn texture_contents(name: &str) -> i32 {
+    let name = name.to_ascii_lowercase();
+
+    if name.starts_with("*lava") {
+        contents::LAVA
+    } else if name.starts_with("*slime") {
+        contents::SLIME
+    } else if name.starts_with('*') {
+        contents::WATER
+    } else if name.starts_with("sky") {
+        contents::SKY
+    } else if name == "clip" {
+        contents::CLIP
+    } else if name == "origin" {
+        contents::ORIGIN
+    } else {
+        contents::SOLID
+    }
+}
+
+pub(super) fn is_liquid(contents: i32) -> bool {
+    (contents::LAVA..=contents::WATER).contains(&contents)
+}
+
+/// Side of a brush, facing out of the brush
+#[derive(Clone, Debug)]
+pub(super) struct BrushSide {
+    /// Index into `Planes`
+    pub plane: usize,
+
+    /// Texture info of visible sides, `None` for sides of clip hull brushes
+    pub tex_info: Option<usize>,
+
+    /// Part of the plane bounding the brush, `None` if the side does not
+    /// touch the brush
+    pub winding: Option<Winding>,
+}
+
+/// Convex brush with its sides cut down to polygons
+#[derive(Clone, Debug)]
+pub(super) struct MapBrush {
+    pub contents: i32,
+    pub sides: Vec<BrushSide>,
+    pub mins: Vec3,
+    pub maxs: Vec3,
+}
+
+impl MapBrush {
+    /// Load a brush from a map.  Returns `None` for brushes with degenerate
+    /// planes or no volume.
+    pub fn from_map(
+        brush: &Brush,
+        planes: &mut Planes,
+        textures: &mut Textures,
+    ) -> Option<MapBrush> {
+        let contents = brush
+            .first()
+            .map(|surface| texture_contents(&surface.texture.to_string_lossy()))
+            .unwrap_or(contents::SOLID);
+
+        let textured =
+            contents != contents::CLIP && contents != contents::ORIGIN;
+        let mut sides = Vec::with_capacity(brush.len());
+
+        for surface in brush {
+            let plane = PlaneEq::from_points(surface.half_space)?;
+            let tex_info = if textured {
+                Some(textures.tex_info(surface, plane.normal))
+            } else {
+                None
+            };
+
+            sides.push((plane, tex_info));
+        }
+
+        MapBrush::from_planes(contents, sides, planes)
+    }
+
+    fn from_planes(
+        contents: i32,
+        side_planes: Vec<(PlaneEq, Option<usize>)>,
+        planes: &mut Planes,
+    ) -> Option<MapBrush> {
+        let mut sides: Vec<BrushSide> = Vec::new();
+
+        for (plane, tex_info) in side_planes {
+            let plane = planes.find(plane);
+
+            if sides.iter().any(|side| side.plane == plane) {
+                continue;
+            }
+
+            sides.push(BrushSide {
+                plane,
+                tex_info,
+                winding: None,
+            });
+        }
+
+        let mut mins = [f64::INFINITY; 3];
+        let mut maxs = [f64::NEG_INFINITY; 3];
+
+        for idx in 0..sides.len() {
+            let mut winding = Some(base_winding(&planes.get(sides[idx].plane)));
+
+            for (other_idx, other) in sides.iter().enumerate() {
+                if other_idx == idx {
+                    continue;
+                }
+
+                winding =
+                    winding.and_then(|w| split(&w, &planes.get(other.plane)).1);
+            }
+
+            if let Some(winding) = &winding {
+                for point in winding {
+                    for axis in 0..3 {
+                        mins[axis] = mins[axis].min(point[axis]);
+                        maxs[axis] = maxs[axis].max(point[axis]);
+                    }
+                }
+            }
+
+            sides[idx].winding = winding;
+        }
+
+        if sides.iter().filter(|side| side.winding.is_some()).count() < 4 {
+            return None;
+        }
+
+        Some(MapBrush {
+            contents,
+            sides,
+            mins,
+            maxs,
+        })
+    }
+
+    /// Solid brush grown by the box of a clip hull, so that the hull can be
+    /// traced as a point.  Axial bevel planes are added so that boxes do not
+    /// catch on sharp edges.
+    pub fn expand(&self, hull: usize, planes: &mut Planes) -> Option<MapBrush> {
+        let hull_mins = Hull::clip_mins(hull).map(f64::from);
+        let hull_maxs = Hull::clip_maxs(hull).map(f64::from);
+        let mut side_planes = Vec::with_capacity(self.sides.len() + 6);
+
+        for side in &self.sides {
+            let plane = planes.get(side.plane);
+            let offset: f64 = (0..3)
+                .map(|i| {
+                    if plane.normal[i] > 0.0 {
+                        -plane.normal[i] * hull_mins[i]
+                    } else {
+                        -plane.normal[i] * hull_maxs[i]
+                    }
+                })
+                .sum();
+
+            side_planes.push((
+                PlaneEq {
+                    normal: plane.normal,
+                    dist: plane.dist + offset,
+                },
+                None,
+            ));
+        }
+
+        for axis in 0..3 {
+            let mut normal = [0.0; 3];
+            normal[axis] = 1.0;
+
+            side_planes.push((
+                PlaneEq {
+                    normal,
+                    dist: self.maxs[axis] - hull_mins[axis],
+                },
+                None,
+            ));
+
+            side_planes.push((
+                PlaneEq {
+                    normal: scale(normal, -1.0),
+                    dist: hull_maxs[axis] - self.mins[axis],
+                },
+                None,
+            ));
+        }
+
+        MapBrush::from_planes(contents::SOLID, side_planes, planes)
+    }
+
+    /// Whether a point is inside or on the surface of the brush
+    pub fn contains(&self, point: Vec3, planes: &Planes) -> bool {
+        self.sides
+            .iter()
+            .all(|side| planes.get(side.plane).distance(point) < ON_EPSILON)
+    }
+
+    // Whether the brush hides the faces of another brush it overlaps
+    fn hides(&self, other: &MapBrush) -> bool {
+        match self.contents {
+            contents::SOLID | contents::SKY => true,
+            c if is_liquid(c) => is_liquid(other.contents),
+            _ => false,
+        }
+    }
+
+    fn overlaps(&self, other: &MapBrush) -> bool {
+        (0..3).all(|axis| {
+            self.mins[axis] <= other.maxs[axis] + ON_EPSILON
+                && self.maxs[axis] >= other.mins[axis] - ON_EPSILON
+        })
+    }
+}
+
+/// Visible part of a brush side
+#[derive(Clone, Debug)]
+pub(super) struct CsgFace {
+    /// Index into `Planes`, facing the front of the face
+    pub plane: usize,
+
+    pub tex_info: Option<usize>,
+    pub winding: Winding,
+}
+
+/// Clip the sides of each brush against the brushes that hide them, leaving
+/// the faces on the surface of the union of the brushes.  Where sides of
+/// overlapping brushes coincide, the side of the later brush is kept.
+/// Liquid faces are also added facing into the liquid, so that they can be
+/// seen from both sides.
+pub(super) fn csg_faces(brushes: &[MapBrush], planes: &Planes) -> Vec<CsgFace> {
+    let mut faces = Vec::new();
+
+    for (idx, brush) in brushes.iter().enumerate() {
+        for side in &brush.sides {
+            let Some(winding) = &side.winding else {
+                continue;
+            };
+
+            let mut fragments = vec![winding.clone()];
+
+            for (other_idx, other) in brushes.iter().enumerate() {
+                if other_idx == idx
+                    || !other.hides(brush)
+                    || !other.overlaps(brush)
+                {
+                    continue;
+                }
+
+                fragments = fragments
+                    .into_iter()
+                    .flat_map(|fragment| {
+                        clip_outside(
+                            fragment,
+                            side.plane,
+                            other,
+                            other_idx > idx,
+                            planes,
+                        )
+                    })
+                    .collect();
+            }
+
+            for winding in fragments {
+                if is_liquid(brush.contents) {
+                    faces.push(CsgFace {
+                        plane: side.plane ^ 1,
+                        tex_info: side.tex_info,
+                        winding: winding.iter().rev().copied().collect(),
+                    });
+                }
+
+                faces.push(CsgFace {
+                    plane: side.plane,
+                    tex_info: side.tex_info,
+                    winding,
+                });
+            }
+        }
+    }
+
+    faces
+}
+
+// Parts of a face outside of a brush.  A face on a side of the brush facing
+// the same way is inside the brush if `overwrite` is set.
+fn clip_outside(
+    winding: Winding,
+    face_plane: usize,
+    brush: &MapBrush,
+    overwrite: bool,
+    planes: &Planes,
+) -> Vec<Winding> {
+    let mut outside = Vec::new();
+    let mut rest = winding;
+
+    for side in &brush.sides {
+        if side.plane == face_plane && !overwrite {
+            outside.push(rest);
+            return outside;
+        }
+
+        if side.plane == face_plane || side.plane == face_plane ^ 1 {
+            continue;
+        }
+
+        let (front, back) = split(&rest, &planes.get(side.plane));
+        outside.extend(front);
+
+        match back {
+            Some(back) => rest = back,
+            None => return outside,
+        }
+    }
+
+    outside
+}
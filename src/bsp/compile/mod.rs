@@ -0,0 +1,616 @@
+mod brush;
+
+mod tree;
+
+mod winding;
+
+use super::light::value_of;
+use super::{
+    contents, tex_flags, Bsp, ClipNode, ClipNodeChild, Edge, Face, Hull, Leaf,
+    Model, Node, NodeChild, Plane,
+};
+use crate::qmap::{Entity, QuakeMap};
+use crate::{lump, wad, BinParseError, BinParseResult};
+use brush::{csg_faces, is_liquid, CsgFace, MapBrush, Textures};
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::io::{Read, Seek};
+use std::mem::take;
+use std::string::{String, ToString};
+use std::vec::Vec;
+use tree::{Tree, ROOT};
+use winding::{area, dot, split, PlaneEq, Planes, Vec3, Winding};
+
+/// Largest extent of a face in texels before it is subdivided, keeping
+/// lightmaps within the limits of the engine
+const SUBDIVIDE_SIZE: f64 = 240.0;
+
+/// Faces smaller than this are dropped
+const MIN_FACE_AREA: f64 = 0.01;
+
+/// Vertex coordinates within this distance of an integer are rounded to it
+const VERTEX_SNAP: f64 = 0.01;
+
+impl Bsp {
+    /// Compiles a map into an unlit BSP29 without visibility, embedding the
+    /// textures it uses from a WAD.  The first entity is the world, and
+    /// every other entity with brushes becomes a submodel, named by a
+    /// `model` key added to its entity.
+    ///
+    /// Brushes are clipped against each other, and the faces left are split
+    /// into a BSP tree.  Brush contents are determined by texture: `*lava`,
+    /// `*slime`, other textures starting with `*` are water, `sky`, `clip`
+    /// for brushes only found in clip hulls, and `origin` for brushes which
+    /// are ignored.  The player and shambler clip hulls are built from
+    /// brushes grown by the size of their boxes.
+    ///
+    /// Space that can't be reached from any point entity with an origin is
+    /// filled in, which fails if the map leaks.  If no entities are within
+    /// the map, nothing is filled.
+    ///
+    /// This is a simple reference implementation: it is slow, and omits
+    /// optimizations such as face merging, vertex welding across T-junctions,
+    /// and non-axial bevel planes.
+    pub fn compile<R: Read + Seek>(
+        map: &QuakeMap,
+        wad: &mut wad::Parser<'_, R>,
+    ) -> BinParseResult<Bsp> {
+        if map
+            .entities
+            .first()
+            .is_none_or(|world| world.brushes.is_empty())
+        {
+            return Err(BinParseError::Parse(String::from(
+                "World has no brushes",
+            )));
+        }
+
+        let origins: Vec<(Vec3, &Entity)> = map
+            .entities
+            .iter()
+            .skip(1)
+            .filter(|entity| entity.brushes.is_empty())
+            .filter_map(|entity| Some((entity_origin(entity)?, entity)))
+            .collect();
+
+        let mut compiler = Compiler::default();
+        let mut entities = map.clone();
+
+        compiler.bsp.leaves.push(Leaf {
+            contents: contents::SOLID,
+            vis_offset: -1,
+            mins: [0.0; 3],
+            maxs: [0.0; 3],
+            first_mark_surface: 0,
+            mark_surface_count: 0,
+            ambient_levels: [0; 4],
+        });
+
+        for (idx, entity) in map.entities.iter().enumerate() {
+            entities.entities[idx].brushes.clear();
+
+            if entity.brushes.is_empty() {
+                continue;
+            }
+
+            if idx == 0 {
+                compiler.compile_model(entity, Some(&origins))?;
+                continue;
+            }
+
+            let model = format!("*{}", compiler.bsp.models.len());
+            let edict = &mut entities.entities[idx].edict;
+            edict.retain(|(key, _)| key.as_bytes() != b"model");
+            edict.push((
+                CString::new("model").unwrap(),
+                CString::new(model).unwrap(),
+            ));
+
+            compiler.compile_model(entity, None)?;
+        }
+
+        let mut bsp = take(&mut compiler.bsp);
+
+        bsp.planes = compiler
+            .planes
+            .canonical()
+            .map(|plane| Plane {
+                normal: plane.normal.map(|n| n as f32),
+                dist: plane.dist as f32,
+                kind: plane.kind(),
+            })
+            .collect();
+
+        bsp.textures = textures_lump(&compiler.textures.names, wad)?;
+        bsp.tex_info = compiler.textures.tex_info;
+
+        entities
+            .write_to(&mut bsp.entities)
+            .map_err(|e| BinParseError::Parse(e.to_string()))?;
+        bsp.entities.push(0);
+
+        Ok(bsp)
+    }
+}
+
+fn entity_origin(entity: &Entity) -> Option<Vec3> {
+    let coords = value_of(entity, "origin")?
+        .split_whitespace()
+        .map(str::parse::<f64>)
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+
+    coords.try_into().ok()
+}
+
+// Contents at a point, where the brushes containing it overlap
+fn contents_at(brushes: &[MapBrush], point: Vec3, planes: &Planes) -> i32 {
+    let rank = |contents| match contents {
+        contents::SOLID => 5,
+        contents::SKY => 4,
+        contents::LAVA => 3,
+        contents::SLIME => 2,
+        contents::WATER => 1,
+        _ => 0,
+    };
+
+    brushes
+        .iter()
+        .filter(|brush| brush.contains(point, planes))
+        .map(|brush| brush.contents)
+        .max_by_key(|&contents| rank(contents))
+        .unwrap_or(contents::EMPTY)
+}
+
+fn brush_bounds(brushes: &[MapBrush]) -> (Vec3, Vec3) {
+    if brushes.is_empty() {
+        return ([0.0; 3], [0.0; 3]);
+    }
+
+    brushes.iter().fold(
+        ([f64::INFINITY; 3], [f64::NEG_INFINITY; 3]),
+        |(mins, maxs), brush| {
+            (
+                [0, 1, 2].map(|i| mins[i].min(brush.mins[i])),
+                [0, 1, 2].map(|i| maxs[i].max(brush.maxs[i])),
+            )
+        },
+    )
+}
+
+// Round bounds outward to whole units
+fn bounds_to_f32(bounds: Option<(Vec3, Vec3)>) -> ([f32; 3], [f32; 3]) {
+    match bounds {
+        Some((mins, maxs)) => (
+            mins.map(|c| c.floor() as f32),
+            maxs.map(|c| c.ceil() as f32),
+        ),
+        None => ([0.0; 3], [0.0; 3]),
+    }
+}
+
+// Textures lump with the textures named, in order, copied from a WAD
+fn textures_lump<R: Read + Seek>(
+    names: &[String],
+    wad: &mut wad::Parser<'_, R>,
+) -> BinParseResult<Vec<u8>> {
+    if names.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let directory = wad.directory();
+    let header_len = 4 * (names.len() + 1);
+    let mut header = (names.len() as i32).to_le_bytes().to_vec();
+    let mut data = Vec::new();
+
+    for name in names {
+        let entry = directory
+            .iter()
+            .find(|(entry_name, _)| entry_name.eq_ignore_ascii_case(name))
+            .map(|(_, entry)| entry)
+            .ok_or_else(|| {
+                BinParseError::Parse(format!("Texture `{name}` not in WAD"))
+            })?;
+
+        if entry.kind() != lump::kind::MIPTEX {
+            return Err(BinParseError::Parse(format!(
+                "WAD entry `{name}` is not a texture"
+            )));
+        }
+
+        let offset = i32::try_from(header_len + data.len()).map_err(|_| {
+            BinParseError::Parse(String::from("Textures lump too large"))
+        })?;
+
+        header.extend(offset.to_le_bytes());
+        data.extend(wad.read_raw(entry)?.iter());
+        data.resize(data.len().next_multiple_of(4), 0);
+    }
+
+    header.extend(data);
+    Ok(header)
+}
+
+#[derive(Default)]
+struct Compiler {
+    planes: Planes,
+    textures: Textures,
+    bsp: Bsp,
+    vertices: HashMap<[u32; 3], u32>,
+
+    // Edges used by only one face so far, which may be shared by a face
+    // using them in the opposite direction
+    unshared_edges: HashMap<[u32; 2], u32>,
+}
+
+impl Compiler {
+    fn compile_model(
+        &mut self,
+        entity: &Entity,
+        origins: Option<&[(Vec3, &Entity)]>,
+    ) -> BinParseResult<()> {
+        let brushes: Vec<MapBrush> = entity
+            .brushes
+            .iter()
+            .filter_map(|brush| {
+                MapBrush::from_map(brush, &mut self.planes, &mut self.textures)
+            })
+            .filter(|brush| brush.contents != contents::ORIGIN)
+            .collect();
+
+        let (mins, maxs) = brush_bounds(&brushes);
+
+        let visible: Vec<MapBrush> = brushes
+            .iter()
+            .filter(|brush| brush.contents != contents::CLIP)
+            .cloned()
+            .collect();
+
+        let planes = &self.planes;
+        let faces = csg_faces(&visible, planes);
+        let mut tree = Tree::build(faces, planes, mins, maxs);
+        tree.classify(|point| contents_at(&visible, point, planes));
+
+        let origin_points: Vec<Vec3> = origins
+            .unwrap_or_default()
+            .iter()
+            .map(|(p, _)| *p)
+            .collect();
+        let mut filled = false;
+
+        if origins.is_some() {
+            filled =
+                tree.fill_outside(&origin_points, planes).map_err(|idx| {
+                    let (origin, entity) = origins.unwrap()[idx];
+                    let classname = value_of(entity, "classname").unwrap_or("");
+
+                    BinParseError::Parse(format!(
+                        "Map leaks: `{classname}` at ({} {} {}) can reach \
+                    outside the map",
+                        origin[0], origin[1], origin[2],
+                    ))
+                })?;
+        }
+
+        let first_face = self.bsp.faces.len();
+        let first_leaf = self.bsp.leaves.len();
+        let mut leaf_faces = vec![Vec::new(); tree.nodes.len()];
+
+        let head_node = match self.emit_node(&tree, ROOT, &mut leaf_faces) {
+            NodeChild::Node(idx) => idx,
+
+            // The engine expects a node at the head of hull 0
+            leaf => {
+                let plane = self.planes.find(PlaneEq {
+                    normal: [0.0, 0.0, 1.0],
+                    dist: 0.0,
+                });
+
+                self.bsp.nodes.push(Node {
+                    plane: (plane >> 1) as u32,
+                    children: [leaf, leaf],
+                    mins: [0.0; 3],
+                    maxs: [0.0; 3],
+                    first_face: first_face as u32,
+                    face_count: 0,
+                });
+
+                (self.bsp.nodes.len() - 1) as u32
+            }
+        };
+
+        let mut head_nodes = [head_node as i32, 0, 0, 0];
+
+        for hull in [Hull::PLAYER, Hull::SHAMBLER] {
+            let expanded: Vec<MapBrush> = brushes
+                .iter()
+                .filter(|brush| !is_liquid(brush.contents))
+                .filter_map(|brush| brush.expand(hull, &mut self.planes))
+                .collect();
+
+            let (hull_mins, hull_maxs) = brush_bounds(&expanded);
+            let planes = &self.planes;
+            let faces = csg_faces(&expanded, planes);
+            let mut tree = Tree::build(faces, planes, hull_mins, hull_maxs);
+
+            tree.classify(|point| {
+                if expanded.iter().any(|brush| brush.contains(point, planes)) {
+                    contents::SOLID
+                } else {
+                    contents::EMPTY
+                }
+            });
+
+            // Grown brushes seal at least as well as the originals, but
+            // entities near walls may now be within them
+            if filled {
+                let _ = tree.fill_outside(&origin_points, planes);
+            }
+
+            head_nodes[hull] = match self.emit_clip_node(&tree, ROOT) {
+                ClipNodeChild::Node(idx) => idx as i32,
+                ClipNodeChild::Contents(contents) => contents,
+            };
+        }
+
+        let (mins, maxs) = bounds_to_f32(Some((mins, maxs)));
+
+        self.bsp.models.push(Model {
+            mins,
+            maxs,
+            origin: [0.0; 3],
+            head_nodes,
+            vis_leaf_count: (self.bsp.leaves.len() - first_leaf) as u32,
+            first_face: first_face as u32,
+            face_count: (self.bsp.faces.len() - first_face) as u32,
+        });
+
+        Ok(())
+    }
+
+    // Write a node and its descendants, along with their faces.  Solid leaves
+    // all refer to leaf 0.
+    fn emit_node(
+        &mut self,
+        tree: &Tree,
+        node: usize,
+        leaf_faces: &mut [Vec<u32>],
+    ) -> NodeChild {
+        let tree_node = &tree.nodes[node];
+        let (mins, maxs) = bounds_to_f32(tree.bounds(node));
+
+        let Some(plane) = tree_node.plane else {
+            if tree_node.contents == contents::SOLID {
+                return NodeChild::Leaf(0);
+            }
+
+            let marks = take(&mut leaf_faces[node]);
+
+            self.bsp.leaves.push(Leaf {
+                contents: tree_node.contents,
+                vis_offset: -1,
+                mins,
+                maxs,
+                first_mark_surface: self.bsp.mark_surfaces.len() as u32,
+                mark_surface_count: marks.len() as u32,
+                ambient_levels: [0; 4],
+            });
+
+            self.bsp.mark_surfaces.extend(marks);
+            return NodeChild::Leaf((self.bsp.leaves.len() - 1) as u32);
+        };
+
+        let idx = self.bsp.nodes.len();
+        let first_face = self.bsp.faces.len();
+
+        self.bsp.nodes.push(Node {
+            plane: (plane >> 1) as u32,
+            children: [NodeChild::Leaf(0); 2],
+            mins,
+            maxs,
+            first_face: first_face as u32,
+            face_count: 0,
+        });
+
+        for leaf_face in tree.visible_faces(node, &self.planes) {
+            for winding in self.subdivide(&leaf_face.face) {
+                let face = CsgFace {
+                    winding,
+                    ..leaf_face.face.clone()
+                };
+
+                if let Some(face_idx) = self.emit_face(&face) {
+                    leaf_faces[leaf_face.leaf].push(face_idx);
+                }
+            }
+        }
+
+        let face_count = self.bsp.faces.len() - first_face;
+        let children = tree_node
+            .children
+            .map(|c| self.emit_node(tree, c, leaf_faces));
+
+        let out = &mut self.bsp.nodes[idx];
+        out.children = children;
+        out.face_count = face_count as u32;
+
+        NodeChild::Node(idx as u32)
+    }
+
+    // Split a face along its texture axes until its extents fit a lightmap.
+    // Special textures have no lightmaps and are left whole.
+    fn subdivide(&self, face: &CsgFace) -> Vec<Winding> {
+        let tex_info = &self.textures.tex_info[face.tex_info.unwrap()];
+
+        if tex_info.flags & tex_flags::SPECIAL != 0 {
+            return vec![face.winding.clone()];
+        }
+
+        let mut pending = vec![face.winding.clone()];
+
+        for vec in tex_info.vecs {
+            let axis = [vec[0], vec[1], vec[2]].map(f64::from);
+            let length = dot(axis, axis).sqrt();
+            let normal = axis.map(|c| c / length);
+            let mut done = Vec::new();
+
+            for mut winding in pending {
+                loop {
+                    let coords = winding.iter().map(|&p| dot(p, axis));
+                    let min = coords.clone().fold(f64::INFINITY, f64::min);
+                    let max = coords.fold(f64::NEG_INFINITY, f64::max);
+
+                    if max - min <= SUBDIVIDE_SIZE {
+                        done.push(winding);
+                        break;
+                    }
+
+                    let plane = PlaneEq {
+                        normal,
+                        dist: (min + SUBDIVIDE_SIZE - 16.0) / length,
+                    };
+
+                    match split(&winding, &plane) {
+                        (Some(front), Some(back)) => {
+                            done.push(back);
+                            winding = front;
+                        }
+                        (Some(whole), None) | (None, Some(whole)) => {
+                            done.push(whole);
+                            break;
+                        }
+                        (None, None) => break,
+                    }
+                }
+            }
+
+            pending = done;
+        }
+
+        pending
+    }
+
+    fn emit_face(&mut self, face: &CsgFace) -> Option<u32> {
+        if area(&face.winding) < MIN_FACE_AREA {
+            return None;
+        }
+
+        let mut vertices: Vec<u32> = Vec::with_capacity(face.winding.len());
+
+        for &point in &face.winding {
+            let vertex = self.vertex(point);
+
+            if vertices.last() != Some(&vertex) {
+                vertices.push(vertex);
+            }
+        }
+
+        if vertices.len() > 1 && vertices.first() == vertices.last() {
+            vertices.pop();
+        }
+
+        if vertices.len() < 3 {
+            return None;
+        }
+
+        let first_edge = self.bsp.surf_edges.len();
+
+        for (idx, &start) in vertices.iter().enumerate() {
+            let end = vertices[(idx + 1) % vertices.len()];
+            let surf_edge = self.edge(start, end);
+            self.bsp.surf_edges.push(surf_edge);
+        }
+
+        self.bsp.faces.push(Face {
+            plane: (face.plane >> 1) as u32,
+            side: (face.plane & 1) as u32,
+            first_edge: first_edge as u32,
+            edge_count: vertices.len() as u32,
+            tex_info: face.tex_info.unwrap() as u32,
+            styles: [255; 4],
+            light_offset: -1,
+        });
+
+        Some((self.bsp.faces.len() - 1) as u32)
+    }
+
+    fn vertex(&mut self, point: Vec3) -> u32 {
+        let point = point.map(|c| {
+            let rounded = c.round();
+
+            if (c - rounded).abs() < VERTEX_SNAP {
+                rounded as f32
+            } else {
+                c as f32
+            }
+        });
+
+        let vertices = &mut self.bsp.vertices;
+
+        *self
+            .vertices
+            .entry(point.map(f32::to_bits))
+            .or_insert_with(|| {
+                vertices.push(point);
+                (vertices.len() - 1) as u32
+            })
+    }
+
+    // Surface edge from one vertex to another, reusing an edge in the
+    // opposite direction if one is not yet shared
+    fn edge(&mut self, start: u32, end: u32) -> i32 {
+        if let Some(edge) = self.unshared_edges.remove(&[end, start]) {
+            return -(edge as i32);
+        }
+
+        // Edge 0 can't be referred to in reverse, so it is left unused
+        if self.bsp.edges.is_empty() {
+            self.bsp.edges.push(Edge { vertices: [0, 0] });
+        }
+
+        let edge = self.bsp.edges.len() as u32;
+        self.bsp.edges.push(Edge {
+            vertices: [start, end],
+        });
+        self.unshared_edges.insert([start, end], edge);
+
+        edge as i32
+    }
+
+    // Write a clip hull node and its descendants, collapsing nodes whose
+    // children have the same contents
+    fn emit_clip_node(&mut self, tree: &Tree, node: usize) -> ClipNodeChild {
+        let tree_node = &tree.nodes[node];
+
+        let Some(plane) = tree_node.plane else {
+            return ClipNodeChild::Contents(
+                if tree_node.contents == contents::SOLID {
+                    contents::SOLID
+                } else {
+                    contents::EMPTY
+                },
+            );
+        };
+
+        let idx = self.bsp.clip_nodes.len();
+
+        self.bsp.clip_nodes.push(ClipNode {
+            plane: (plane >> 1) as u32,
+            children: [ClipNodeChild::Contents(contents::EMPTY); 2],
+        });
+
+        let children = tree_node.children.map(|c| self.emit_clip_node(tree, c));
+
+        if let [ClipNodeChild::Contents(front), ClipNodeChild::Contents(back)] =
+            children
+        {
+            if front == back {
+                self.bsp.clip_nodes.pop();
+                return children[0];
+            }
+        }
+
+        self.bsp.clip_nodes[idx].children = children;
+        ClipNodeChild::Node(idx as u32)
+    }
+}
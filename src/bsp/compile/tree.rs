@@ -0,0 +1,473 @@
+use super::super::contents;
+use super::brush::CsgFace;
+use super::winding::{
+    base_winding, center, split, PlaneEq, Planes, Vec3, Winding, ON_EPSILON,
+};
+use std::mem::take;
+use std::vec::Vec;
+
+/// Space left between the brushes and the box bounding the outermost leaves
+const SIDESPACE: f64 = 24.0;
+
+/// Index of the root node of a tree
+pub(super) const ROOT: usize = 0;
+
+/// Node or leaf of a tree under construction
+#[derive(Clone, Debug)]
+pub(super) struct TreeNode {
+    /// Even index into `Planes` for nodes, `None` for leaves
+    pub plane: Option<usize>,
+
+    /// Front and back children of nodes
+    pub children: [usize; 2],
+
+    /// Faces lying on the plane of a node
+    pub faces: Vec<CsgFace>,
+
+    /// Contents of a leaf
+    pub contents: i32,
+
+    portals: Vec<usize>,
+}
+
+impl TreeNode {
+    fn leaf() -> TreeNode {
+        TreeNode {
+            plane: None,
+            children: [0; 2],
+            faces: Vec::new(),
+            contents: contents::EMPTY,
+            portals: Vec::new(),
+        }
+    }
+}
+
+// Opening between two leaves or nodes, in front of the plane for the first
+#[derive(Clone, Debug)]
+struct Portal {
+    plane: PlaneEq,
+    nodes: [usize; 2],
+    winding: Winding,
+}
+
+/// Face fragment seen from a leaf
+#[derive(Clone, Debug)]
+pub(super) struct LeafFace {
+    pub leaf: usize,
+    pub face: CsgFace,
+}
+
+/// BSP tree partitioning the space around a set of brushes, with portals
+/// connecting its leaves
+#[derive(Clone, Debug)]
+pub(super) struct Tree {
+    pub nodes: Vec<TreeNode>,
+    portals: Vec<Option<Portal>>,
+
+    // Leaf standing in for everything outside of the bounding box
+    outside: usize,
+}
+
+impl Tree {
+    /// Partition space with the planes of the faces until no faces remain
+    /// within a leaf, then connect the leaves within the box from `mins` to
+    /// `maxs` with portals.  Leaves are empty until classified.
+    pub fn build(
+        faces: Vec<CsgFace>,
+        planes: &Planes,
+        mins: Vec3,
+        maxs: Vec3,
+    ) -> Tree {
+        let mut tree = Tree {
+            nodes: Vec::new(),
+            portals: Vec::new(),
+            outside: 0,
+        };
+
+        tree.build_node(faces, planes);
+        tree.outside = tree.nodes.len();
+        tree.nodes.push(TreeNode::leaf());
+
+        let mut box_planes = Vec::with_capacity(6);
+
+        for axis in 0..3 {
+            let mut normal = [0.0; 3];
+            normal[axis] = 1.0;
+
+            box_planes.push(PlaneEq {
+                normal,
+                dist: mins[axis] - SIDESPACE,
+            });
+
+            normal[axis] = -1.0;
+
+            box_planes.push(PlaneEq {
+                normal,
+                dist: -(maxs[axis] + SIDESPACE),
+            });
+        }
+
+        for plane in &box_planes {
+            let mut winding = Some(base_winding(plane));
+
+            for other in &box_planes {
+                if other != plane {
+                    winding = winding.and_then(|w| split(&w, other).0);
+                }
+            }
+
+            if let Some(winding) = winding {
+                tree.add_portal(Portal {
+                    plane: *plane,
+                    nodes: [ROOT, tree.outside],
+                    winding,
+                });
+            }
+        }
+
+        tree.portalize(ROOT, planes);
+        tree
+    }
+
+    fn build_node(&mut self, faces: Vec<CsgFace>, planes: &Planes) -> usize {
+        let idx = self.nodes.len();
+        self.nodes.push(TreeNode::leaf());
+
+        if faces.is_empty() {
+            return idx;
+        }
+
+        let plane_idx = choose_plane(&faces, planes);
+        let plane = planes.get(plane_idx);
+        let mut on_plane = Vec::new();
+        let mut sides = [Vec::new(), Vec::new()];
+
+        for face in faces {
+            if face.plane & !1 == plane_idx {
+                on_plane.push(face);
+                continue;
+            }
+
+            let (front, back) = split(&face.winding, &plane);
+
+            for (side, winding) in [front, back].into_iter().enumerate() {
+                if let Some(winding) = winding {
+                    sides[side].push(CsgFace {
+                        winding,
+                        ..face.clone()
+                    });
+                }
+            }
+        }
+
+        let [front, back] = sides;
+        let children = [
+            self.build_node(front, planes),
+            self.build_node(back, planes),
+        ];
+
+        self.nodes[idx] = TreeNode {
+            plane: Some(plane_idx),
+            children,
+            faces: on_plane,
+            ..TreeNode::leaf()
+        };
+
+        idx
+    }
+
+    fn add_portal(&mut self, portal: Portal) {
+        let idx = self.portals.len();
+
+        for node in portal.nodes {
+            self.nodes[node].portals.push(idx);
+        }
+
+        self.portals.push(Some(portal));
+    }
+
+    // Create a portal on the plane of each node, and divide the portals
+    // bounding each node between its children
+    fn portalize(&mut self, node: usize, planes: &Planes) {
+        let Some(plane_idx) = self.nodes[node].plane else {
+            return;
+        };
+
+        let plane = planes.get(plane_idx);
+        let [front, back] = self.nodes[node].children;
+        let bounding = take(&mut self.nodes[node].portals);
+        let mut winding = Some(base_winding(&plane));
+
+        for &idx in &bounding {
+            let portal = self.portals[idx].as_ref().unwrap();
+            let facing_node = if portal.nodes[0] == node {
+                portal.plane
+            } else {
+                portal.plane.flipped()
+            };
+
+            winding = winding.and_then(|w| split(&w, &facing_node).0);
+        }
+
+        if let Some(winding) = winding {
+            self.add_portal(Portal {
+                plane,
+                nodes: [front, back],
+                winding,
+            });
+        }
+
+        for idx in bounding {
+            let portal = self.portals[idx].take().unwrap();
+            let side = usize::from(portal.nodes[1] == node);
+            let other = portal.nodes[1 - side];
+            self.nodes[other].portals.retain(|&p| p != idx);
+
+            let (front_part, back_part) = split(&portal.winding, &plane);
+
+            for (part, child) in [(front_part, front), (back_part, back)] {
+                if let Some(winding) = part {
+                    let mut nodes = portal.nodes;
+                    nodes[side] = child;
+
+                    self.add_portal(Portal {
+                        plane: portal.plane,
+                        nodes,
+                        winding,
+                    });
+                }
+            }
+        }
+
+        self.portalize(front, planes);
+        self.portalize(back, planes);
+    }
+
+    fn portals_of(&self, node: usize) -> impl Iterator<Item = &Portal> {
+        self.nodes[node]
+            .portals
+            .iter()
+            .filter_map(|&idx| self.portals[idx].as_ref())
+    }
+
+    /// Set the contents of each leaf to the contents at its center.  Leaves
+    /// without portals have no volume and are solid.
+    pub fn classify(&mut self, contents_at: impl Fn(Vec3) -> i32) {
+        for idx in 0..self.nodes.len() {
+            if self.nodes[idx].plane.is_some() || idx == self.outside {
+                continue;
+            }
+
+            let points: Vec<Vec3> = self
+                .portals_of(idx)
+                .flat_map(|portal| portal.winding.iter().copied())
+                .collect();
+
+            self.nodes[idx].contents = if points.is_empty() {
+                contents::SOLID
+            } else {
+                contents_at(center(&points))
+            };
+        }
+    }
+
+    /// Leaf containing a point
+    pub fn leaf_at(&self, point: Vec3, planes: &Planes) -> usize {
+        let mut idx = ROOT;
+
+        while let Some(plane) = self.nodes[idx].plane {
+            let side = usize::from(planes.get(plane).distance(point) < 0.0);
+            idx = self.nodes[idx].children[side];
+        }
+
+        idx
+    }
+
+    /// Flood the open leaves from each of `origins` and fill the leaves that
+    /// can't be reached with solid.  Returns whether any leaves were
+    /// flooded, or the index of the first origin from which the outside of
+    /// the map could be reached, in which case nothing is filled.
+    pub fn fill_outside(
+        &mut self,
+        origins: &[Vec3],
+        planes: &Planes,
+    ) -> Result<bool, usize> {
+        let mut reached = vec![false; self.nodes.len()];
+        let mut flooded = false;
+
+        for (origin_idx, &origin) in origins.iter().enumerate() {
+            let start = self.leaf_at(origin, planes);
+
+            if !is_open(self.nodes[start].contents) || reached[start] {
+                continue;
+            }
+
+            flooded = true;
+            reached[start] = true;
+            let mut queue = vec![start];
+
+            while let Some(leaf) = queue.pop() {
+                for portal in self.portals_of(leaf) {
+                    let next =
+                        portal.nodes[usize::from(portal.nodes[0] == leaf)];
+
+                    if next == self.outside {
+                        return Err(origin_idx);
+                    }
+
+                    if is_open(self.nodes[next].contents) && !reached[next] {
+                        reached[next] = true;
+                        queue.push(next);
+                    }
+                }
+            }
+        }
+
+        if !flooded {
+            return Ok(false);
+        }
+
+        for (idx, node) in self.nodes.iter_mut().enumerate() {
+            if node.plane.is_none() && !reached[idx] {
+                node.contents = contents::SOLID;
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Parts of the faces of a node visible from open leaves, found by
+    /// pushing each face down the side of the tree it faces
+    pub fn visible_faces(&self, node: usize, planes: &Planes) -> Vec<LeafFace> {
+        let mut visible = Vec::new();
+
+        for face in &self.nodes[node].faces {
+            let child = self.nodes[node].children[face.plane & 1];
+            self.push_face(child, face.clone(), planes, &mut visible);
+        }
+
+        visible
+    }
+
+    fn push_face(
+        &self,
+        node: usize,
+        face: CsgFace,
+        planes: &Planes,
+        visible: &mut Vec<LeafFace>,
+    ) {
+        let tree_node = &self.nodes[node];
+
+        let Some(plane) = tree_node.plane else {
+            if is_open(tree_node.contents) {
+                visible.push(LeafFace { leaf: node, face });
+            }
+
+            return;
+        };
+
+        let (front, back) = split(&face.winding, &planes.get(plane));
+
+        for (side, winding) in [front, back].into_iter().enumerate() {
+            if let Some(winding) = winding {
+                let fragment = CsgFace {
+                    winding,
+                    ..face.clone()
+                };
+
+                self.push_face(
+                    tree_node.children[side],
+                    fragment,
+                    planes,
+                    visible,
+                );
+            }
+        }
+    }
+
+    /// Bounds of the portals of the leaves under a node, or `None` if there
+    /// are none
+    pub fn bounds(&self, node: usize) -> Option<(Vec3, Vec3)> {
+        let tree_node = &self.nodes[node];
+
+        if tree_node.plane.is_some() {
+            let [front, back] = tree_node.children.map(|c| self.bounds(c));
+
+            return match (front, back) {
+                (Some((mins0, maxs0)), Some((mins1, maxs1))) => Some((
+                    [0, 1, 2].map(|i| mins0[i].min(mins1[i])),
+                    [0, 1, 2].map(|i| maxs0[i].max(maxs1[i])),
+                )),
+                (bounds, None) | (None, bounds) => bounds,
+            };
+        }
+
+        let mut points = self
+            .portals_of(node)
+            .flat_map(|portal| portal.winding.iter().copied())
+            .peekable();
+
+        points.peek()?;
+
+        Some(points.fold(
+            ([f64::INFINITY; 3], [f64::NEG_INFINITY; 3]),
+            |(mins, maxs), point| {
+                (
+                    [0, 1, 2].map(|i| mins[i].min(point[i])),
+                    [0, 1, 2].map(|i| maxs[i].max(point[i])),
+                )
+            },
+        ))
+    }
+}
+
+/// Whether the flood fill and faces can reach into a leaf
+pub(super) fn is_open(contents: i32) -> bool {
+    contents != contents::SOLID && contents != contents::SKY
+}
+
+// Pick the plane of one of the faces to split them with, preferring axial
+// planes, and among those the plane splitting the fewest faces while
+// dividing them evenly
+fn choose_plane(faces: &[CsgFace], planes: &Planes) -> usize {
+    let mut candidates: Vec<usize> =
+        faces.iter().map(|face| face.plane & !1).collect();
+    candidates.sort_unstable();
+    candidates.dedup();
+
+    if candidates.iter().any(|&idx| planes.get(idx).is_axial()) {
+        candidates.retain(|&idx| planes.get(idx).is_axial());
+    }
+
+    let score = |plane_idx: usize| {
+        let plane = planes.get(plane_idx);
+        let (mut splits, mut front, mut back) = (0i64, 0i64, 0i64);
+
+        for face in faces {
+            if face.plane & !1 == plane_idx {
+                continue;
+            }
+
+            let dists = face.winding.iter().map(|&p| plane.distance(p));
+            let (mut in_front, mut behind) = (false, false);
+
+            for dist in dists {
+                in_front |= dist > ON_EPSILON;
+                behind |= dist < -ON_EPSILON;
+            }
+
+            match (in_front, behind) {
+                (true, true) => splits += 1,
+                (false, true) => back += 1,
+                _ => front += 1,
+            }
+        }
+
+        splits * 5 + (front - back).abs()
+    };
+
+    candidates
+        .into_iter()
+        .min_by_key(|&idx| score(idx))
+        .unwrap()
+}
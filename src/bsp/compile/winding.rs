@@ -0,0 +1,295 @@
+use super::super::PlaneKind;
+use std::vec::Vec;
+
+pub(super) type Vec3 = [f64; 3];
+
+/// Convex polygon, wound clockwise when viewed from the front
+pub(super) type Winding = Vec<Vec3>;
+
+/// Distance within which points are considered to lie on a plane
+pub(super) const ON_EPSILON: f64 = 0.05;
+
+/// Half-extent of the winding created for an unbounded plane, larger than any
+/// map
+const BASE_WINDING_RANGE: f64 = 65536.0;
+
+const NORMAL_EPSILON: f64 = 0.00001;
+const DIST_EPSILON: f64 = 0.01;
+
+/// Plane equation.  Points `p` on the plane satisfy `dot(normal, p) == dist`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) struct PlaneEq {
+    pub normal: Vec3,
+    pub dist: f64,
+}
+
+impl PlaneEq {
+    /// Plane through 3 points, facing the direction from which the points
+    /// appear clockwise, as for brush faces in map files
+    pub fn from_points([p0, p1, p2]: [Vec3; 3]) -> Option<PlaneEq> {
+        let normal = normalize(cross(sub(p0, p1), sub(p2, p1)))?;
+
+        Some(PlaneEq {
+            normal,
+            dist: dot(p1, normal),
+        })
+    }
+
+    pub fn distance(&self, point: Vec3) -> f64 {
+        dot(self.normal, point) - self.dist
+    }
+
+    pub fn flipped(&self) -> PlaneEq {
+        PlaneEq {
+            normal: self.normal.map(|n| -n),
+            dist: -self.dist,
+        }
+    }
+
+    pub fn kind(&self) -> PlaneKind {
+        let abs = self.normal.map(f64::abs);
+
+        if abs[0] == 1.0 {
+            PlaneKind::X
+        } else if abs[1] == 1.0 {
+            PlaneKind::Y
+        } else if abs[2] == 1.0 {
+            PlaneKind::Z
+        } else if abs[0] >= abs[1] && abs[0] >= abs[2] {
+            PlaneKind::AnyX
+        } else if abs[1] >= abs[2] {
+            PlaneKind::AnyY
+        } else {
+            PlaneKind::AnyZ
+        }
+    }
+
+    pub fn is_axial(&self) -> bool {
+        matches!(self.kind(), PlaneKind::X | PlaneKind::Y | PlaneKind::Z)
+    }
+}
+
+/// Deduplicated planes, stored in pairs facing opposite directions so that
+/// the index of a plane XOR 1 is the index of its flipped counterpart.  Even
+/// indices are the orientations written to the planes lump: axial planes face
+/// along the positive axis, and other planes have a positive major component.
+#[derive(Clone, Debug, Default)]
+pub(super) struct Planes {
+    planes: Vec<PlaneEq>,
+}
+
+impl Planes {
+    /// Index of a plane, adding it if there is no close match
+    pub fn find(&mut self, plane: PlaneEq) -> usize {
+        let plane = snap_plane(plane);
+
+        for (idx, existing) in self.planes.iter().enumerate() {
+            let normal_matches = (0..3).all(|i| {
+                (existing.normal[i] - plane.normal[i]).abs() < NORMAL_EPSILON
+            });
+
+            if normal_matches
+                && (existing.dist - plane.dist).abs() < DIST_EPSILON
+            {
+                return idx;
+            }
+        }
+
+        let major = (0..3)
+            .max_by(|&a, &b| {
+                plane.normal[a].abs().total_cmp(&plane.normal[b].abs())
+            })
+            .unwrap();
+
+        let canonical = if plane.normal[major] < 0.0 {
+            plane.flipped()
+        } else {
+            plane
+        };
+
+        let idx = self.planes.len();
+        self.planes.push(canonical);
+        self.planes.push(canonical.flipped());
+
+        if canonical == plane {
+            idx
+        } else {
+            idx + 1
+        }
+    }
+
+    pub fn get(&self, idx: usize) -> PlaneEq {
+        self.planes[idx]
+    }
+
+    /// Planes facing in their canonical directions, in order of their
+    /// indices divided by 2
+    pub fn canonical(&self) -> impl Iterator<Item = &PlaneEq> {
+        self.planes.iter().step_by(2)
+    }
+}
+
+// Round nearly-axial normals and nearly-integral distances to reduce drift
+fn snap_plane(plane: PlaneEq) -> PlaneEq {
+    let mut normal = plane.normal;
+
+    for axis in 0..3 {
+        if (normal[axis].abs() - 1.0).abs() < NORMAL_EPSILON {
+            normal = [0.0; 3];
+            normal[axis] = plane.normal[axis].signum();
+            break;
+        }
+    }
+
+    let rounded = plane.dist.round();
+    let dist = if (plane.dist - rounded).abs() < DIST_EPSILON {
+        rounded
+    } else {
+        plane.dist
+    };
+
+    PlaneEq { normal, dist }
+}
+
+/// Large square on a plane, used as the starting point for clipping
+pub(super) fn base_winding(plane: &PlaneEq) -> Winding {
+    let abs = plane.normal.map(f64::abs);
+
+    let up = if abs[2] > abs[0] && abs[2] > abs[1] {
+        [1.0, 0.0, 0.0]
+    } else {
+        [0.0, 0.0, 1.0]
+    };
+
+    let up = sub(up, scale(plane.normal, dot(up, plane.normal)));
+    let up = scale(normalize(up).unwrap(), BASE_WINDING_RANGE);
+    let right = cross(up, plane.normal);
+    let origin = scale(plane.normal, plane.dist);
+
+    vec![
+        add(sub(origin, right), up),
+        add(add(origin, right), up),
+        sub(add(origin, right), up),
+        sub(sub(origin, right), up),
+    ]
+}
+
+/// Split a winding into the parts in front of and behind a plane.  Points
+/// within `ON_EPSILON` of the plane belong to both sides, and a winding lying
+/// on the plane is considered in front.
+pub(super) fn split(
+    winding: &[Vec3],
+    plane: &PlaneEq,
+) -> (Option<Winding>, Option<Winding>) {
+    let dists: Vec<f64> =
+        winding.iter().map(|&point| plane.distance(point)).collect();
+
+    if dists.iter().all(|&dist| dist > -ON_EPSILON) {
+        return (Some(winding.to_vec()), None);
+    }
+
+    if dists.iter().all(|&dist| dist < ON_EPSILON) {
+        return (None, Some(winding.to_vec()));
+    }
+
+    let mut front = Vec::new();
+    let mut back = Vec::new();
+
+    for (idx, &point) in winding.iter().enumerate() {
+        let dist = dists[idx];
+
+        if dist.abs() <= ON_EPSILON {
+            front.push(point);
+            back.push(point);
+            continue;
+        }
+
+        if dist > 0.0 {
+            front.push(point);
+        } else {
+            back.push(point);
+        }
+
+        let next_idx = (idx + 1) % winding.len();
+        let next_dist = dists[next_idx];
+
+        if next_dist.abs() <= ON_EPSILON || (next_dist > 0.0) == (dist > 0.0) {
+            continue;
+        }
+
+        let next = winding[next_idx];
+        let t = dist / (dist - next_dist);
+        let mut mid = [0.0; 3];
+
+        for axis in 0..3 {
+            // Keep axial splits exact
+            mid[axis] = if plane.normal[axis] == 1.0 {
+                plane.dist
+            } else if plane.normal[axis] == -1.0 {
+                -plane.dist
+            } else {
+                point[axis] + t * (next[axis] - point[axis])
+            };
+        }
+
+        front.push(mid);
+        back.push(mid);
+    }
+
+    let valid = |w: Winding| if w.len() >= 3 { Some(w) } else { None };
+    (valid(front), valid(back))
+}
+
+/// Average of the points of a winding
+pub(super) fn center(points: &[Vec3]) -> Vec3 {
+    let sum = points.iter().fold([0.0; 3], |sum, &point| add(sum, point));
+    scale(sum, 1.0 / points.len() as f64)
+}
+
+pub(super) fn area(winding: &[Vec3]) -> f64 {
+    let mut total = [0.0; 3];
+
+    for idx in 2..winding.len() {
+        let edge_cross = cross(
+            sub(winding[idx - 1], winding[0]),
+            sub(winding[idx], winding[0]),
+        );
+        total = add(total, edge_cross);
+    }
+
+    dot(total, total).sqrt() / 2.0
+}
+
+pub(super) fn add(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub(super) fn sub(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub(super) fn scale(a: Vec3, s: f64) -> Vec3 {
+    a.map(|c| c * s)
+}
+
+pub(super) fn dot(a: Vec3, b: Vec3) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(a: Vec3) -> Option<Vec3> {
+    let length = dot(a, a).sqrt();
+
+    if length < NORMAL_EPSILON {
+        None
+    } else {
+        Some(scale(a, 1.0 / length))
+    }
+}
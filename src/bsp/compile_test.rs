@@ -0,0 +1,396 @@
+use crate::{bsp, lump, qmap, wad, BinParseError};
+use bsp::{contents, tex_flags, Bsp, Hull};
+use std::io::Cursor;
+use std::string::String;
+use std::vec::Vec;
+
+fn miptex_bytes(name: &str, size: u32) -> Vec<u8> {
+    let mut miptex = Vec::new();
+    let mut name_field = [0u8; 16];
+    name_field[..name.len()].copy_from_slice(name.as_bytes());
+
+    miptex.extend(name_field);
+    miptex.extend(size.to_le_bytes());
+    miptex.extend(size.to_le_bytes());
+
+    let mut offset = 40u32;
+
+    for mip in 0..4 {
+        miptex.extend(offset.to_le_bytes());
+        offset += (size >> mip) * (size >> mip);
+    }
+
+    miptex.resize(offset as usize, 7);
+    miptex
+}
+
+fn wad_bytes() -> Vec<u8> {
+    let names = ["WALL", "*water", "sky1", "floor"];
+    let lumps: Vec<Vec<u8>> =
+        names.iter().map(|name| miptex_bytes(name, 16)).collect();
+    let mut wad = Vec::new();
+    let mut entries = Vec::new();
+
+    wad.extend(b"WAD2");
+    wad.extend((names.len() as u32).to_le_bytes());
+    wad.extend([0; 4]);
+
+    for (name, lump) in names.iter().zip(&lumps) {
+        let mut name_field = [0u8; 16];
+        name_field[..name.len()].copy_from_slice(name.as_bytes());
+
+        entries.extend((wad.len() as u32).to_le_bytes());
+        entries.extend((lump.len() as u32).to_le_bytes());
+        entries.extend((lump.len() as u32).to_le_bytes());
+        entries.push(lump::kind::MIPTEX);
+        entries.extend([0; 3]);
+        entries.extend(name_field);
+
+        wad.extend(lump);
+    }
+
+    let directory_offset = wad.len() as u32;
+    wad[8..12].copy_from_slice(&directory_offset.to_le_bytes());
+    wad.extend(entries);
+    wad
+}
+
+// Axis-aligned box brush with the given texture on every side
+fn box_brush(mins: [i32; 3], maxs: [i32; 3], texture: &str) -> String {
+    let [x1, y1, z1] = mins;
+    let [x2, y2, z2] = maxs;
+
+    let half_spaces = [
+        [[x1, y1, z1], [x1, y1 + 1, z1], [x1, y1, z1 + 1]],
+        [[x2, y1, z1], [x2, y1, z1 + 1], [x2, y1 + 1, z1]],
+        [[x1, y1, z1], [x1, y1, z1 + 1], [x1 + 1, y1, z1]],
+        [[x1, y2, z1], [x1 + 1, y2, z1], [x1, y2, z1 + 1]],
+        [[x1, y1, z1], [x1 + 1, y1, z1], [x1, y1 + 1, z1]],
+        [[x1, y1, z2], [x1, y1 + 1, z2], [x1 + 1, y1, z2]],
+    ];
+
+    let mut text = String::from("{\n");
+
+    for points in half_spaces {
+        for [x, y, z] in points {
+            text.push_str(&format!("( {x} {y} {z} ) "));
+        }
+
+        text.push_str(&format!("{texture} 0 0 0 1 1\n"));
+    }
+
+    text.push_str("}\n");
+    text
+}
+
+// Sealed room with walls 16 units thick around the interior
+fn room_brushes(size: i32, height: i32) -> Vec<String> {
+    let (s, t) = (size, size + 16);
+
+    vec![
+        box_brush([-t, -t, -16], [t, t, 0], "wall"),
+        box_brush([-t, -t, height], [t, t, height + 16], "wall"),
+        box_brush([-t, -t, 0], [-s, t, height], "wall"),
+        box_brush([s, -t, 0], [t, t, height], "wall"),
+        box_brush([-s, -t, 0], [s, -s, height], "wall"),
+        box_brush([-s, s, 0], [s, t, height], "wall"),
+    ]
+}
+
+fn map(world_brushes: &[String], entities: &str) -> qmap::QuakeMap {
+    let text = format!(
+        "{{\n\"classname\" \"worldspawn\"\n{}}}\n{entities}",
+        world_brushes.concat()
+    );
+
+    qmap::parse(&mut text.as_bytes()).unwrap()
+}
+
+const PLAYER_START: &str =
+    "{\n\"classname\" \"info_player_start\"\n\"origin\" \"0 0 24\"\n}\n";
+
+fn compile(map: &qmap::QuakeMap) -> Result<Bsp, BinParseError> {
+    let mut cursor = Cursor::new(wad_bytes());
+    let (mut wad, _) = wad::Parser::new(&mut cursor).unwrap();
+    Bsp::compile(map, &mut wad)
+}
+
+fn room() -> Bsp {
+    compile(&map(&room_brushes(64, 128), PLAYER_START)).unwrap()
+}
+
+#[test]
+fn compile_room() {
+    let bsp = room();
+
+    assert_eq!(bsp.version, bsp::BSP_VERSION);
+    assert_eq!(bsp.models.len(), 1);
+    assert_eq!(bsp.faces.len(), 6);
+    assert_eq!(bsp.models[0].mins, [-80.0, -80.0, -16.0]);
+    assert_eq!(bsp.models[0].maxs, [80.0, 80.0, 144.0]);
+    assert_eq!(bsp.models[0].vis_leaf_count as usize, bsp.leaves.len() - 1);
+    assert_eq!(bsp.leaves[0].contents, contents::SOLID);
+    assert!(bsp.leaves[1..]
+        .iter()
+        .all(|leaf| leaf.contents == contents::EMPTY && leaf.vis_offset == -1));
+
+    // Every face looks into the room
+    for polygon in bsp.polygons() {
+        let polygon = polygon.unwrap();
+        let sign = if polygon.back { -1.0 } else { 1.0 };
+        let normal = polygon.plane.normal.map(|n| n * sign);
+        let count = polygon.points.len() as f32;
+        let center = [0, 1, 2]
+            .map(|i| polygon.points.iter().map(|p| p[i]).sum::<f32>() / count);
+
+        let front = [0, 1, 2].map(|i| center[i] + normal[i]);
+        let back = [0, 1, 2].map(|i| center[i] - normal[i]);
+
+        assert_eq!(
+            bsp.point_contents(Hull::POINT, front).unwrap(),
+            contents::EMPTY
+        );
+        assert_eq!(
+            bsp.point_contents(Hull::POINT, back).unwrap(),
+            contents::SOLID
+        );
+        assert_eq!(polygon.styles, [255; 4]);
+    }
+
+    let mip_textures = bsp.mip_textures().unwrap();
+    assert_eq!(mip_textures.len(), 1);
+    assert_eq!(
+        mip_textures[0].as_ref().unwrap().name_to_string(),
+        Ok("WALL".into())
+    );
+
+    let entities = bsp.parse_entities().unwrap();
+    assert_eq!(entities.entities.len(), 2);
+    assert!(entities.entities.iter().all(|e| e.brushes.is_empty()));
+}
+
+#[test]
+fn compile_room_hulls() {
+    let bsp = room();
+    let contents_at = |hull, point| bsp.point_contents(hull, point).unwrap();
+
+    assert_eq!(contents_at(Hull::POINT, [0.0, 0.0, 64.0]), contents::EMPTY);
+    assert_eq!(contents_at(Hull::POINT, [0.0, 0.0, -8.0]), contents::SOLID);
+    assert_eq!(contents_at(Hull::POINT, [50.0, 0.0, 64.0]), contents::EMPTY);
+
+    // Space outside the room is filled in
+    assert_eq!(
+        contents_at(Hull::POINT, [0.0, 0.0, -500.0]),
+        contents::SOLID
+    );
+    assert_eq!(
+        contents_at(Hull::PLAYER, [0.0, 0.0, 500.0]),
+        contents::SOLID
+    );
+
+    assert_eq!(contents_at(Hull::PLAYER, [0.0, 0.0, 30.0]), contents::EMPTY);
+    assert_eq!(contents_at(Hull::PLAYER, [0.0, 0.0, 20.0]), contents::SOLID);
+    assert_eq!(
+        contents_at(Hull::PLAYER, [50.0, 0.0, 64.0]),
+        contents::SOLID
+    );
+    assert_eq!(
+        contents_at(Hull::PLAYER, [0.0, 0.0, 100.0]),
+        contents::SOLID
+    );
+
+    assert_eq!(
+        contents_at(Hull::SHAMBLER, [0.0, 0.0, 30.0]),
+        contents::EMPTY
+    );
+    assert_eq!(
+        contents_at(Hull::SHAMBLER, [40.0, 0.0, 30.0]),
+        contents::SOLID
+    );
+    assert_eq!(
+        contents_at(Hull::SHAMBLER, [0.0, 0.0, 70.0]),
+        contents::SOLID
+    );
+
+    let trace = bsp
+        .trace(0, Hull::PLAYER, [0.0, 0.0, 90.0], [0.0, 0.0, -100.0])
+        .unwrap();
+
+    assert!(trace.fraction < 1.0);
+    assert!((trace.end[2] - 24.0).abs() < 0.1);
+    assert_eq!(trace.plane.unwrap().normal, [0.0, 0.0, 1.0]);
+}
+
+#[test]
+fn compile_roundtrip() {
+    let bsp = room();
+    let mut bytes = Vec::new();
+    bsp.write_to(&mut bytes).unwrap();
+
+    let mut cursor = Cursor::new(&bytes[..]);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+
+    assert!(parser.validate().unwrap().is_valid());
+    assert_eq!(parser.parse_bsp().unwrap(), bsp);
+}
+
+#[test]
+fn compile_leak() {
+    let mut brushes = room_brushes(64, 128);
+    brushes.pop();
+
+    let err = compile(&map(&brushes, PLAYER_START)).unwrap_err();
+
+    assert!(matches!(
+        err,
+        BinParseError::Parse(message) if message.contains("info_player_start")
+    ));
+}
+
+#[test]
+fn compile_without_entities() {
+    let bsp = compile(&map(&room_brushes(64, 128), "")).unwrap();
+
+    // Nothing is filled, so the outsides of the walls are kept, split where
+    // they cross the planes of other walls
+    assert!(bsp.faces.len() > 12);
+    assert_eq!(
+        bsp.point_contents(Hull::POINT, [0.0, 0.0, -500.0]).unwrap(),
+        contents::EMPTY
+    );
+}
+
+#[test]
+fn compile_brush_entity() {
+    let door = format!(
+        "{{\n\"classname\" \"func_door\"\n{}}}\n",
+        box_brush([-16, -16, 32], [16, 16, 64], "floor")
+    );
+
+    let bsp = compile(&map(
+        &room_brushes(64, 128),
+        &format!("{PLAYER_START}{door}"),
+    ))
+    .unwrap();
+
+    assert_eq!(bsp.models.len(), 2);
+    assert_eq!(bsp.models[1].face_count, 6);
+    assert_eq!(bsp.models[1].mins, [-16.0, -16.0, 32.0]);
+    assert_eq!(bsp.models[1].first_face, bsp.models[0].face_count);
+
+    let entities = bsp.parse_entities().unwrap();
+    let model = bsp.entity_model(&entities.entities[2]);
+    assert_eq!(model, Some(&bsp.models[1]));
+
+    // The door is not part of the world
+    assert_eq!(
+        bsp.point_contents(Hull::POINT, [0.0, 0.0, 48.0]).unwrap(),
+        contents::EMPTY
+    );
+
+    let door_hull = bsp.hull(1, Hull::POINT).unwrap();
+    assert_eq!(
+        door_hull.point_contents([0.0, 0.0, 48.0]).unwrap(),
+        contents::SOLID
+    );
+
+    let door_hull = bsp.hull(1, Hull::PLAYER).unwrap();
+    assert_eq!(
+        door_hull.point_contents([0.0, 30.0, 48.0]).unwrap(),
+        contents::SOLID
+    );
+    assert_eq!(
+        door_hull.point_contents([0.0, 40.0, 48.0]).unwrap(),
+        contents::EMPTY
+    );
+}
+
+#[test]
+fn compile_liquid() {
+    let mut brushes = room_brushes(64, 128);
+    brushes.push(box_brush([-64, -64, 0], [64, 64, 32], "*water"));
+
+    let bsp = compile(&map(&brushes, PLAYER_START)).unwrap();
+
+    assert_eq!(
+        bsp.point_contents(Hull::POINT, [0.0, 0.0, 16.0]).unwrap(),
+        contents::WATER
+    );
+    assert_eq!(
+        bsp.point_contents(Hull::PLAYER, [0.0, 0.0, 30.0]).unwrap(),
+        contents::EMPTY
+    );
+
+    // The surface is visible from above and below
+    let water_faces: Vec<_> = bsp
+        .polygons()
+        .map(|polygon| polygon.unwrap())
+        .filter(|polygon| {
+            bsp.tex_info[polygon.tex_info as usize].flags & tex_flags::SPECIAL
+                != 0
+        })
+        .collect();
+
+    assert_eq!(water_faces.len(), 2);
+    assert!(water_faces
+        .iter()
+        .all(|p| p.plane.normal == [0.0, 0.0, 1.0]));
+    assert_ne!(water_faces[0].back, water_faces[1].back);
+}
+
+#[test]
+fn compile_clip_brush() {
+    let mut brushes = room_brushes(64, 128);
+    brushes.push(box_brush([-16, -16, 0], [16, 16, 64], "clip"));
+
+    let bsp = compile(&map(&brushes, PLAYER_START)).unwrap();
+
+    assert_eq!(bsp.faces.len(), 6);
+    assert_eq!(bsp.mip_textures().unwrap().len(), 1);
+    assert_eq!(
+        bsp.point_contents(Hull::POINT, [0.0, 0.0, 32.0]).unwrap(),
+        contents::EMPTY
+    );
+    assert_eq!(
+        bsp.point_contents(Hull::PLAYER, [0.0, 0.0, 32.0]).unwrap(),
+        contents::SOLID
+    );
+}
+
+#[test]
+fn compile_subdivide() {
+    let bsp = compile(&map(&room_brushes(256, 128), PLAYER_START)).unwrap();
+
+    assert!(bsp.faces.len() > 6);
+
+    // Without subdivision the floor would span 512 texels
+    for face in &bsp.faces {
+        let tex_info = &bsp.tex_info[face.tex_info as usize];
+        let coords: Vec<[f32; 2]> = bsp
+            .winding(face)
+            .unwrap()
+            .into_iter()
+            .map(|point| tex_info.tex_coords(point))
+            .collect();
+
+        for axis in 0..2 {
+            let axis_coords = coords.iter().map(|st| st[axis]);
+            let min = axis_coords.clone().fold(f32::INFINITY, f32::min);
+            let max = axis_coords.fold(f32::NEG_INFINITY, f32::max);
+            assert!(max - min <= 240.0);
+        }
+
+        let extents = bsp.lightmap_extents(face).unwrap().unwrap();
+        assert!(extents.size.iter().all(|&luxels| luxels <= 18));
+    }
+}
+
+#[test]
+fn compile_bad_maps() {
+    let mut brushes = room_brushes(64, 128);
+    brushes.push(box_brush([-16, -16, 0], [16, 16, 16], "missing"));
+    assert!(compile(&map(&brushes, PLAYER_START)).is_err());
+
+    assert!(compile(&qmap::QuakeMap::new()).is_err());
+    assert!(compile(&map(&[], PLAYER_START)).is_err());
+}
@@ -0,0 +1,265 @@
+use super::parser::{
+    check_palettes, parse_entities_lump, parse_mip_texture_palettes,
+    parse_mip_textures,
+};
+use super::{
+    ClipNode, Edge, Face, Leaf, MarkSurface, Model, Node, Plane, SurfEdge,
+    TexInfo, Vertex, BSP_VERSION,
+};
+use crate::lump::{MipTexture, MipTextureHead};
+use crate::qmap::{Entity, QuakeMap};
+use crate::{BinParseError, BinParseResult, Palette};
+use std::boxed::Box;
+use std::mem::size_of;
+use std::vec::Vec;
+
+/// Fully decoded BSP, as obtained from `Parser::parse_bsp`.  Lumps are stored
+/// in their typed forms, with indices left as they appear in the file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bsp {
+    pub version: u32,
+
+    /// Raw entities lump, see `Bsp::parse_entities`
+    pub entities: Vec<u8>,
+
+    pub planes: Vec<Plane>,
+
+    /// Raw textures lump, see `Bsp::mip_textures`
+    pub textures: Vec<u8>,
+
+    pub vertices: Vec<Vertex>,
+
+    /// Raw visibility lump, see `Bsp::pvs`
+    pub visibility: Vec<u8>,
+
+    pub nodes: Vec<Node>,
+    pub tex_info: Vec<TexInfo>,
+    pub faces: Vec<Face>,
+
+    /// Raw light lump, see `Bsp::face_lightmaps`
+    pub lighting: Vec<u8>,
+
+    pub clip_nodes: Vec<ClipNode>,
+    pub leaves: Vec<Leaf>,
+    pub mark_surfaces: Vec<MarkSurface>,
+    pub edges: Vec<Edge>,
+    pub surf_edges: Vec<SurfEdge>,
+    pub models: Vec<Model>,
+}
+
+impl Default for Bsp {
+    /// BSP29 with all lumps empty
+    fn default() -> Self {
+        Self {
+            version: BSP_VERSION,
+            entities: Vec::new(),
+            planes: Vec::new(),
+            textures: Vec::new(),
+            vertices: Vec::new(),
+            visibility: Vec::new(),
+            nodes: Vec::new(),
+            tex_info: Vec::new(),
+            faces: Vec::new(),
+            lighting: Vec::new(),
+            clip_nodes: Vec::new(),
+            leaves: Vec::new(),
+            mark_surfaces: Vec::new(),
+            edges: Vec::new(),
+            surf_edges: Vec::new(),
+            models: Vec::new(),
+        }
+    }
+}
+
+/// Face with its winding reconstructed from the surface edges, edges, and
+/// vertices lumps
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polygon {
+    /// Vertices of the face in winding order
+    pub points: Vec<Vertex>,
+
+    /// Plane the face lies on
+    pub plane: Plane,
+
+    /// Whether the face lies on the back side of the plane, in which case the
+    /// face normal is opposite to the plane normal
+    pub back: bool,
+
+    /// Index into the texture info lump
+    pub tex_info: u32,
+
+    /// Light styles for each lightmap, terminated by 255
+    pub styles: [u8; 4],
+}
+
+impl Bsp {
+    /// Iterate over every face as a polygon, in the order of the faces lump.
+    /// Items are errors if a face refers to a plane, edge, or vertex that does
+    /// not exist.
+    pub fn polygons(
+        &self,
+    ) -> impl Iterator<Item = BinParseResult<Polygon>> + '_ {
+        self.faces.iter().map(|face| self.polygon(face))
+    }
+
+    /// Reconstruct the polygon for a single face
+    pub fn polygon(&self, face: &Face) -> BinParseResult<Polygon> {
+        let plane = *self
+            .planes
+            .get(face.plane as usize)
+            .ok_or_else(|| out_of_range("Plane", face.plane))?;
+
+        Ok(Polygon {
+            points: self.winding(face)?,
+            plane,
+            back: face.is_back(),
+            tex_info: face.tex_info,
+            styles: face.styles,
+        })
+    }
+
+    /// Obtain the vertices of a face in winding order.  Negative surface edges
+    /// traverse their edge in reverse.
+    pub fn winding(&self, face: &Face) -> BinParseResult<Vec<Vertex>> {
+        let first = face.first_edge as usize;
+        let last = first
+            .checked_add(face.edge_count as usize)
+            .filter(|&last| last <= self.surf_edges.len())
+            .ok_or_else(|| out_of_range("Surface edge", face.first_edge))?;
+
+        self.surf_edges[first..last]
+            .iter()
+            .map(|&surf_edge| {
+                let edge_idx = surf_edge.unsigned_abs();
+                let edge = self
+                    .edges
+                    .get(edge_idx as usize)
+                    .ok_or_else(|| out_of_range("Edge", edge_idx))?;
+
+                let vertex_idx = if surf_edge < 0 {
+                    edge.vertices[1]
+                } else {
+                    edge.vertices[0]
+                };
+
+                self.vertices
+                    .get(vertex_idx as usize)
+                    .copied()
+                    .ok_or_else(|| out_of_range("Vertex", vertex_idx))
+            })
+            .collect()
+    }
+
+    /// Attempts to parse the entities lump, up to the first null byte
+    pub fn parse_entities(&self) -> BinParseResult<QuakeMap> {
+        parse_entities_lump(&self.entities)
+    }
+
+    /// Attempts to parse the textures embedded in the textures lump.  Textures
+    /// missing from the BSP or loaded from a WAD are `None`.
+    pub fn mip_textures(&self) -> BinParseResult<Vec<Option<MipTexture>>> {
+        parse_mip_textures(&self.textures, self.version)
+    }
+
+    /// Attempts to parse the palette embedded with each texture of a
+    /// Half-Life BSP.  Fails for other versions, which use a global palette.
+    pub fn mip_texture_palettes(
+        &self,
+    ) -> BinParseResult<Vec<Option<Box<Palette>>>> {
+        check_palettes(self.version)?;
+        parse_mip_texture_palettes(&self.textures)
+    }
+
+    /// Texture coordinates for each vertex of a face, in winding order.
+    /// Coordinates are normalized by the size of the face's texture, such that
+    /// (1, 1) is the bottom-right corner of the texture.
+    pub fn face_uvs(&self, face: &Face) -> BinParseResult<Vec<[f32; 2]>> {
+        let tex_info = self
+            .tex_info
+            .get(face.tex_info as usize)
+            .ok_or_else(|| out_of_range("Texture info", face.tex_info))?;
+
+        let head = self.mip_texture_head(tex_info.mip_texture)?;
+        let size = [head.width as f32, head.height as f32];
+
+        Ok(self
+            .winding(face)?
+            .into_iter()
+            .map(|point| {
+                let [s, t] = tex_info.tex_coords(point);
+                [s / size[0], t / size[1]]
+            })
+            .collect())
+    }
+
+    pub(super) fn mip_texture_head(
+        &self,
+        index: u32,
+    ) -> BinParseResult<MipTextureHead> {
+        let count = self
+            .textures
+            .get(..4)
+            .map_or(0, |bytes| i32::from_le_bytes(bytes.try_into().unwrap()));
+
+        if i64::from(index) >= i64::from(count) {
+            return Err(out_of_range("Texture", index));
+        }
+
+        let offset_pos = 4 + 4 * index as usize;
+        let offset = self
+            .textures
+            .get(offset_pos..(offset_pos + 4))
+            .map(|bytes| i32::from_le_bytes(bytes.try_into().unwrap()))
+            .ok_or_else(|| out_of_range("Texture", index))?;
+
+        let offset = usize::try_from(offset).map_err(|_| {
+            BinParseError::Parse(format!("Texture {index} is missing"))
+        })?;
+
+        let head_bytes = offset
+            .checked_add(size_of::<MipTextureHead>())
+            .and_then(|end| self.textures.get(offset..end))
+            .ok_or_else(|| {
+                BinParseError::Parse(format!("Texture {index} cut off"))
+            })?;
+
+        <[u8; size_of::<MipTextureHead>()]>::try_from(head_bytes)
+            .unwrap()
+            .try_into()
+    }
+
+    /// Find the submodel an entity refers to.  Worldspawn uses model 0, brush
+    /// entities name their model with a key such as `"model" "*37"`.  Returns
+    /// `None` for point entities and references to models that do not exist.
+    pub fn entity_model(&self, entity: &Entity) -> Option<&Model> {
+        let value_of = |key: &[u8]| {
+            entity
+                .edict
+                .iter()
+                .find(|(k, _)| k.as_bytes() == key)
+                .map(|(_, v)| v.as_bytes())
+        };
+
+        if value_of(b"classname") == Some(b"worldspawn") {
+            return self.models.first();
+        }
+
+        let index = value_of(b"model")?.strip_prefix(b"*")?;
+        let index: usize = std::str::from_utf8(index).ok()?.parse().ok()?;
+        self.models.get(index)
+    }
+
+    /// Pair each entity of the map with its submodel, if it has one
+    pub fn entity_models<'a>(
+        &'a self,
+        map: &'a QuakeMap,
+    ) -> impl Iterator<Item = (&'a Entity, Option<&'a Model>)> + 'a {
+        map.entities
+            .iter()
+            .map(move |entity| (entity, self.entity_model(entity)))
+    }
+}
+
+pub(super) fn out_of_range(kind: &str, index: u32) -> BinParseError {
+    BinParseError::Parse(format!("{kind} index {index} out of range"))
+}
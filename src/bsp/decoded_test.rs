@@ -0,0 +1,191 @@
+use super::test_fixtures::{model, textures_lump};
+use crate::qmap::{Entity, QuakeMap};
+use crate::{bsp, BinParseError};
+use bsp::{Bsp, Edge, Face, Model, Plane, PlaneKind, TexInfo};
+use std::ffi::CString;
+use std::vec::Vec;
+
+fn entity(pairs: &[(&str, &str)]) -> Entity {
+    let mut entity = Entity::new();
+
+    for (key, value) in pairs {
+        entity
+            .edict
+            .push((CString::new(*key).unwrap(), CString::new(*value).unwrap()));
+    }
+
+    entity
+}
+
+fn square_bsp() -> Bsp {
+    Bsp {
+        planes: vec![Plane {
+            normal: [0.0, 0.0, 1.0],
+            dist: 16.0,
+            kind: PlaneKind::Z,
+        }],
+        textures: textures_lump("floor", [32, 16], 0, &[true, false]),
+        vertices: vec![
+            [0.0, 0.0, 16.0],
+            [64.0, 0.0, 16.0],
+            [64.0, 64.0, 16.0],
+            [0.0, 64.0, 16.0],
+        ],
+        tex_info: vec![
+            TexInfo {
+                vecs: [[1.0, 0.0, 0.0, 0.0], [0.0, -1.0, 0.0, 8.0]],
+                mip_texture: 0,
+                flags: 0,
+            };
+            4
+        ],
+        faces: vec![Face {
+            plane: 0,
+            side: 1,
+            first_edge: 1,
+            edge_count: 4,
+            tex_info: 3,
+            styles: [0, 255, 255, 255],
+            light_offset: -1,
+        }],
+        edges: vec![
+            Edge { vertices: [0, 0] },
+            Edge { vertices: [0, 1] },
+            Edge { vertices: [2, 1] },
+            Edge { vertices: [2, 3] },
+            Edge { vertices: [0, 3] },
+        ],
+        surf_edges: vec![0, 1, -2, 3, -4],
+        models: vec![
+            model(0, 0),
+            Model {
+                mins: [-1.0; 3],
+                maxs: [1.0; 3],
+                ..model(0, 0)
+            },
+        ],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn face_winding() {
+    let bsp = square_bsp();
+    let winding = bsp.winding(&bsp.faces[0]).unwrap();
+
+    assert_eq!(
+        winding,
+        vec![
+            [0.0, 0.0, 16.0],
+            [64.0, 0.0, 16.0],
+            [64.0, 64.0, 16.0],
+            [0.0, 64.0, 16.0],
+        ]
+    );
+}
+
+#[test]
+fn face_polygons() {
+    let bsp = square_bsp();
+    let polygons = bsp.polygons().collect::<Result<Vec<_>, _>>().unwrap();
+
+    assert_eq!(polygons.len(), 1);
+    assert_eq!(polygons[0].points.len(), 4);
+    assert_eq!(polygons[0].plane, bsp.planes[0]);
+    assert!(polygons[0].back);
+    assert_eq!(polygons[0].tex_info, 3);
+    assert_eq!(polygons[0].styles, [0, 255, 255, 255]);
+}
+
+#[test]
+fn face_surf_edges_out_of_range() {
+    let mut bsp = square_bsp();
+    bsp.faces[0].edge_count = 5;
+    let err = bsp.polygon(&bsp.faces[0]).unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn face_edge_out_of_range() {
+    let mut bsp = square_bsp();
+    bsp.surf_edges[2] = -5;
+    let err = bsp.winding(&bsp.faces[0]).unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn face_vertex_out_of_range() {
+    let mut bsp = square_bsp();
+    bsp.edges[3].vertices[0] = 4;
+    let err = bsp.winding(&bsp.faces[0]).unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn face_plane_out_of_range() {
+    let mut bsp = square_bsp();
+    bsp.faces[0].plane = 1;
+    let err = bsp.polygon(&bsp.faces[0]).unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn entity_submodels() {
+    let bsp = square_bsp();
+    let map = QuakeMap {
+        entities: vec![
+            entity(&[("classname", "worldspawn")]),
+            entity(&[("classname", "func_door"), ("model", "*1")]),
+            entity(&[("classname", "func_plat"), ("model", "*2")]),
+            entity(&[("classname", "light")]),
+            entity(&[("classname", "misc_explobox"), ("model", "box.bsp")]),
+        ],
+    };
+
+    let models = bsp
+        .entity_models(&map)
+        .map(|(_, model)| model.map(|m| m.maxs[0]))
+        .collect::<Vec<_>>();
+
+    assert_eq!(models, vec![Some(0.0), Some(1.0), None, None, None]);
+}
+
+#[test]
+fn bsp_mip_textures() {
+    let bsp = square_bsp();
+    let textures = bsp.mip_textures().unwrap();
+
+    assert_eq!(textures.len(), 2);
+    assert_eq!(textures[0].as_ref().unwrap().mip(0).width(), 32);
+    assert!(textures[1].is_none());
+}
+
+#[test]
+fn face_uvs() {
+    let bsp = square_bsp();
+    let uvs = bsp.face_uvs(&bsp.faces[0]).unwrap();
+
+    assert_eq!(uvs, vec![[0.0, 0.5], [2.0, 0.5], [2.0, -3.5], [0.0, -3.5]]);
+}
+
+#[test]
+fn face_uvs_missing_texture() {
+    let mut bsp = square_bsp();
+    bsp.tex_info[3].mip_texture = 1;
+    let err = bsp.face_uvs(&bsp.faces[0]).unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn face_uvs_texture_out_of_range() {
+    let mut bsp = square_bsp();
+    bsp.tex_info[3].mip_texture = 2;
+    let err = bsp.face_uvs(&bsp.faces[0]).unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
@@ -0,0 +1,516 @@
+use super::decoded::out_of_range;
+use super::{Bsp, Face, LightmapExtents, TexInfo, LUXEL_SIZE};
+use crate::lump::Image;
+use crate::{slice_to_cstring, BinParseResult};
+use std::collections::BTreeMap;
+use std::string::{String, ToString};
+use std::vec::Vec;
+
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const LINEAR: u32 = 9729;
+const CLAMP_TO_EDGE: u32 = 33071;
+
+/// Minimum width of the lightmap atlas in luxels
+const ATLAS_WIDTH: u32 = 256;
+
+/// glTF 2.0 export of a BSP's geometry
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GltfExport {
+    /// glTF JSON document, referring to the buffer and lightmap atlas by the
+    /// URIs they were exported with
+    pub json: String,
+
+    /// Binary buffer holding vertex attributes and indices
+    pub buffer: Vec<u8>,
+
+    /// Lightmaps of every face (first style only) packed into one image,
+    /// addressed by the second set of texture coordinates.  Pixels are light
+    /// intensities, to be saved as a greyscale PNG.
+    pub lightmap_atlas: Image,
+}
+
+/// Faces sharing a texture within a model, in glTF axes
+#[derive(Default)]
+struct Primitive {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    tex_coords: Vec<[f32; 2]>,
+    lightmap_coords: Vec<[f32; 2]>,
+    indices: Vec<u32>,
+}
+
+/// Shelf packer for face lightmaps.  Luxel (0, 0) is reserved as fully bright
+/// for faces without a lightmap.
+struct Atlas {
+    width: u32,
+    height: u32,
+    cursor: [u32; 2],
+    shelf_height: u32,
+}
+
+impl Atlas {
+    fn new(width: u32) -> Self {
+        let mut atlas = Self {
+            width,
+            height: 0,
+            cursor: [0, 0],
+            shelf_height: 0,
+        };
+        atlas.place([1, 1]);
+        atlas
+    }
+
+    fn place(&mut self, size: [u32; 2]) -> [u32; 2] {
+        if self.cursor[0] + size[0] > self.width {
+            self.cursor = [0, self.cursor[1] + self.shelf_height];
+            self.shelf_height = 0;
+        }
+
+        let position = self.cursor;
+        self.cursor[0] += size[0];
+        self.shelf_height = self.shelf_height.max(size[1]);
+        self.height = self.height.max(position[1] + size[1]);
+        position
+    }
+}
+
+impl Bsp {
+    /// Export the geometry of every model as glTF 2.0.  Each model becomes a
+    /// node whose mesh has one primitive per texture, with texture coordinates
+    /// in `TEXCOORD_0` and lightmap atlas coordinates in `TEXCOORD_1`.  Every
+    /// material uses the lightmap atlas as its occlusion texture.  Positions
+    /// are converted from Quake's Z-up axes to glTF's Y-up axes.  Faces with
+    /// fewer than 3 edges are skipped.  `buffer_uri` and `lightmap_uri` are
+    /// where the buffer and atlas will be stored relative to the JSON.
+    pub fn to_gltf(
+        &self,
+        buffer_uri: &str,
+        lightmap_uri: &str,
+    ) -> BinParseResult<GltfExport> {
+        let mut lightmaps = Vec::with_capacity(self.faces.len());
+        let mut atlas_width = ATLAS_WIDTH;
+
+        for face in &self.faces {
+            let extents = if has_triangles(face) {
+                self.lightmap_extents(face)?
+            } else {
+                None
+            };
+
+            let lightmap = match extents {
+                Some(extents) if face.light_offset >= 0 => self
+                    .face_lightmaps(face)?
+                    .into_iter()
+                    .next()
+                    .map(|lightmap| (extents, lightmap.image)),
+                _ => None,
+            };
+
+            if let Some((extents, _)) = &lightmap {
+                atlas_width = atlas_width.max(extents.size[0]);
+            }
+
+            lightmaps.push(lightmap);
+        }
+
+        let mut atlas = Atlas::new(atlas_width);
+        let placements: Vec<_> = lightmaps
+            .iter()
+            .map(|lightmap| {
+                lightmap
+                    .as_ref()
+                    .map(|(extents, _)| atlas.place(extents.size))
+            })
+            .collect();
+
+        let mut pixels =
+            vec![0u8; atlas.width as usize * atlas.height as usize];
+        pixels[0] = 255;
+
+        for (lightmap, placement) in lightmaps.iter().zip(&placements) {
+            if let (Some((extents, image)), Some([x, y])) =
+                (lightmap, placement)
+            {
+                let width = extents.size[0] as usize;
+
+                for (row, luxels) in image.pixels().chunks(width).enumerate() {
+                    let start = (*y as usize + row) * atlas.width as usize
+                        + *x as usize;
+                    pixels[start..(start + width)].copy_from_slice(luxels);
+                }
+            }
+        }
+
+        let atlas_size = [atlas.width as f32, atlas.height as f32];
+        let mut materials = BTreeMap::new();
+        let mut meshes = Vec::with_capacity(self.models.len());
+
+        for model in &self.models {
+            let mut primitives = BTreeMap::<u32, Primitive>::new();
+            let first = model.first_face as usize;
+
+            for face_idx in first..(first + model.face_count as usize) {
+                let face = self
+                    .faces
+                    .get(face_idx)
+                    .ok_or_else(|| out_of_range("Face", face_idx as u32))?;
+
+                // glTF forbids accessors without elements
+                if !has_triangles(face) {
+                    continue;
+                }
+
+                let tex_info =
+                    self.tex_info.get(face.tex_info as usize).ok_or_else(
+                        || out_of_range("Texture info", face.tex_info),
+                    )?;
+                let texture = tex_info.mip_texture;
+
+                materials
+                    .entry(texture)
+                    .or_insert_with(|| self.texture_name(texture));
+
+                let primitive = primitives.entry(texture).or_default();
+                let lightmap = lightmaps[face_idx]
+                    .as_ref()
+                    .zip(placements[face_idx])
+                    .map(|((extents, _), placement)| (*extents, placement));
+
+                self.push_face(
+                    primitive, face, tex_info, lightmap, atlas_size,
+                )?;
+            }
+
+            meshes.push(primitives);
+        }
+
+        let material_indices: BTreeMap<u32, usize> = materials
+            .keys()
+            .enumerate()
+            .map(|(idx, &texture)| (texture, idx))
+            .collect();
+
+        let mut doc = Document::default();
+
+        for (model_idx, primitives) in meshes.iter().enumerate() {
+            let mut node = format!(r#"{{"name":"*{model_idx}""#);
+
+            if !primitives.is_empty() {
+                let encoded: Vec<_> = primitives
+                    .iter()
+                    .map(|(texture, primitive)| {
+                        doc.primitive(primitive, material_indices[texture])
+                    })
+                    .collect();
+                node.push_str(&format!(r#","mesh":{}"#, doc.meshes.len()));
+                doc.meshes.push(format!(
+                    r#"{{"primitives":[{}]}}"#,
+                    encoded.join(",")
+                ));
+            }
+
+            node.push('}');
+            doc.nodes.push(node);
+        }
+
+        let materials: Vec<_> = materials
+            .values()
+            .map(|name| {
+                format!(
+                    r#"{{"name":{},"occlusionTexture":{{"index":0,"texCoord":1}}}}"#,
+                    json_string(name),
+                )
+            })
+            .collect();
+
+        let (images, samplers, textures) = if materials.is_empty() {
+            (Vec::new(), Vec::new(), Vec::new())
+        } else {
+            (
+                vec![format!(r#"{{"uri":{}}}"#, json_string(lightmap_uri))],
+                vec![format!(
+                    r#"{{"magFilter":{LINEAR},"minFilter":{LINEAR},"wrapS":{CLAMP_TO_EDGE},"wrapT":{CLAMP_TO_EDGE}}}"#,
+                )],
+                vec![String::from(r#"{"sampler":0,"source":0}"#)],
+            )
+        };
+
+        let node_indices: Vec<_> =
+            (0..doc.nodes.len()).map(|idx| idx.to_string()).collect();
+
+        let scene = if node_indices.is_empty() {
+            String::from("{}")
+        } else {
+            format!(r#"{{"nodes":[{}]}}"#, node_indices.join(","))
+        };
+
+        let mut json = format!(
+            r#"{{"asset":{{"version":"2.0","generator":"quake-util"}},"scene":0,"scenes":[{scene}]"#,
+        );
+
+        // glTF forbids empty arrays and empty buffers
+        if !doc.buffer.is_empty() {
+            json.push_str(&format!(
+                r#","buffers":[{{"uri":{},"byteLength":{}}}]"#,
+                json_string(buffer_uri),
+                doc.buffer.len(),
+            ));
+        }
+
+        for (key, items) in [
+            ("nodes", &doc.nodes),
+            ("meshes", &doc.meshes),
+            ("materials", &materials),
+            ("textures", &textures),
+            ("images", &images),
+            ("samplers", &samplers),
+            ("bufferViews", &doc.buffer_views),
+            ("accessors", &doc.accessors),
+        ] {
+            if !items.is_empty() {
+                json.push_str(&format!(r#","{key}":[{}]"#, items.join(",")));
+            }
+        }
+
+        json.push('}');
+
+        Ok(GltfExport {
+            json,
+            buffer: doc.buffer,
+            lightmap_atlas: Image::from_pixels(
+                atlas.width,
+                pixels.into_boxed_slice(),
+            ),
+        })
+    }
+
+    fn texture_name(&self, texture: u32) -> String {
+        match self.mip_texture_head(texture) {
+            Ok(head) => {
+                slice_to_cstring(&head.name).to_string_lossy().into_owned()
+            }
+            Err(_) => format!("texture {texture}"),
+        }
+    }
+
+    fn push_face(
+        &self,
+        primitive: &mut Primitive,
+        face: &Face,
+        tex_info: &TexInfo,
+        lightmap: Option<(LightmapExtents, [u32; 2])>,
+        atlas_size: [f32; 2],
+    ) -> BinParseResult<()> {
+        let polygon = self.polygon(face)?;
+        let texture_size = self
+            .mip_texture_head(tex_info.mip_texture)
+            .map(|head| [head.width as f32, head.height as f32])
+            .unwrap_or([64.0, 64.0]);
+
+        let normal = if polygon.back {
+            polygon.plane.normal.map(|n| -n)
+        } else {
+            polygon.plane.normal
+        };
+
+        let base = primitive.positions.len() as u32;
+
+        for point in &polygon.points {
+            let [s, t] = tex_info.tex_coords(*point);
+
+            let lightmap_coords = match lightmap {
+                Some((extents, [x, y])) => {
+                    let luxel = LUXEL_SIZE as f32;
+                    let mins = extents.texture_mins.map(|m| m as f32);
+                    [
+                        (x as f32 + (s - mins[0]) / luxel + 0.5)
+                            / atlas_size[0],
+                        (y as f32 + (t - mins[1]) / luxel + 0.5)
+                            / atlas_size[1],
+                    ]
+                }
+                None => [0.5 / atlas_size[0], 0.5 / atlas_size[1]],
+            };
+
+            primitive.positions.push(to_gltf_axes(*point));
+            primitive.normals.push(to_gltf_axes(normal));
+            primitive
+                .tex_coords
+                .push([s / texture_size[0], t / texture_size[1]]);
+            primitive.lightmap_coords.push(lightmap_coords);
+        }
+
+        // Quake windings are clockwise when seen from the front, glTF expects
+        // counter-clockwise
+        for i in 1..(polygon.points.len().saturating_sub(1) as u32) {
+            primitive.indices.extend([base, base + i + 1, base + i]);
+        }
+
+        Ok(())
+    }
+}
+
+/// Buffer and JSON fragments accumulated while encoding primitives
+#[derive(Default)]
+struct Document {
+    buffer: Vec<u8>,
+    buffer_views: Vec<String>,
+    accessors: Vec<String>,
+    meshes: Vec<String>,
+    nodes: Vec<String>,
+}
+
+impl Document {
+    fn primitive(&mut self, primitive: &Primitive, material: usize) -> String {
+        let (min, max) = bounds(&primitive.positions);
+        let position = self.accessor(
+            primitive.positions.as_flattened(),
+            "VEC3",
+            primitive.positions.len(),
+            Some((&min, &max)),
+        );
+        let normal = self.accessor(
+            primitive.normals.as_flattened(),
+            "VEC3",
+            primitive.normals.len(),
+            None,
+        );
+        let tex_coords = self.accessor(
+            primitive.tex_coords.as_flattened(),
+            "VEC2",
+            primitive.tex_coords.len(),
+            None,
+        );
+        let lightmap_coords = self.accessor(
+            primitive.lightmap_coords.as_flattened(),
+            "VEC2",
+            primitive.lightmap_coords.len(),
+            None,
+        );
+
+        let view = self.buffer_view(
+            primitive.indices.iter().flat_map(|i| i.to_le_bytes()),
+            ELEMENT_ARRAY_BUFFER,
+        );
+        let indices = self.accessors.len();
+        self.accessors.push(format!(
+            r#"{{"bufferView":{view},"componentType":{UNSIGNED_INT},"count":{},"type":"SCALAR"}}"#,
+            primitive.indices.len(),
+        ));
+
+        format!(
+            concat!(
+                r#"{{"attributes":{{"POSITION":{},"NORMAL":{},"#,
+                r#""TEXCOORD_0":{},"TEXCOORD_1":{}}},"#,
+                r#""indices":{},"material":{}}}"#,
+            ),
+            position, normal, tex_coords, lightmap_coords, indices, material,
+        )
+    }
+
+    fn accessor(
+        &mut self,
+        floats: &[f32],
+        kind: &str,
+        count: usize,
+        bounds: Option<(&[f32; 3], &[f32; 3])>,
+    ) -> usize {
+        let view = self.buffer_view(
+            floats.iter().flat_map(|f| f.to_le_bytes()),
+            ARRAY_BUFFER,
+        );
+        let mut accessor = format!(
+            r#"{{"bufferView":{view},"componentType":{FLOAT},"count":{count},"type":"{kind}""#,
+        );
+
+        if let Some((min, max)) = bounds {
+            accessor.push_str(&format!(
+                r#","min":[{}],"max":[{}]"#,
+                json_floats(min),
+                json_floats(max),
+            ));
+        }
+
+        accessor.push('}');
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    fn buffer_view(
+        &mut self,
+        bytes: impl Iterator<Item = u8>,
+        target: u32,
+    ) -> usize {
+        let offset = self.buffer.len();
+        self.buffer.extend(bytes);
+        let length = self.buffer.len() - offset;
+
+        self.buffer_views.push(format!(
+            r#"{{"buffer":0,"byteOffset":{offset},"byteLength":{length},"target":{target}}}"#,
+        ));
+        self.buffer_views.len() - 1
+    }
+}
+
+fn has_triangles(face: &Face) -> bool {
+    face.edge_count >= 3
+}
+
+/// Convert from Quake axes (Z up) to glTF axes (Y up, -Z forward)
+fn to_gltf_axes([x, y, z]: [f32; 3]) -> [f32; 3] {
+    [x, z, -y]
+}
+
+fn bounds(points: &[[f32; 3]]) -> ([f32; 3], [f32; 3]) {
+    let mut min = [f32::INFINITY; 3];
+    let mut max = [f32::NEG_INFINITY; 3];
+
+    for point in points {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(point[axis]);
+            max[axis] = max[axis].max(point[axis]);
+        }
+    }
+
+    (min, max)
+}
+
+fn json_floats(floats: &[f32]) -> String {
+    let strings: Vec<_> = floats.iter().map(|f| json_float(*f)).collect();
+    strings.join(",")
+}
+
+// JSON has no representation for non-finite numbers, and adding zero keeps
+// negative zero from being written as "-0"
+fn json_float(float: f32) -> String {
+    if float.is_finite() {
+        format!("{}", float + 0.0)
+    } else {
+        String::from("0")
+    }
+}
+
+fn json_string(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len() + 2);
+    escaped.push('"');
+
+    for ch in string.chars() {
+        match ch {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => {
+                escaped.push_str(&format!("\\u{:04x}", ch as u32));
+            }
+            ch => escaped.push(ch),
+        }
+    }
+
+    escaped.push('"');
+    escaped
+}
@@ -0,0 +1,167 @@
+use super::test_fixtures::{model, textures_lump};
+use crate::bsp;
+use bsp::{tex_flags, Bsp, Edge, Face, Plane, PlaneKind, TexInfo};
+use std::vec::Vec;
+
+fn face(side: u32, tex_info: u32, light_offset: i32) -> Face {
+    Face {
+        plane: 0,
+        side,
+        first_edge: 0,
+        edge_count: 4,
+        tex_info,
+        styles: [0, 255, 255, 255],
+        light_offset,
+    }
+}
+
+fn floor_bsp() -> Bsp {
+    Bsp {
+        planes: vec![Plane {
+            normal: [0.0, 0.0, 1.0],
+            dist: 0.0,
+            kind: PlaneKind::Z,
+        }],
+        textures: textures_lump("floor", [32, 16], 0, &[true, false]),
+        vertices: vec![
+            [0.0, 0.0, 0.0],
+            [64.0, 0.0, 0.0],
+            [64.0, 32.0, 0.0],
+            [0.0, 32.0, 0.0],
+        ],
+        tex_info: vec![
+            TexInfo {
+                vecs: [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0]],
+                mip_texture: 0,
+                flags: 0,
+            },
+            TexInfo {
+                vecs: [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0]],
+                mip_texture: 1,
+                flags: tex_flags::SPECIAL,
+            },
+        ],
+        faces: vec![face(0, 0, 0), face(1, 1, -1), face(0, 0, -1)],
+        lighting: (0..15).map(|i| i * 10).collect(),
+        edges: vec![
+            Edge { vertices: [0, 1] },
+            Edge { vertices: [1, 2] },
+            Edge { vertices: [2, 3] },
+            Edge { vertices: [3, 0] },
+        ],
+        surf_edges: vec![0, 1, 2, 3],
+        models: vec![model(0, 2), model(2, 1), model(0, 0)],
+        ..Default::default()
+    }
+}
+
+fn floats_at(buffer: &[u8], offset: usize, count: usize) -> Vec<f32> {
+    buffer[offset..(offset + count * 4)]
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+        .collect()
+}
+
+#[test]
+fn gltf_document() {
+    let gltf = floor_bsp().to_gltf("floor.bin", "floor.png").unwrap();
+    let json = &gltf.json;
+
+    assert!(json.starts_with(r#"{"asset":{"version":"2.0""#));
+    assert!(json.ends_with('}'));
+    assert!(json.contains(r#""scenes":[{"nodes":[0,1,2]}]"#));
+    assert!(json.contains(r#"{"name":"*0","mesh":0}"#));
+    assert!(json.contains(r#"{"name":"*1","mesh":1}"#));
+    assert!(json.contains(r#"{"name":"*2"}"#));
+    assert!(json.contains(concat!(
+        r#""materials":[{"name":"floor","occlusionTexture":{"index":0,"texCoord":1}},"#,
+        r#"{"name":"texture 1","occlusionTexture":{"index":0,"texCoord":1}}]"#,
+    )));
+    assert!(json.contains(r#""textures":[{"sampler":0,"source":0}]"#));
+    assert!(json.contains(r#""images":[{"uri":"floor.png"}]"#));
+    assert!(json.contains(r#""samplers":[{"magFilter":9729"#));
+    assert!(json.contains(&format!(
+        r#""buffers":[{{"uri":"floor.bin","byteLength":{}}}]"#,
+        gltf.buffer.len()
+    )));
+    assert!(json.contains(r#""TEXCOORD_1":3"#));
+    assert!(json.contains(r#""min":[0,0,-32],"max":[64,0,0]"#));
+
+    // Model 0 has two primitives and model 1 has one, each with 4 vertices
+    // and 6 indices
+    assert_eq!(gltf.buffer.len(), 3 * (4 * (12 + 12 + 8 + 8) + 6 * 4));
+}
+
+#[test]
+fn gltf_geometry() {
+    let gltf = floor_bsp().to_gltf("floor.bin", "floor.png").unwrap();
+    let buffer = &gltf.buffer;
+
+    assert_eq!(
+        floats_at(buffer, 0, 12),
+        [0.0, 0.0, 0.0, 64.0, 0.0, 0.0, 64.0, 0.0, -32.0, 0.0, 0.0, -32.0]
+    );
+    assert_eq!(floats_at(buffer, 48, 3), [0.0, 1.0, 0.0]);
+    assert_eq!(floats_at(buffer, 96, 4), [0.0, 0.0, 2.0, 0.0]);
+
+    let lightmap_coords = floats_at(buffer, 128, 8);
+    assert_eq!(lightmap_coords[0], 1.5 / 256.0);
+    assert_eq!(lightmap_coords[1], 0.5 / 3.0);
+    assert_eq!(lightmap_coords[4], 5.5 / 256.0);
+    assert_eq!(lightmap_coords[5], 2.5 / 3.0);
+
+    let indices: Vec<_> = buffer[160..184]
+        .chunks_exact(4)
+        .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+        .collect();
+    assert_eq!(indices, [0, 2, 1, 0, 3, 2]);
+
+    // Back face of the special texture points down and uses the fullbright
+    // luxel
+    let second = 184;
+    assert_eq!(floats_at(buffer, second + 48, 3), [0.0, -1.0, 0.0]);
+    assert_eq!(floats_at(buffer, second + 128, 2), [0.5 / 256.0, 0.5 / 3.0]);
+}
+
+#[test]
+fn gltf_lightmap_atlas() {
+    let gltf = floor_bsp().to_gltf("floor.bin", "floor.png").unwrap();
+    let atlas = &gltf.lightmap_atlas;
+
+    assert_eq!(atlas.width(), 256);
+    assert_eq!(atlas.height(), 3);
+    assert_eq!(atlas.pixels()[0], 255);
+    assert_eq!(&atlas.pixels()[1..6], &[0, 10, 20, 30, 40]);
+    assert_eq!(&atlas.pixels()[257..262], &[50, 60, 70, 80, 90]);
+    assert_eq!(atlas.pixels()[6], 0);
+}
+
+#[test]
+fn gltf_empty() {
+    let gltf = Bsp::default().to_gltf("empty.bin", "empty.png").unwrap();
+
+    assert!(gltf.buffer.is_empty());
+    assert!(!gltf.json.contains("[]"));
+    assert!(!gltf.json.contains("buffers"));
+    assert!(!gltf.json.contains("images"));
+}
+
+#[test]
+fn gltf_skips_degenerate_faces() {
+    let mut bsp = floor_bsp();
+    bsp.faces.push(Face {
+        edge_count: 2,
+        ..face(0, 0, -1)
+    });
+    bsp.faces.push(Face {
+        edge_count: 0,
+        ..face(0, 0, -1)
+    });
+    bsp.models.push(model(3, 2));
+
+    let gltf = bsp.to_gltf("floor.bin", "floor.png").unwrap();
+
+    assert!(gltf.json.contains(r#"{"name":"*3"}"#));
+    assert!(!gltf.json.contains(r#""count":0"#));
+    assert_eq!(gltf.buffer, floor_bsp().to_gltf("", "").unwrap().buffer);
+}
@@ -0,0 +1,423 @@
+use super::decoded::out_of_range;
+use super::{contents, Bsp, ClipNodeChild, NodeChild, Plane, PlaneKind};
+use crate::{BinParseError, BinParseResult};
+use std::string::String;
+
+/// Deepest node a trace descends to before giving up, well beyond any
+/// compiled map but shallow enough not to overflow the stack
+const MAX_TRACE_DEPTH: usize = 1024;
+
+/// Collision hull of a submodel.  Hull 0 is traced by points and is built from
+/// the BSP nodes and leaves; hulls 1 and up are pre-expanded for boxes and
+/// are built from clip nodes.
+#[derive(Clone, Copy, Debug)]
+pub struct Hull<'a> {
+    bsp: &'a Bsp,
+    index: usize,
+    head_node: i32,
+}
+
+impl<'a> Hull<'a> {
+    /// Hull for points
+    pub const POINT: usize = 0;
+
+    /// Hull for player-sized boxes, 32 x 32 x 56 units
+    pub const PLAYER: usize = 1;
+
+    /// Hull for shambler-sized boxes, 64 x 64 x 88 units
+    pub const SHAMBLER: usize = 2;
+
+    /// Number of hulls stored per submodel
+    pub const COUNT: usize = 4;
+
+    /// Offset from the origin of an entity to the minimum corner of the box
+    /// used for a hull
+    pub fn clip_mins(index: usize) -> [f32; 3] {
+        match index {
+            Self::PLAYER => [-16.0, -16.0, -24.0],
+            Self::SHAMBLER => [-32.0, -32.0, -24.0],
+            _ => [0.0; 3],
+        }
+    }
+
+    /// Offset from the origin of an entity to the maximum corner of the box
+    /// used for a hull
+    pub fn clip_maxs(index: usize) -> [f32; 3] {
+        match index {
+            Self::PLAYER => [16.0, 16.0, 32.0],
+            Self::SHAMBLER => [32.0, 32.0, 64.0],
+            _ => [0.0; 3],
+        }
+    }
+
+    /// Index of the hull within its submodel
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Root of the hull, either a node index (non-negative) or contents
+    /// (negative)
+    pub fn head_node(&self) -> i32 {
+        self.head_node
+    }
+
+    /// Find the contents at a point, walking the hull the way the engine does.
+    /// Points exactly on a plane are considered in front of it.
+    pub fn point_contents(&self, point: [f32; 3]) -> BinParseResult<i32> {
+        let mut num = self.head_node;
+
+        // Bail out on cyclic trees rather than looping forever
+        for _ in 0..=self.node_count() {
+            if num < 0 {
+                return Ok(num);
+            }
+
+            let (plane, children) = self.node(num)?;
+
+            num = if plane_dist(plane, point) < 0.0 {
+                children[1]
+            } else {
+                children[0]
+            };
+        }
+
+        Err(BinParseError::Parse(String::from("Cycle in hull")))
+    }
+
+    /// Sweep the hull's box from `start` to `end`, stopping at the first solid
+    /// surface, the same way as the engine's `SV_RecursiveHullCheck`.  Points
+    /// are relative to the submodel's position; offset them by the inverse
+    /// of an entity's origin to trace against a moved brush entity.  Fails on
+    /// hulls more than 1024 nodes deep.
+    pub fn trace(
+        &self,
+        start: [f32; 3],
+        end: [f32; 3],
+    ) -> BinParseResult<Trace> {
+        let mut trace = Trace {
+            fraction: 1.0,
+            end,
+            plane: None,
+            all_solid: true,
+            start_solid: false,
+            in_open: false,
+            in_water: false,
+        };
+
+        self.recursive_check(
+            self.head_node,
+            0.0,
+            1.0,
+            start,
+            end,
+            &mut trace,
+            0,
+        )?;
+
+        Ok(trace)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn recursive_check(
+        &self,
+        num: i32,
+        p1f: f32,
+        p2f: f32,
+        p1: [f32; 3],
+        p2: [f32; 3],
+        trace: &mut Trace,
+        depth: usize,
+    ) -> BinParseResult<bool> {
+        if num < 0 {
+            if num != contents::SOLID {
+                trace.all_solid = false;
+
+                if num == contents::EMPTY {
+                    trace.in_open = true;
+                } else {
+                    trace.in_water = true;
+                }
+            } else {
+                trace.start_solid = true;
+            }
+
+            return Ok(true);
+        }
+
+        if depth > self.node_count() {
+            return Err(BinParseError::Parse(String::from("Cycle in hull")));
+        }
+
+        if depth > MAX_TRACE_DEPTH {
+            return Err(BinParseError::Parse(format!(
+                "Hull deeper than {MAX_TRACE_DEPTH} nodes"
+            )));
+        }
+
+        let (plane, children) = self.node(num)?;
+        let t1 = plane_dist(plane, p1);
+        let t2 = plane_dist(plane, p2);
+
+        if t1 >= 0.0 && t2 >= 0.0 {
+            return self.recursive_check(
+                children[0],
+                p1f,
+                p2f,
+                p1,
+                p2,
+                trace,
+                depth + 1,
+            );
+        }
+
+        if t1 < 0.0 && t2 < 0.0 {
+            return self.recursive_check(
+                children[1],
+                p1f,
+                p2f,
+                p1,
+                p2,
+                trace,
+                depth + 1,
+            );
+        }
+
+        // Put the crossing point DIST_EPSILON units on the near side
+        let mut frac = if t1 < 0.0 {
+            (t1 + DIST_EPSILON) / (t1 - t2)
+        } else {
+            (t1 - DIST_EPSILON) / (t1 - t2)
+        }
+        .clamp(0.0, 1.0);
+
+        let lerp = |frac: f32| {
+            let midf = p1f + (p2f - p1f) * frac;
+            let mid = [0, 1, 2].map(|i| p1[i] + frac * (p2[i] - p1[i]));
+            (midf, mid)
+        };
+
+        let (mut midf, mut mid) = lerp(frac);
+        let side = usize::from(t1 < 0.0);
+
+        // Move up to the node
+        if !self.recursive_check(
+            children[side],
+            p1f,
+            midf,
+            p1,
+            mid,
+            trace,
+            depth + 1,
+        )? {
+            return Ok(false);
+        }
+
+        let far_contents = Hull {
+            head_node: children[side ^ 1],
+            ..*self
+        }
+        .point_contents(mid)?;
+
+        if far_contents != contents::SOLID {
+            // Go past the node
+            return self.recursive_check(
+                children[side ^ 1],
+                midf,
+                p2f,
+                mid,
+                p2,
+                trace,
+                depth + 1,
+            );
+        }
+
+        if trace.all_solid {
+            // Never got out of the solid area
+            return Ok(false);
+        }
+
+        // The other side of the node is solid, this is the impact point
+        trace.plane = Some(if side == 0 {
+            TracePlane {
+                normal: plane.normal,
+                dist: plane.dist,
+            }
+        } else {
+            TracePlane {
+                normal: plane.normal.map(|n| -n),
+                dist: -plane.dist,
+            }
+        });
+
+        while self.point_contents(mid)? == contents::SOLID {
+            // Shouldn't really happen, but does occasionally
+            frac -= 0.1;
+
+            if frac < 0.0 {
+                trace.fraction = midf;
+                trace.end = mid;
+                return Ok(false);
+            }
+
+            (midf, mid) = lerp(frac);
+        }
+
+        trace.fraction = midf;
+        trace.end = mid;
+        Ok(false)
+    }
+
+    pub(crate) fn node_count(&self) -> usize {
+        if self.index == Self::POINT {
+            self.bsp.nodes.len()
+        } else {
+            self.bsp.clip_nodes.len()
+        }
+    }
+
+    /// Plane and front/back children of a node.  Children are node indices if
+    /// non-negative and contents if negative.
+    pub(crate) fn node(
+        &self,
+        num: i32,
+    ) -> BinParseResult<(&'a Plane, [i32; 2])> {
+        let bsp = self.bsp;
+        let num = num as u32;
+
+        let (plane_idx, children) = if self.index == Self::POINT {
+            let node = bsp
+                .nodes
+                .get(num as usize)
+                .ok_or_else(|| out_of_range("Node", num))?;
+
+            let mut children = [0i32; 2];
+
+            for (side, child) in node.children.iter().enumerate() {
+                children[side] = match *child {
+                    NodeChild::Node(n) => n as i32,
+                    NodeChild::Leaf(l) => {
+                        bsp.leaves
+                            .get(l as usize)
+                            .ok_or_else(|| out_of_range("Leaf", l))?
+                            .contents
+                    }
+                };
+            }
+
+            (node.plane, children)
+        } else {
+            let node = bsp
+                .clip_nodes
+                .get(num as usize)
+                .ok_or_else(|| out_of_range("Clip node", num))?;
+
+            let children = node.children.map(|child| match child {
+                ClipNodeChild::Node(n) => n as i32,
+                ClipNodeChild::Contents(c) => c,
+            });
+
+            (node.plane, children)
+        };
+
+        let plane = bsp
+            .planes
+            .get(plane_idx as usize)
+            .ok_or_else(|| out_of_range("Plane", plane_idx))?;
+
+        Ok((plane, children))
+    }
+}
+
+/// Distance kept between a trace's end point and the surface it hits
+pub const DIST_EPSILON: f32 = 0.03125;
+
+/// Plane a trace collided with, facing towards the start of the trace
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TracePlane {
+    pub normal: [f32; 3],
+    pub dist: f32,
+}
+
+/// Result of sweeping a hull from a start point to an end point
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Trace {
+    /// Portion of the move completed, 1.0 if nothing was hit
+    pub fraction: f32,
+
+    /// Final position
+    pub end: [f32; 3],
+
+    /// Plane of the surface hit, if any
+    pub plane: Option<TracePlane>,
+
+    /// Trace never left solid space
+    pub all_solid: bool,
+
+    /// Trace started in solid space
+    pub start_solid: bool,
+
+    /// Trace passed through empty space
+    pub in_open: bool,
+
+    /// Trace passed through liquid
+    pub in_water: bool,
+}
+
+/// Signed distance from a plane to a point, taking the axial shortcut used by
+/// the engine
+pub(crate) fn plane_dist(plane: &Plane, point: [f32; 3]) -> f32 {
+    match plane.kind {
+        PlaneKind::X => point[0] - plane.dist,
+        PlaneKind::Y => point[1] - plane.dist,
+        PlaneKind::Z => point[2] - plane.dist,
+        _ => {
+            plane.normal[0] * point[0]
+                + plane.normal[1] * point[1]
+                + plane.normal[2] * point[2]
+                - plane.dist
+        }
+    }
+}
+
+impl Bsp {
+    /// Obtain a collision hull of a submodel (0 for the world)
+    pub fn hull(&self, model: usize, index: usize) -> BinParseResult<Hull<'_>> {
+        let head_nodes = self
+            .models
+            .get(model)
+            .ok_or_else(|| out_of_range("Model", model as u32))?
+            .head_nodes;
+
+        let head_node = *head_nodes.get(index).ok_or_else(|| {
+            BinParseError::Parse(format!("Bad hull index {index}"))
+        })?;
+
+        Ok(Hull {
+            bsp: self,
+            index,
+            head_node,
+        })
+    }
+
+    /// Find the contents (see `bsp::contents`) of the world at a point for
+    /// the given hull, e.g. `Hull::PLAYER`
+    pub fn point_contents(
+        &self,
+        hull: usize,
+        point: [f32; 3],
+    ) -> BinParseResult<i32> {
+        self.hull(0, hull)?.point_contents(point)
+    }
+
+    /// Sweep a hull of a submodel (0 for the world) from `start` to `end`, see
+    /// `Hull::trace`
+    pub fn trace(
+        &self,
+        model: usize,
+        hull: usize,
+        start: [f32; 3],
+        end: [f32; 3],
+    ) -> BinParseResult<Trace> {
+        self.hull(model, hull)?.trace(start, end)
+    }
+}
@@ -0,0 +1,279 @@
+use super::test_fixtures::{leaf, model};
+use crate::{bsp, BinParseError};
+use bsp::{
+    contents, Bsp, ClipNode, ClipNodeChild, Hull, Model, Node, NodeChild,
+    Plane, PlaneKind, TracePlane, DIST_EPSILON,
+};
+
+fn horizontal_plane(height: f32) -> Plane {
+    Plane {
+        normal: [0.0, 0.0, 1.0],
+        dist: height,
+        kind: PlaneKind::Z,
+    }
+}
+
+/// Open space above z = 0, water between z = -32 and z = 0, and solid below.
+/// The player hull has a floor at z = 24.
+fn pool_bsp() -> Bsp {
+    Bsp {
+        planes: vec![
+            horizontal_plane(0.0),
+            horizontal_plane(-32.0),
+            horizontal_plane(24.0),
+        ],
+        nodes: vec![
+            Node {
+                plane: 0,
+                children: [NodeChild::Leaf(1), NodeChild::Node(1)],
+                mins: [-64.0; 3],
+                maxs: [64.0; 3],
+                first_face: 0,
+                face_count: 0,
+            },
+            Node {
+                plane: 1,
+                children: [NodeChild::Leaf(2), NodeChild::Leaf(0)],
+                mins: [-64.0; 3],
+                maxs: [64.0; 3],
+                first_face: 0,
+                face_count: 0,
+            },
+        ],
+        clip_nodes: vec![ClipNode {
+            plane: 2,
+            children: [
+                ClipNodeChild::Contents(contents::EMPTY),
+                ClipNodeChild::Contents(contents::SOLID),
+            ],
+        }],
+        leaves: vec![
+            leaf(contents::SOLID),
+            leaf(contents::EMPTY),
+            leaf(contents::WATER),
+        ],
+        models: vec![
+            model(0, 0),
+            Model {
+                head_nodes: [1, contents::SOLID, 0, 0],
+                ..model(0, 0)
+            },
+        ],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn point_hull_contents() {
+    let bsp = pool_bsp();
+
+    assert_eq!(
+        bsp.point_contents(Hull::POINT, [0.0, 0.0, 10.0]).unwrap(),
+        contents::EMPTY
+    );
+    assert_eq!(
+        bsp.point_contents(Hull::POINT, [0.0, 0.0, 0.0]).unwrap(),
+        contents::EMPTY
+    );
+    assert_eq!(
+        bsp.point_contents(Hull::POINT, [5.0, 5.0, -10.0]).unwrap(),
+        contents::WATER
+    );
+    assert_eq!(
+        bsp.point_contents(Hull::POINT, [0.0, 0.0, -40.0]).unwrap(),
+        contents::SOLID
+    );
+}
+
+#[test]
+fn player_hull_contents() {
+    let bsp = pool_bsp();
+
+    assert_eq!(
+        bsp.point_contents(Hull::PLAYER, [0.0, 0.0, 30.0]).unwrap(),
+        contents::EMPTY
+    );
+    assert_eq!(
+        bsp.point_contents(Hull::PLAYER, [0.0, 0.0, 20.0]).unwrap(),
+        contents::SOLID
+    );
+}
+
+#[test]
+fn submodel_hull_contents() {
+    let bsp = pool_bsp();
+    let hull = bsp.hull(1, Hull::PLAYER).unwrap();
+
+    assert_eq!(hull.head_node(), contents::SOLID);
+    assert_eq!(hull.point_contents([0.0; 3]).unwrap(), contents::SOLID);
+
+    let hull = bsp.hull(1, Hull::POINT).unwrap();
+    assert_eq!(hull.point_contents([0.0; 3]).unwrap(), contents::WATER);
+}
+
+#[test]
+fn bad_hull() {
+    let bsp = pool_bsp();
+
+    let err = bsp.point_contents(Hull::COUNT, [0.0; 3]).unwrap_err();
+    assert!(matches!(err, BinParseError::Parse(_)));
+
+    let err = bsp.hull(2, Hull::POINT).unwrap_err();
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn cyclic_hull() {
+    let mut bsp = pool_bsp();
+    bsp.clip_nodes[0].children[1] = ClipNodeChild::Node(0);
+    let err = bsp
+        .point_contents(Hull::PLAYER, [0.0, 0.0, 0.0])
+        .unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn hull_sizes() {
+    assert_eq!(Hull::clip_mins(Hull::POINT), [0.0; 3]);
+    assert_eq!(Hull::clip_maxs(Hull::PLAYER), [16.0, 16.0, 32.0]);
+    assert_eq!(Hull::clip_mins(Hull::SHAMBLER), [-32.0, -32.0, -24.0]);
+}
+
+#[test]
+fn trace_point_into_floor() {
+    let bsp = pool_bsp();
+    let trace = bsp
+        .trace(0, Hull::POINT, [8.0, 0.0, 100.0], [8.0, 0.0, -100.0])
+        .unwrap();
+
+    assert_eq!(trace.fraction, (132.0 - DIST_EPSILON) / 200.0);
+    assert!((trace.end[2] - (-32.0 + DIST_EPSILON)).abs() < 1e-4);
+    assert_eq!(
+        trace.plane,
+        Some(TracePlane {
+            normal: [0.0, 0.0, 1.0],
+            dist: -32.0,
+        })
+    );
+    assert!(!trace.all_solid);
+    assert!(!trace.start_solid);
+    assert!(trace.in_open);
+    assert!(trace.in_water);
+}
+
+#[test]
+fn trace_player_into_floor() {
+    let bsp = pool_bsp();
+    let trace = bsp
+        .trace(0, Hull::PLAYER, [0.0, 0.0, 64.0], [0.0, 0.0, 0.0])
+        .unwrap();
+
+    assert!(trace.fraction < 1.0);
+    assert!((trace.end[2] - (24.0 + DIST_EPSILON)).abs() < 1e-4);
+    assert_eq!(trace.plane.unwrap().normal, [0.0, 0.0, 1.0]);
+    assert!(trace.in_open);
+    assert!(!trace.in_water);
+}
+
+#[test]
+fn trace_up_through_ceiling() {
+    let mut bsp = pool_bsp();
+    bsp.clip_nodes[0].children.reverse();
+    let trace = bsp
+        .trace(0, Hull::PLAYER, [0.0, 0.0, 0.0], [0.0, 0.0, 64.0])
+        .unwrap();
+
+    assert!((trace.end[2] - (24.0 - DIST_EPSILON)).abs() < 1e-4);
+    assert_eq!(
+        trace.plane,
+        Some(TracePlane {
+            normal: [0.0, 0.0, -1.0],
+            dist: -24.0,
+        })
+    );
+}
+
+#[test]
+fn trace_in_open() {
+    let bsp = pool_bsp();
+    let trace = bsp
+        .trace(0, Hull::POINT, [-50.0, 0.0, 16.0], [50.0, 10.0, 16.0])
+        .unwrap();
+
+    assert_eq!(trace.fraction, 1.0);
+    assert_eq!(trace.end, [50.0, 10.0, 16.0]);
+    assert!(trace.plane.is_none());
+    assert!(trace.in_open);
+    assert!(!trace.all_solid);
+}
+
+#[test]
+fn trace_all_solid() {
+    let bsp = pool_bsp();
+    let trace = bsp
+        .trace(0, Hull::POINT, [0.0, 0.0, -50.0], [0.0, 0.0, -60.0])
+        .unwrap();
+
+    assert!(trace.all_solid);
+    assert!(trace.start_solid);
+    assert!(trace.plane.is_none());
+}
+
+#[test]
+fn trace_out_of_solid() {
+    let bsp = pool_bsp();
+    let trace = bsp
+        .trace(0, Hull::POINT, [0.0, 0.0, -50.0], [0.0, 0.0, 10.0])
+        .unwrap();
+
+    assert!(trace.start_solid);
+    assert!(!trace.all_solid);
+    assert_eq!(trace.fraction, 1.0);
+}
+
+#[test]
+fn trace_solid_submodel() {
+    let bsp = pool_bsp();
+    let trace = bsp
+        .trace(1, Hull::PLAYER, [0.0, 0.0, 0.0], [0.0, 0.0, 64.0])
+        .unwrap();
+
+    assert!(trace.all_solid);
+    assert!(trace.start_solid);
+}
+
+#[test]
+fn trace_cyclic_hull() {
+    let mut bsp = pool_bsp();
+    bsp.clip_nodes[0].children[1] = ClipNodeChild::Node(0);
+    let err = bsp
+        .trace(0, Hull::PLAYER, [0.0, 0.0, 64.0], [0.0, 0.0, 0.0])
+        .unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn trace_deep_hull() {
+    let mut bsp = pool_bsp();
+    let depth = 100_000;
+
+    bsp.clip_nodes = (0..depth)
+        .map(|idx| ClipNode {
+            plane: 2,
+            children: [
+                ClipNodeChild::Node(idx + 1),
+                ClipNodeChild::Contents(contents::SOLID),
+            ],
+        })
+        .collect();
+    bsp.clip_nodes[depth as usize - 1].children[0] =
+        ClipNodeChild::Contents(contents::EMPTY);
+
+    let err = bsp
+        .trace(0, Hull::PLAYER, [0.0, 0.0, 64.0], [0.0, 0.0, 32.0])
+        .unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
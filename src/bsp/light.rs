@@ -0,0 +1,338 @@
+use super::decoded::out_of_range;
+use super::{Bsp, Face, Hull, LightmapExtents, Plane, TexInfo, LUXEL_SIZE};
+use crate::qmap::{Entity, QuakeMap};
+use crate::{BinParseError, BinParseResult};
+use std::string::String;
+use std::vec::Vec;
+
+/// Intensity of lights without a `light` key
+pub const DEFAULT_LIGHT: f32 = 300.0;
+
+/// Distance luxels are moved off of their face before tracing to lights, so
+/// that they are not shadowed by the face itself
+const SAMPLE_OFFSET: f32 = 1.0;
+
+/// Maximum light styles per face
+const MAX_STYLES: usize = 4;
+
+/// Style marking an unused style slot of a face
+const NO_STYLE: u8 = 255;
+
+/// Omnidirectional light with linear falloff, as placed by `light` entities
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointLight {
+    pub origin: [f32; 3],
+
+    /// Brightness at the light's origin, which falls off by 1 per unit of
+    /// distance
+    pub intensity: f32,
+
+    /// Light style the light contributes to, e.g. for flickering lights
+    pub style: u8,
+}
+
+impl PointLight {
+    /// Collects the lights of a map, e.g. as obtained from
+    /// `Parser::parse_entities`.  Every entity with a classname starting with
+    /// `light` is a light, with `light` and `style` keys defaulting to 300 and
+    /// 0 respectively.  Fails if a light has a missing or malformed origin, or
+    /// malformed keys.  Style 255 is rejected, since it marks unused style
+    /// slots.
+    pub fn from_map(map: &QuakeMap) -> BinParseResult<Vec<PointLight>> {
+        map.entities
+            .iter()
+            .filter(|entity| {
+                value_of(entity, "classname")
+                    .is_some_and(|classname| classname.starts_with("light"))
+            })
+            .map(PointLight::from_entity)
+            .collect()
+    }
+
+    fn from_entity(entity: &Entity) -> BinParseResult<PointLight> {
+        let origin_value = value_of(entity, "origin").unwrap_or("");
+        let bad_key = |key: &str, value: &str| {
+            BinParseError::Parse(format!("Bad light {key} `{value}`"))
+        };
+
+        let coords = origin_value
+            .split_whitespace()
+            .map(str::parse::<f32>)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| bad_key("origin", origin_value))?;
+
+        let origin = <[f32; 3]>::try_from(coords)
+            .map_err(|_| bad_key("origin", origin_value))?;
+
+        let intensity = match value_of(entity, "light") {
+            Some(value) => value
+                .trim()
+                .parse()
+                .map_err(|_| bad_key("intensity", value))?,
+            None => DEFAULT_LIGHT,
+        };
+
+        let style = match value_of(entity, "style") {
+            Some(value) => value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|&style| style != NO_STYLE)
+                .ok_or_else(|| bad_key("style", value))?,
+            None => 0,
+        };
+
+        Ok(PointLight {
+            origin,
+            intensity,
+            style,
+        })
+    }
+}
+
+// World position of each luxel of a face, in row-major order, moved off of
+// the front side of the face.  Luxels whose position can't be found (if the
+// texture axes are parallel to the face) are `None`.
+struct FaceSamples {
+    normal: [f32; 3],
+    points: Vec<Option<[f32; 3]>>,
+}
+
+pub(super) fn value_of<'a>(entity: &'a Entity, key: &str) -> Option<&'a str> {
+    entity
+        .edict
+        .iter()
+        .find(|(k, _)| k.as_bytes() == key.as_bytes())
+        .and_then(|(_, v)| v.to_str().ok())
+}
+
+impl Bsp {
+    /// Replaces the light lump with lightmaps computed from `lights`, and
+    /// updates the light offset and styles of every face.  Each luxel receives
+    /// light from each point light it can see, scaled by the light's
+    /// distance and by the angle at which it strikes the face.  Visibility is
+    /// found by tracing against the world's point hull.  Faces receiving no
+    /// light, and faces with special textures, are left without lightmaps.
+    /// Fails if a face is lit by more than 4 styles, or a light has style 255,
+    /// which marks unused style slots.
+    ///
+    /// This is a simple reference implementation, and is not intended to
+    /// match the output of any light compiler.
+    pub fn bake_lighting(
+        &mut self,
+        lights: &[PointLight],
+    ) -> BinParseResult<()> {
+        let mut styles: Vec<u8> =
+            lights.iter().map(|light| light.style).collect();
+        styles.sort_unstable();
+        styles.dedup();
+
+        if styles.last() == Some(&NO_STYLE) {
+            return Err(BinParseError::Parse(format!(
+                "Bad light style {NO_STYLE}"
+            )));
+        }
+
+        let channels = self.light_channels();
+        let mut lighting = Vec::new();
+        let mut faces = self.faces.clone();
+
+        for face in &mut faces {
+            face.light_offset = -1;
+            face.styles = [NO_STYLE; MAX_STYLES];
+
+            let Some(extents) = self.lightmap_extents(face)? else {
+                continue;
+            };
+
+            let samples = self.face_samples(face, &extents)?;
+            let mut slot = 0;
+
+            for &style in &styles {
+                let style_lights = lights.iter().filter(|l| l.style == style);
+                let luxels = self.illuminate(&samples, style_lights)?;
+
+                if luxels.iter().all(|&luxel| luxel == 0) {
+                    continue;
+                }
+
+                if slot == MAX_STYLES {
+                    return Err(BinParseError::Parse(format!(
+                        "Face on plane {} lit by more than {MAX_STYLES} \
+                         light styles",
+                        face.plane
+                    )));
+                }
+
+                if slot == 0 {
+                    face.light_offset =
+                        i32::try_from(lighting.len()).map_err(|_| {
+                            BinParseError::Parse(String::from(
+                                "Light lump too large",
+                            ))
+                        })?;
+                }
+
+                face.styles[slot] = style;
+                slot += 1;
+
+                for luxel in luxels {
+                    lighting.extend((0..channels).map(|_| luxel));
+                }
+            }
+        }
+
+        self.faces = faces;
+        self.lighting = lighting;
+        Ok(())
+    }
+
+    fn face_samples(
+        &self,
+        face: &Face,
+        extents: &LightmapExtents,
+    ) -> BinParseResult<FaceSamples> {
+        let tex_info = self
+            .tex_info
+            .get(face.tex_info as usize)
+            .ok_or_else(|| out_of_range("Texture info", face.tex_info))?;
+        let plane = self
+            .planes
+            .get(face.plane as usize)
+            .ok_or_else(|| out_of_range("Plane", face.plane))?;
+
+        let (normal, dist) = if face.side == 0 {
+            (plane.normal, plane.dist)
+        } else {
+            (plane.normal.map(|n| -n), -plane.dist)
+        };
+
+        let facing = Plane {
+            normal,
+            dist,
+            ..*plane
+        };
+
+        let offset = normal.map(|n| n * SAMPLE_OFFSET);
+        let mut points = Vec::with_capacity(extents.luxel_count());
+
+        for t in 0..extents.size[1] {
+            for s in 0..extents.size[0] {
+                let tex_coords = [(0, s), (1, t)].map(|(axis, luxel)| {
+                    extents.texture_mins[axis] as f32
+                        + (luxel * LUXEL_SIZE) as f32
+                });
+
+                let point = texture_to_world(tex_info, &facing, tex_coords)
+                    .map(|point| add(point, offset));
+
+                points.push(point);
+            }
+        }
+
+        Ok(FaceSamples { normal, points })
+    }
+
+    // Brightness of each luxel from a set of lights, clamped to a byte
+    fn illuminate<'a>(
+        &self,
+        samples: &FaceSamples,
+        lights: impl Iterator<Item = &'a PointLight> + Clone,
+    ) -> BinParseResult<Vec<u8>> {
+        let normal = samples.normal;
+
+        samples
+            .points
+            .iter()
+            .map(|point| {
+                let Some(point) = point else {
+                    return Ok(0);
+                };
+
+                let mut total = 0.0f32;
+
+                for light in lights.clone() {
+                    let to_light = sub(light.origin, *point);
+                    let dist = dot(to_light, to_light).sqrt();
+
+                    if dist >= light.intensity || dist == 0.0 {
+                        continue;
+                    }
+
+                    let cos = dot(normal, to_light) / dist;
+
+                    if cos <= 0.0 {
+                        continue;
+                    }
+
+                    let trace =
+                        self.trace(0, Hull::POINT, *point, light.origin)?;
+
+                    if trace.start_solid || trace.fraction < 1.0 {
+                        continue;
+                    }
+
+                    total += (light.intensity - dist) * (0.5 + 0.5 * cos);
+                }
+
+                Ok(total.round().clamp(0.0, 255.0) as u8)
+            })
+            .collect()
+    }
+}
+
+// Solve for the point on a plane with the given texture coordinates
+fn texture_to_world(
+    tex_info: &TexInfo,
+    plane: &Plane,
+    [s, t]: [f32; 2],
+) -> Option<[f32; 3]> {
+    let s_axis = [
+        tex_info.vecs[0][0],
+        tex_info.vecs[0][1],
+        tex_info.vecs[0][2],
+    ];
+    let t_axis = [
+        tex_info.vecs[1][0],
+        tex_info.vecs[1][1],
+        tex_info.vecs[1][2],
+    ];
+    let normal = plane.normal;
+
+    let t_cross_n = cross(t_axis, normal);
+    let det = dot(s_axis, t_cross_n);
+
+    if det.abs() < f32::EPSILON {
+        return None;
+    }
+
+    let rhs = [s - tex_info.vecs[0][3], t - tex_info.vecs[1][3], plane.dist];
+    let columns = [t_cross_n, cross(normal, s_axis), cross(s_axis, t_axis)];
+    let mut point = [0.0f32; 3];
+
+    for (value, column) in rhs.into_iter().zip(columns) {
+        point = add(point, column.map(|c| c * value / det));
+    }
+
+    Some(point)
+}
+
+fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
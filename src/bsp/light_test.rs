@@ -0,0 +1,240 @@
+use super::test_fixtures::leaf;
+use crate::{bsp, qmap, BinParseError};
+use bsp::{
+    contents, tex_flags, Bsp, Edge, Face, Model, Node, NodeChild, Plane,
+    PlaneKind, PointLight, TexInfo, DEFAULT_LIGHT, HL_BSP_VERSION,
+};
+use std::vec::Vec;
+
+fn node(plane: u32, children: [NodeChild; 2]) -> Node {
+    Node {
+        plane,
+        children,
+        mins: [-128.0; 3],
+        maxs: [128.0; 3],
+        first_face: 0,
+        face_count: 0,
+    }
+}
+
+/// Floor at z = 0 with a step 16 units high for x > 32.  A single floor face
+/// covers x from -64 to 32 and y from 0 to 32, with texture axes along X and
+/// Y so that its lightmap is 7 x 3 luxels.
+fn step_bsp() -> Bsp {
+    Bsp {
+        planes: vec![
+            Plane {
+                normal: [0.0, 0.0, 1.0],
+                dist: 0.0,
+                kind: PlaneKind::Z,
+            },
+            Plane {
+                normal: [1.0, 0.0, 0.0],
+                dist: 32.0,
+                kind: PlaneKind::X,
+            },
+            Plane {
+                normal: [0.0, 0.0, 1.0],
+                dist: 16.0,
+                kind: PlaneKind::Z,
+            },
+        ],
+        vertices: vec![
+            [-64.0, 0.0, 0.0],
+            [32.0, 0.0, 0.0],
+            [32.0, 32.0, 0.0],
+            [-64.0, 32.0, 0.0],
+        ],
+        nodes: vec![
+            node(0, [NodeChild::Node(1), NodeChild::Leaf(0)]),
+            node(1, [NodeChild::Node(2), NodeChild::Leaf(1)]),
+            node(2, [NodeChild::Leaf(1), NodeChild::Leaf(0)]),
+        ],
+        leaves: vec![leaf(contents::SOLID), leaf(contents::EMPTY)],
+        tex_info: vec![
+            TexInfo {
+                vecs: [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0]],
+                mip_texture: 0,
+                flags: 0,
+            },
+            TexInfo {
+                vecs: [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0]],
+                mip_texture: 0,
+                flags: tex_flags::SPECIAL,
+            },
+        ],
+        faces: vec![Face {
+            plane: 0,
+            side: 0,
+            first_edge: 0,
+            edge_count: 4,
+            tex_info: 0,
+            styles: [0, 255, 255, 255],
+            light_offset: 0,
+        }],
+        lighting: vec![7; 21],
+        edges: vec![
+            Edge { vertices: [0, 1] },
+            Edge { vertices: [1, 2] },
+            Edge { vertices: [2, 3] },
+            Edge { vertices: [3, 0] },
+        ],
+        surf_edges: vec![0, 1, 2, 3],
+        models: vec![Model {
+            mins: [-128.0; 3],
+            maxs: [128.0; 3],
+            origin: [0.0; 3],
+            head_nodes: [0, 0, 0, 0],
+            vis_leaf_count: 1,
+            first_face: 0,
+            face_count: 1,
+        }],
+        ..Default::default()
+    }
+}
+
+fn light(origin: [f32; 3], intensity: f32, style: u8) -> PointLight {
+    PointLight {
+        origin,
+        intensity,
+        style,
+    }
+}
+
+#[test]
+fn point_lights_from_map() {
+    let map = qmap::parse(
+        &mut &br#"
+        { "classname" "worldspawn" }
+        { "classname" "light" "origin" "0 16 33" }
+        { "classname" "light_fluoro" "origin" "-8 0 64.5" "light" "200"
+          "style" "5" }
+        { "classname" "info_player_start" "origin" "0 0 24" }
+        "#[..],
+    )
+    .unwrap();
+
+    assert_eq!(
+        PointLight::from_map(&map).unwrap(),
+        [
+            light([0.0, 16.0, 33.0], DEFAULT_LIGHT, 0),
+            light([-8.0, 0.0, 64.5], 200.0, 5),
+        ]
+    );
+}
+
+#[test]
+fn point_light_bad_origin() {
+    let map =
+        qmap::parse(&mut &br#"{ "classname" "light" "origin" "0 16" }"#[..])
+            .unwrap();
+
+    let err = PointLight::from_map(&map).unwrap_err();
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn point_light_no_style() {
+    let map = qmap::parse(
+        &mut &br#"{ "classname" "light" "origin" "0 0 0" "style" "255" }"#[..],
+    )
+    .unwrap();
+
+    let err = PointLight::from_map(&map).unwrap_err();
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn bake_falloff() {
+    let mut bsp = step_bsp();
+    bsp.bake_lighting(&[light([0.0, 16.0, 33.0], 100.0, 0)])
+        .unwrap();
+
+    assert_eq!(bsp.faces[0].light_offset, 0);
+    assert_eq!(bsp.faces[0].styles, [0, 255, 255, 255]);
+    assert_eq!(bsp.lighting.len(), 21);
+
+    let luxel = |s: usize, t: usize| bsp.lighting[t * 7 + s];
+
+    // Directly below the light, 32 units away
+    assert_eq!(luxel(4, 1), 68);
+
+    // 64 units to the side: (100 - 71.55) * (0.5 + 0.5 * 32 / 71.55)
+    assert_eq!(luxel(0, 1), 21);
+
+    // Falloff is symmetric about the light
+    assert_eq!(luxel(3, 0), luxel(5, 2));
+}
+
+#[test]
+fn bake_shadows_and_styles() {
+    let mut bsp = step_bsp();
+    bsp.bake_lighting(&[
+        light([48.0, 16.0, 24.0], 300.0, 3),
+        light([0.0, 16.0, 33.0], 100.0, 0),
+    ])
+    .unwrap();
+
+    assert_eq!(bsp.faces[0].styles, [0, 3, 255, 255]);
+    assert_eq!(bsp.lighting.len(), 42);
+
+    let style_3 = &bsp.lighting[21..];
+
+    // The step blocks the light from reaching the floor near it
+    assert_eq!(style_3[7 + 5], 0);
+    assert!(style_3[7] > 0);
+}
+
+#[test]
+fn bake_too_many_styles() {
+    let mut bsp = step_bsp();
+    let lights: Vec<_> = (0..5)
+        .map(|style| light([0.0, 16.0, 33.0], 100.0, style))
+        .collect();
+
+    bsp.bake_lighting(&lights[..4]).unwrap();
+    assert_eq!(bsp.faces[0].styles, [0, 1, 2, 3]);
+
+    let err = bsp.bake_lighting(&lights).unwrap_err();
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn bake_no_style() {
+    let mut bsp = step_bsp();
+    let err = bsp
+        .bake_lighting(&[light([0.0, 16.0, 33.0], 100.0, 255)])
+        .unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn bake_unlit_faces() {
+    let mut bsp = step_bsp();
+    bsp.bake_lighting(&[light([0.0, 16.0, -33.0], 300.0, 0)])
+        .unwrap();
+
+    assert_eq!(bsp.faces[0].light_offset, -1);
+    assert_eq!(bsp.faces[0].styles, [255; 4]);
+    assert_eq!(bsp.lighting, Vec::<u8>::new());
+
+    let mut bsp = step_bsp();
+    bsp.faces[0].tex_info = 1;
+    bsp.bake_lighting(&[light([0.0, 16.0, 33.0], 300.0, 0)])
+        .unwrap();
+
+    assert_eq!(bsp.faces[0].light_offset, -1);
+    assert!(bsp.lighting.is_empty());
+}
+
+#[test]
+fn bake_hl_rgb() {
+    let mut bsp = step_bsp();
+    bsp.version = HL_BSP_VERSION;
+    bsp.bake_lighting(&[light([0.0, 16.0, 33.0], 100.0, 0)])
+        .unwrap();
+
+    assert_eq!(bsp.lighting.len(), 63);
+    assert_eq!(&bsp.lighting[((7 + 4) * 3)..((7 + 5) * 3)], &[68, 68, 68]);
+}
@@ -0,0 +1,240 @@
+use super::decoded::out_of_range;
+use super::{tex_flags, Bsp, Face, HL_BSP_VERSION};
+use crate::lump::Image;
+use crate::{BinParseError, BinParseResult};
+use std::vec::Vec;
+
+/// Size of a luxel (lightmap sample) in texels
+pub const LUXEL_SIZE: u32 = 16;
+
+// Largest lightmap width or height accepted, in luxels.  The original engine
+// stops at 18, but later engines and compilers allow much larger surfaces.
+const MAX_LIGHTMAP_SIZE: f64 = 256.0;
+
+/// Placement of a face's lightmap in texture space
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LightmapExtents {
+    /// Texture coordinates of the first luxel, snapped to the luxel grid
+    pub texture_mins: [i32; 2],
+
+    /// Width and height of the lightmap in luxels
+    pub size: [u32; 2],
+}
+
+impl LightmapExtents {
+    /// Number of luxels in a single lightmap
+    pub fn luxel_count(&self) -> usize {
+        self.size[0] as usize * self.size[1] as usize
+    }
+}
+
+/// Lightmap for a single light style of a face.  Image pixels are light
+/// intensities (0 for dark, 255 for fully bright) rather than palette indices.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Lightmap {
+    pub style: u8,
+    pub image: Image,
+}
+
+/// Colored lightmap for a single light style of a face
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RgbLightmap {
+    pub style: u8,
+
+    /// Width and height in luxels
+    pub size: [u32; 2],
+
+    /// Luxels in row-major order
+    pub luxels: Vec<[u8; 3]>,
+}
+
+// Light style and light lump bytes for each of a face's lightmaps
+type StyleSamples<'a> = Vec<(u8, &'a [u8])>;
+
+impl Bsp {
+    /// Compute the lightmap extents of a face from its texture info, the same
+    /// way the engine does.  Returns `None` for faces with special (e.g.
+    /// liquid or sky) textures, which are not lightmapped.
+    pub fn lightmap_extents(
+        &self,
+        face: &Face,
+    ) -> BinParseResult<Option<LightmapExtents>> {
+        let tex_info = self
+            .tex_info
+            .get(face.tex_info as usize)
+            .ok_or_else(|| out_of_range("Texture info", face.tex_info))?;
+
+        if tex_info.flags & tex_flags::SPECIAL != 0 {
+            return Ok(None);
+        }
+
+        let mut mins = [f64::INFINITY; 2];
+        let mut maxs = [f64::NEG_INFINITY; 2];
+
+        for point in self.winding(face)? {
+            for axis in 0..2 {
+                let vec = tex_info.vecs[axis];
+                let value = (0..3)
+                    .map(|i| f64::from(point[i]) * f64::from(vec[i]))
+                    .sum::<f64>()
+                    + f64::from(vec[3]);
+
+                mins[axis] = mins[axis].min(value);
+                maxs[axis] = maxs[axis].max(value);
+            }
+        }
+
+        if mins[0] > maxs[0] {
+            return Err(BinParseError::Parse(format!(
+                "Face on plane {} has no vertices",
+                face.plane
+            )));
+        }
+
+        let bad_extents = || {
+            BinParseError::Parse(format!(
+                "Face on plane {} has bad lightmap extents",
+                face.plane
+            ))
+        };
+
+        let luxel_size = f64::from(LUXEL_SIZE);
+        let mut texture_mins = [0i32; 2];
+        let mut size = [0u32; 2];
+
+        for axis in 0..2 {
+            let luxel_min = (mins[axis] / luxel_size).floor();
+            let luxel_max = (maxs[axis] / luxel_size).ceil();
+            let span = luxel_max - luxel_min;
+            let texture_min = luxel_min * luxel_size;
+
+            if !(0.0..MAX_LIGHTMAP_SIZE).contains(&span)
+                || !(f64::from(i32::MIN)..=f64::from(i32::MAX))
+                    .contains(&texture_min)
+            {
+                return Err(bad_extents());
+            }
+
+            texture_mins[axis] = texture_min as i32;
+            size[axis] = span as u32 + 1;
+        }
+
+        Ok(Some(LightmapExtents { texture_mins, size }))
+    }
+
+    /// Extract the lightmaps of a face, one per light style in use.  Faces
+    /// without lighting data yield no lightmaps.  RGB lighting (Half-Life) is
+    /// averaged to a single intensity.
+    pub fn face_lightmaps(&self, face: &Face) -> BinParseResult<Vec<Lightmap>> {
+        let Some((extents, styles)) = self.face_light_samples(face)? else {
+            return Ok(Vec::new());
+        };
+
+        Ok(styles
+            .into_iter()
+            .map(|(style, samples)| {
+                let pixels = match self.light_channels() {
+                    1 => samples.to_vec(),
+                    _ => samples
+                        .chunks_exact(3)
+                        .map(|rgb| {
+                            let sum: u32 =
+                                rgb.iter().map(|&c| u32::from(c)).sum();
+                            (sum / 3) as u8
+                        })
+                        .collect(),
+                };
+
+                Lightmap {
+                    style,
+                    image: Image::from_pixels(
+                        extents.size[0],
+                        pixels.into_boxed_slice(),
+                    ),
+                }
+            })
+            .collect())
+    }
+
+    /// Extract the lightmaps of a face in color, one per light style in use.
+    /// Monochrome lighting is expanded to grey.
+    pub fn face_rgb_lightmaps(
+        &self,
+        face: &Face,
+    ) -> BinParseResult<Vec<RgbLightmap>> {
+        let Some((extents, styles)) = self.face_light_samples(face)? else {
+            return Ok(Vec::new());
+        };
+
+        Ok(styles
+            .into_iter()
+            .map(|(style, samples)| {
+                let luxels = match self.light_channels() {
+                    1 => samples.iter().map(|&c| [c; 3]).collect(),
+                    _ => samples
+                        .chunks_exact(3)
+                        .map(|rgb| rgb.try_into().unwrap())
+                        .collect(),
+                };
+
+                RgbLightmap {
+                    style,
+                    size: extents.size,
+                    luxels,
+                }
+            })
+            .collect())
+    }
+
+    // Bytes per luxel in the light lump
+    pub(super) fn light_channels(&self) -> usize {
+        if self.version == HL_BSP_VERSION {
+            3
+        } else {
+            1
+        }
+    }
+
+    fn face_light_samples(
+        &self,
+        face: &Face,
+    ) -> BinParseResult<Option<(LightmapExtents, StyleSamples<'_>)>> {
+        let Ok(offset) = usize::try_from(face.light_offset) else {
+            return Ok(None);
+        };
+
+        let Some(extents) = self.lightmap_extents(face)? else {
+            return Ok(None);
+        };
+
+        let cut_off = |start: usize| {
+            BinParseError::Parse(format!("Lightmap at offset {start} cut off"))
+        };
+
+        let length = extents
+            .luxel_count()
+            .checked_mul(self.light_channels())
+            .ok_or_else(|| cut_off(offset))?;
+
+        let styles = face
+            .styles
+            .iter()
+            .take_while(|&&style| style != 255)
+            .enumerate()
+            .map(|(slot, &style)| {
+                let start = slot
+                    .checked_mul(length)
+                    .and_then(|skip| offset.checked_add(skip))
+                    .ok_or_else(|| cut_off(offset))?;
+                let samples = start
+                    .checked_add(length)
+                    .and_then(|end| self.lighting.get(start..end))
+                    .ok_or_else(|| cut_off(start))?;
+
+                Ok((style, samples))
+            })
+            .collect::<BinParseResult<_>>()?;
+
+        Ok(Some((extents, styles)))
+    }
+}
@@ -0,0 +1,183 @@
+use crate::bsp;
+use crate::BinParseError;
+use bsp::{Bsp, Edge, Face, LightmapExtents, Plane, PlaneKind, TexInfo};
+use std::vec::Vec;
+
+fn quad_bsp(s_offset: f32, styles: [u8; 4], light_offset: i32) -> Bsp {
+    Bsp {
+        planes: vec![Plane {
+            normal: [0.0, 0.0, 1.0],
+            dist: 0.0,
+            kind: PlaneKind::Z,
+        }],
+        vertices: vec![
+            [0.0, 0.0, 0.0],
+            [64.0, 0.0, 0.0],
+            [64.0, 40.0, 0.0],
+            [0.0, 40.0, 0.0],
+        ],
+        tex_info: vec![TexInfo {
+            vecs: [[1.0, 0.0, 0.0, s_offset], [0.0, 1.0, 0.0, 0.0]],
+            mip_texture: 0,
+            flags: 0,
+        }],
+        faces: vec![Face {
+            plane: 0,
+            side: 0,
+            first_edge: 0,
+            edge_count: 4,
+            tex_info: 0,
+            styles,
+            light_offset,
+        }],
+        lighting: (0..=255).collect(),
+        edges: vec![
+            Edge { vertices: [0, 1] },
+            Edge { vertices: [1, 2] },
+            Edge { vertices: [2, 3] },
+            Edge { vertices: [3, 0] },
+        ],
+        surf_edges: vec![0, 1, 2, 3],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn aligned_extents() {
+    let bsp = quad_bsp(0.0, [0, 255, 255, 255], 0);
+    let extents = bsp.lightmap_extents(&bsp.faces[0]).unwrap().unwrap();
+
+    assert_eq!(
+        extents,
+        LightmapExtents {
+            texture_mins: [0, 0],
+            size: [5, 4],
+        }
+    );
+    assert_eq!(extents.luxel_count(), 20);
+}
+
+#[test]
+fn unaligned_extents() {
+    let bsp = quad_bsp(-8.0, [0, 255, 255, 255], 0);
+    let extents = bsp.lightmap_extents(&bsp.faces[0]).unwrap().unwrap();
+
+    assert_eq!(
+        extents,
+        LightmapExtents {
+            texture_mins: [-16, 0],
+            size: [6, 4],
+        }
+    );
+}
+
+#[test]
+fn special_extents() {
+    let mut bsp = quad_bsp(0.0, [0, 255, 255, 255], 0);
+    bsp.tex_info[0].flags = bsp::tex_flags::SPECIAL;
+
+    assert!(bsp.lightmap_extents(&bsp.faces[0]).unwrap().is_none());
+    assert!(bsp.face_lightmaps(&bsp.faces[0]).unwrap().is_empty());
+}
+
+#[test]
+fn oversized_extents() {
+    let mut bsp = quad_bsp(0.0, [0, 255, 255, 255], 0);
+    bsp.tex_info[0].vecs[0][0] = 1.0e30;
+
+    let err = bsp.lightmap_extents(&bsp.faces[0]).unwrap_err();
+    assert!(matches!(err, BinParseError::Parse(_)));
+
+    let err = bsp.face_lightmaps(&bsp.faces[0]).unwrap_err();
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn non_finite_extents() {
+    let mut bsp = quad_bsp(f32::NAN, [0, 255, 255, 255], 0);
+    let err = bsp.lightmap_extents(&bsp.faces[0]).unwrap_err();
+    assert!(matches!(err, BinParseError::Parse(_)));
+
+    bsp.tex_info[0].vecs[0][3] = f32::INFINITY;
+    let err = bsp.lightmap_extents(&bsp.faces[0]).unwrap_err();
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn lightmap_offset_overflow() {
+    let bsp = quad_bsp(0.0, [0, 1, 2, 3], i32::MAX);
+    let err = bsp.face_lightmaps(&bsp.faces[0]).unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn styled_lightmaps() {
+    let bsp = quad_bsp(0.0, [0, 10, 255, 255], 3);
+    let lightmaps = bsp.face_lightmaps(&bsp.faces[0]).unwrap();
+
+    assert_eq!(lightmaps.len(), 2);
+    assert_eq!(lightmaps[0].style, 0);
+    assert_eq!(lightmaps[1].style, 10);
+
+    for (slot, lightmap) in lightmaps.iter().enumerate() {
+        let first = 3 + 20 * slot as u8;
+        assert_eq!(lightmap.image.width(), 5);
+        assert_eq!(lightmap.image.height(), 4);
+        assert_eq!(
+            lightmap.image.pixels(),
+            (first..(first + 20)).collect::<Vec<_>>()
+        );
+    }
+}
+
+#[test]
+fn unlit_lightmaps() {
+    let bsp = quad_bsp(0.0, [0, 255, 255, 255], -1);
+    assert!(bsp.face_lightmaps(&bsp.faces[0]).unwrap().is_empty());
+}
+
+#[test]
+fn lightmap_cut_off() {
+    let bsp = quad_bsp(0.0, [0, 1, 2, 3], 200);
+    let err = bsp.face_lightmaps(&bsp.faces[0]).unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn rgb_lightmaps() {
+    let mut bsp = quad_bsp(0.0, [0, 255, 255, 255], 6);
+    bsp.version = bsp::HL_BSP_VERSION;
+    let lightmaps = bsp.face_rgb_lightmaps(&bsp.faces[0]).unwrap();
+
+    assert_eq!(lightmaps.len(), 1);
+    assert_eq!(lightmaps[0].style, 0);
+    assert_eq!(lightmaps[0].size, [5, 4]);
+    assert_eq!(lightmaps[0].luxels.len(), 20);
+    assert_eq!(lightmaps[0].luxels[0], [6, 7, 8]);
+    assert_eq!(lightmaps[0].luxels[19], [63, 64, 65]);
+
+    let grey = bsp.face_lightmaps(&bsp.faces[0]).unwrap();
+
+    assert_eq!(grey[0].image.pixels()[0], 7);
+    assert_eq!(grey[0].image.pixels()[19], 64);
+}
+
+#[test]
+fn rgb_lightmaps_cut_off() {
+    let mut bsp = quad_bsp(0.0, [0, 1, 2, 255], 100);
+    bsp.version = bsp::HL_BSP_VERSION;
+    let err = bsp.face_rgb_lightmaps(&bsp.faces[0]).unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn grey_rgb_lightmaps() {
+    let bsp = quad_bsp(0.0, [0, 255, 255, 255], 3);
+    let lightmaps = bsp.face_rgb_lightmaps(&bsp.faces[0]).unwrap();
+
+    assert_eq!(lightmaps[0].luxels[0], [3, 3, 3]);
+    assert_eq!(lightmaps[0].luxels[19], [22, 22, 22]);
+}
@@ -2,14 +2,159 @@ mod repr;
 
 mod parser;
 
-pub use repr::{Entry, EntryOffset, BSP2_VERSION, BSP_VERSION};
+mod decoded;
 
-pub(crate) use repr::Head;
+mod lightmap;
+
+mod vis;
+
+mod hull;
+
+mod writer;
+
+mod bspx;
+
+mod gltf;
+
+mod validate;
+
+mod checksum;
+
+mod light;
+
+mod portal;
+
+mod compile;
+
+/// Quake II BSP (IBSP version 38) reading
+pub mod q2;
+
+/// Quake 3 BSP (IBSP version 46) reading
+pub mod q3;
+
+pub use repr::{
+    ClipNode, ClipNodeChild, Edge, Entry, EntryOffset, Face, Leaf, MarkSurface,
+    Model, Node, NodeChild, Plane, PlaneKind, SurfEdge, TexInfo, Vertex,
+    BSP2_RMQ_VERSION, BSP2_VERSION, BSP_VERSION, HL_BSP_VERSION,
+};
+
+pub(crate) use repr::{Head, Record};
 
 pub use parser::Parser;
 
+pub use decoded::{Bsp, Polygon};
+
+pub use lightmap::{Lightmap, LightmapExtents, RgbLightmap, LUXEL_SIZE};
+
+pub use vis::{decompress_vis, Pvs};
+
+pub use hull::{Hull, Trace, TracePlane, DIST_EPSILON};
+
+pub use writer::Writer;
+
+pub use gltf::GltfExport;
+
+pub use validate::{ValidationIssue, ValidationReport};
+
+pub use checksum::{block_checksum, MapChecksums};
+
+pub use light::{PointLight, DEFAULT_LIGHT};
+
+pub use portal::{compress_vis, Portal, PortalFile};
+
+pub use bspx::{
+    bspx_lumps, BspxBrush, BspxBrushFace, BspxEntry, BspxLump,
+    BspxModelBrushes, DecoupledLightmap, BSPX_MAGIC,
+};
+
+/// Texture info flags
+pub mod tex_flags {
+    /// Surface is not lightmapped and is warped or drawn as sky, e.g. liquids
+    pub const SPECIAL: u32 = 1;
+}
+
+/// Leaf and clip node contents
+pub mod contents {
+    /// Open space
+    pub const EMPTY: i32 = -1;
+
+    /// Solid space, e.g. outside the map or within a wall
+    pub const SOLID: i32 = -2;
+
+    pub const WATER: i32 = -3;
+
+    pub const SLIME: i32 = -4;
+
+    pub const LAVA: i32 = -5;
+
+    pub const SKY: i32 = -6;
+
+    /// Used by compilers for origin brushes, not found in compiled maps
+    pub const ORIGIN: i32 = -7;
+
+    /// Used by compilers for clip brushes, not found in compiled maps
+    pub const CLIP: i32 = -8;
+
+    /// Water current flowing along +X
+    pub const CURRENT_0: i32 = -9;
+
+    /// Water current flowing along +Y
+    pub const CURRENT_90: i32 = -10;
+
+    /// Water current flowing along -X
+    pub const CURRENT_180: i32 = -11;
+
+    /// Water current flowing along -Y
+    pub const CURRENT_270: i32 = -12;
+
+    /// Water current flowing along +Z
+    pub const CURRENT_UP: i32 = -13;
+
+    /// Water current flowing along -Z
+    pub const CURRENT_DOWN: i32 = -14;
+}
+
 #[cfg(test)]
 mod repr_test;
 
 #[cfg(test)]
 mod parser_test;
+
+#[cfg(test)]
+mod decoded_test;
+
+#[cfg(test)]
+mod lightmap_test;
+
+#[cfg(test)]
+mod vis_test;
+
+#[cfg(test)]
+mod hull_test;
+
+#[cfg(test)]
+mod writer_test;
+
+#[cfg(test)]
+mod bspx_test;
+
+#[cfg(test)]
+mod gltf_test;
+
+#[cfg(test)]
+mod validate_test;
+
+#[cfg(test)]
+mod checksum_test;
+
+#[cfg(test)]
+mod light_test;
+
+#[cfg(test)]
+mod portal_test;
+
+#[cfg(test)]
+mod compile_test;
+
+#[cfg(test)]
+mod test_fixtures;
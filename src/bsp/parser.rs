@@ -1,13 +1,27 @@
-use super::{Entry, EntryOffset, Head};
-use crate::{BinParseError, BinParseResult, TextParseError};
+use super::bspx::{BSPX_ENTRY_SZ, BSPX_MAGIC};
+use super::repr::Layout;
+use super::writer::LUMP_ORDER;
+use super::{
+    Bsp, BspxEntry, BspxLump, ClipNode, Edge, Entry, EntryOffset, Face, Head,
+    Leaf, MarkSurface, Model, Node, Plane, Record, SurfEdge, TexInfo, Vertex,
+    Writer, HL_BSP_VERSION,
+};
+use crate::common::LeReader;
+use crate::lump::MipTextureHead;
+use crate::{lump, BinParseError, BinParseResult, Palette, TextParseError};
 use io::{Read, Seek, SeekFrom};
+use std::boxed::Box;
+use std::fmt::Debug;
 use std::io;
 use std::mem::size_of;
 use std::string::String;
+use std::vec::Vec;
 
 use crate::qmap;
 use qmap::QuakeMap;
 
+const MIP_TEXTURE_HEAD_SZ: usize = size_of::<MipTextureHead>();
+
 #[derive(Debug)]
 pub struct Parser<'a, Reader: Seek + Read> {
     cursor: &'a mut Reader,
@@ -50,11 +64,204 @@ impl<'a, Reader: Seek + Read> Parser<'a, Reader> {
         Ok(self.cursor.take(length))
     }
 
+    /// Lists the lumps in the BSPX directory following the standard lumps.
+    /// Yields no entries if there is no BSPX directory.
+    pub fn bspx_entries(&mut self) -> BinParseResult<Vec<BspxEntry>> {
+        let abs_offset = self
+            .start
+            .checked_add(self.bspx_offset())
+            .ok_or(BinParseError::Parse(String::from("Bad offset")))?;
+
+        self.cursor.seek(SeekFrom::Start(abs_offset))?;
+
+        let mut head = Vec::new();
+        (&mut self.cursor).take(8).read_to_end(&mut head)?;
+
+        if head.len() < 8 || head[..4] != BSPX_MAGIC {
+            return Ok(Vec::new());
+        }
+
+        let count = u32::from_le_bytes(head[4..].try_into().unwrap());
+        let length = u64::from(count) * BSPX_ENTRY_SZ as u64;
+        let bytes = read_exact(self.cursor.take(length))?;
+
+        Ok(bytes
+            .chunks_exact(BSPX_ENTRY_SZ)
+            .map(BspxEntry::from_bytes)
+            .collect())
+    }
+
+    pub fn bspx_lump_reader(
+        &mut self,
+        entry: &BspxEntry,
+    ) -> BinParseResult<std::io::Take<&mut Reader>> {
+        let abs_offset = self
+            .start
+            .checked_add(entry.offset().into())
+            .ok_or(BinParseError::Parse(String::from("Bad offset")))?;
+
+        self.cursor.seek(SeekFrom::Start(abs_offset))?;
+
+        Ok(self.cursor.take(entry.length().into()))
+    }
+
+    /// Attempts to read and decode a BSPX lump, see `BspxLump::decode`
+    pub fn parse_bspx_lump(
+        &mut self,
+        entry: &BspxEntry,
+    ) -> BinParseResult<BspxLump> {
+        let bytes = read_exact(self.bspx_lump_reader(entry)?)?;
+        let name = entry.name_to_cstring();
+
+        match name.to_str() {
+            Ok(name) => BspxLump::decode(name, bytes),
+            Err(_) => Ok(BspxLump::Other(bytes)),
+        }
+    }
+
+    /// Attempts to find and decode a BSPX lump by name
+    pub fn parse_bspx_lump_named(
+        &mut self,
+        name: &str,
+    ) -> BinParseResult<Option<BspxLump>> {
+        let entries = self.bspx_entries()?;
+        let entry = entries.iter().find(|entry| {
+            entry.name_to_cstring().as_bytes() == name.as_bytes()
+        });
+
+        entry.map(|entry| self.parse_bspx_lump(entry)).transpose()
+    }
+
     pub fn lump_empty(&self, offset: EntryOffset) -> bool {
         let length = self.header.entry(offset).length;
         length == 0
     }
 
+    /// Attempts to parse the planes lump
+    pub fn parse_planes(&mut self) -> BinParseResult<Vec<Plane>> {
+        self.parse_records(EntryOffset::Planes)
+    }
+
+    /// Attempts to parse the vertices lump
+    pub fn parse_vertices(&mut self) -> BinParseResult<Vec<Vertex>> {
+        self.parse_records(EntryOffset::Vertices)
+    }
+
+    /// Attempts to parse the edges lump, using 16-bit vertex indices for BSP29
+    /// and 32-bit vertex indices for BSP2
+    pub fn parse_edges(&mut self) -> BinParseResult<Vec<Edge>> {
+        self.parse_records(EntryOffset::Edges)
+    }
+
+    /// Attempts to parse the surface edges lump
+    pub fn parse_surf_edges(&mut self) -> BinParseResult<Vec<SurfEdge>> {
+        self.parse_records(EntryOffset::SurfEdges)
+    }
+
+    /// Attempts to parse the texture info lump
+    pub fn parse_tex_info(&mut self) -> BinParseResult<Vec<TexInfo>> {
+        self.parse_records(EntryOffset::TexInfo)
+    }
+
+    /// Attempts to parse the faces lump, using 16-bit indices for BSP29 and
+    /// 32-bit indices for BSP2
+    pub fn parse_faces(&mut self) -> BinParseResult<Vec<Face>> {
+        self.parse_records(EntryOffset::Faces)
+    }
+
+    /// Attempts to parse the nodes lump
+    pub fn parse_nodes(&mut self) -> BinParseResult<Vec<Node>> {
+        self.parse_records(EntryOffset::Nodes)
+    }
+
+    /// Attempts to parse the clip nodes lump
+    pub fn parse_clip_nodes(&mut self) -> BinParseResult<Vec<ClipNode>> {
+        self.parse_records(EntryOffset::ClipNodes)
+    }
+
+    /// Attempts to parse the leaves lump
+    pub fn parse_leaves(&mut self) -> BinParseResult<Vec<Leaf>> {
+        self.parse_records(EntryOffset::Leaves)
+    }
+
+    /// Attempts to parse the mark surfaces lump
+    pub fn parse_mark_surfaces(&mut self) -> BinParseResult<Vec<MarkSurface>> {
+        self.parse_records(EntryOffset::MarkSurfaces)
+    }
+
+    /// Attempts to parse the models lump
+    pub fn parse_models(&mut self) -> BinParseResult<Vec<Model>> {
+        self.parse_records(EntryOffset::Models)
+    }
+
+    /// Attempts to parse the mip-mapped textures embedded in the textures lump.
+    /// Textures missing from the BSP (with an offset of -1) and textures loaded
+    /// from a WAD (with no pixel data, as in Half-Life) are `None`.
+    pub fn parse_mip_textures(
+        &mut self,
+    ) -> BinParseResult<Vec<Option<lump::MipTexture>>> {
+        let bytes = self.read_lump(EntryOffset::Textures)?;
+        parse_mip_textures(&bytes, self.version())
+    }
+
+    /// Attempts to parse the palette of each texture in a Half-Life BSP.
+    /// Missing textures, and textures loaded from a WAD, have no palette.
+    pub fn parse_mip_texture_palettes(
+        &mut self,
+    ) -> BinParseResult<Vec<Option<Box<Palette>>>> {
+        check_palettes(self.version())?;
+        let bytes = self.read_lump(EntryOffset::Textures)?;
+        parse_mip_texture_palettes(&bytes)
+    }
+
+    /// Attempts to parse every lump.  Lumps without a typed representation are
+    /// kept as raw bytes.
+    pub fn parse_bsp(&mut self) -> BinParseResult<Bsp> {
+        Ok(Bsp {
+            version: self.version(),
+            entities: self.read_lump(EntryOffset::Entities)?,
+            planes: self.parse_planes()?,
+            textures: self.read_lump(EntryOffset::Textures)?,
+            vertices: self.parse_vertices()?,
+            visibility: self.read_lump(EntryOffset::Vis)?,
+            nodes: self.parse_nodes()?,
+            tex_info: self.parse_tex_info()?,
+            faces: self.parse_faces()?,
+            lighting: self.read_lump(EntryOffset::Light)?,
+            clip_nodes: self.parse_clip_nodes()?,
+            leaves: self.parse_leaves()?,
+            mark_surfaces: self.parse_mark_surfaces()?,
+            edges: self.parse_edges()?,
+            surf_edges: self.parse_surf_edges()?,
+            models: self.parse_models()?,
+        })
+    }
+
+    /// Reads every lump, including BSPX lumps, verbatim into a writer with the
+    /// same version, e.g. so that individual lumps can be replaced without
+    /// decoding the rest
+    pub fn parse_lumps(&mut self) -> BinParseResult<Writer> {
+        let mut writer = Writer::new(self.version());
+        let head = *self.header();
+        let mut order = LUMP_ORDER;
+
+        // Lumps at the same offset (i.e. empty ones) stay in qbsp order
+        order.sort_by_key(|&entry_offset| head.entry(entry_offset).offset);
+        writer.set_lump_order(order);
+
+        for entry_offset in LUMP_ORDER {
+            writer.set_lump(entry_offset, self.read_lump(entry_offset)?);
+        }
+
+        for entry in self.bspx_entries()? {
+            let bytes = read_exact(self.bspx_lump_reader(&entry)?)?;
+            writer.set_bspx_lump_raw(entry.name(), bytes);
+        }
+
+        Ok(writer)
+    }
+
+    #[allow(clippy::unbuffered_bytes)]
     pub fn parse_entities(&mut self) -> BinParseResult<QuakeMap> {
         let lump = self.lump_reader(EntryOffset::Entities)?;
         let byte_iter = lump.bytes().take_while(|b| match b {
@@ -62,11 +269,268 @@ impl<'a, Reader: Seek + Read> Parser<'a, Reader> {
             _ => true,
         });
 
-        qmap::parse(&mut IterReader::new(byte_iter)).map_err(|e| match e {
-            TextParseError::Io(ioe) => ioe.into(),
-            err => BinParseError::Parse(format!("{err}")),
+        qmap::parse(&mut IterReader::new(byte_iter)).map_err(entities_error)
+    }
+
+    pub(super) fn header(&self) -> &Head {
+        &self.header
+    }
+
+    // Length of the stream from the start of the BSP
+    pub(super) fn stream_length(&mut self) -> BinParseResult<u64> {
+        let end = self.cursor.seek(SeekFrom::End(0))?;
+        Ok(end.saturating_sub(self.start))
+    }
+
+    pub(super) fn read_lump(
+        &mut self,
+        entry_offset: EntryOffset,
+    ) -> BinParseResult<Vec<u8>> {
+        read_exact(self.lump_reader(entry_offset)?)
+    }
+
+    // BSPX directory follows the furthest standard lump, aligned to 4 bytes
+    fn bspx_offset(&self) -> u64 {
+        LUMP_ORDER
+            .iter()
+            .map(|&entry_offset| {
+                let Entry { offset, length } = self.header.entry(entry_offset);
+                u64::from(offset) + u64::from(length)
+            })
+            .max()
+            .unwrap_or(0)
+            .next_multiple_of(4)
+    }
+
+    fn parse_records<R: Record>(
+        &mut self,
+        entry_offset: EntryOffset,
+    ) -> BinParseResult<Vec<R>> {
+        let bytes = self.read_lump(entry_offset)?;
+        decode_records(&bytes, self.header.layout(), entry_offset)
+    }
+}
+
+/// Decode a lump consisting of fixed-size records
+pub(crate) fn decode_records<R: Record>(
+    bytes: &[u8],
+    layout: Layout,
+    lump: impl Debug,
+) -> BinParseResult<Vec<R>> {
+    let size = R::size(layout);
+
+    if !bytes.len().is_multiple_of(size) {
+        return Err(BinParseError::Parse(format!(
+            "{lump:?} lump length {} is not a multiple of {size}",
+            bytes.len(),
+        )));
+    }
+
+    bytes
+        .chunks_exact(size)
+        .map(|chunk| R::from_bytes(chunk, layout))
+        .collect()
+}
+
+/// Parse an entities lump, up to the first null byte
+pub(crate) fn parse_entities_lump(bytes: &[u8]) -> BinParseResult<QuakeMap> {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    qmap::parse(&mut &bytes[..end]).map_err(entities_error)
+}
+
+pub(crate) fn read_exact<R: Read>(
+    mut lump: io::Take<R>,
+) -> BinParseResult<Vec<u8>> {
+    let length = lump.limit();
+    let mut bytes = Vec::new();
+
+    // Grow the buffer as data is read rather than trusting the header, so
+    // that a bad length can't trigger an enormous allocation
+    lump.read_to_end(&mut bytes)?;
+
+    if bytes.len() as u64 != length {
+        return Err(BinParseError::Io(io::ErrorKind::UnexpectedEof.into()));
+    }
+
+    Ok(bytes)
+}
+
+fn entities_error(err: TextParseError) -> BinParseError {
+    match err {
+        TextParseError::Io(ioe) => ioe.into(),
+        err => BinParseError::Parse(format!("{err}")),
+    }
+}
+
+pub(crate) fn parse_mip_textures(
+    bytes: &[u8],
+    version: u32,
+) -> BinParseResult<Vec<Option<lump::MipTexture>>> {
+    let mut cursor = io::Cursor::new(bytes);
+
+    mip_texture_offsets(bytes, version)?
+        .into_iter()
+        .map(|offset| {
+            let Some(offset) = offset else {
+                return Ok(None);
+            };
+
+            check_mip_texture(bytes, offset)?;
+            cursor.seek(SeekFrom::Start(offset as u64))?;
+            lump::parse_mip_texture(&mut cursor).map(Some)
+        })
+        .collect()
+}
+
+/// Parse the palette following the pixels of each mip texture, as found in
+/// Half-Life BSPs
+pub(crate) fn parse_mip_texture_palettes(
+    bytes: &[u8],
+) -> BinParseResult<Vec<Option<Box<Palette>>>> {
+    mip_texture_offsets(bytes, HL_BSP_VERSION)?
+        .into_iter()
+        .map(|offset| {
+            let Some(offset) = offset else {
+                return Ok(None);
+            };
+
+            let cut_off = || {
+                BinParseError::Parse(format!(
+                    "Texture palette at offset {offset} cut off"
+                ))
+            };
+
+            let head = bytes
+                .get(offset..(offset + MIP_TEXTURE_HEAD_SZ))
+                .ok_or_else(cut_off)?;
+            let mut reader = LeReader::new(&head[16..]);
+            let width = reader.u32() as usize;
+            let height = reader.u32() as usize;
+            reader.bytes::<12>();
+            let mip3_start = reader.u32() as usize;
+            let palette_start = (width / 8)
+                .checked_mul(height / 8)
+                .and_then(|mip3_length| mip3_length.checked_add(mip3_start))
+                .and_then(|start| start.checked_add(offset))
+                .ok_or_else(cut_off)?;
+
+            let count_bytes = palette_start
+                .checked_add(2)
+                .and_then(|count_end| bytes.get(palette_start..count_end))
+                .ok_or_else(cut_off)?;
+            let count = u16::from_le_bytes(count_bytes.try_into().unwrap());
+
+            if count > 256 {
+                return Err(BinParseError::Parse(format!(
+                    "Texture palette has {count} colors"
+                )));
+            }
+
+            let color_start =
+                palette_start.checked_add(2).ok_or_else(cut_off)?;
+            let colors = color_start
+                .checked_add(usize::from(count) * 3)
+                .and_then(|color_end| bytes.get(color_start..color_end))
+                .ok_or_else(cut_off)?;
+            let mut palette = Box::new([[0u8; 3]; 256]);
+
+            for (color, rgb) in palette.iter_mut().zip(colors.chunks_exact(3)) {
+                color.copy_from_slice(rgb);
+            }
+
+            Ok(Some(palette))
         })
+        .collect()
+}
+
+// Checks the header of the mip texture at `offset` and that each of its mips
+// lies within the textures lump, so that a bad header can't trigger an
+// enormous allocation
+pub(super) fn check_mip_texture(
+    bytes: &[u8],
+    offset: usize,
+) -> BinParseResult<()> {
+    let cut_off =
+        || BinParseError::Parse(format!("Texture at offset {offset} cut off"));
+
+    let head_bytes = offset
+        .checked_add(MIP_TEXTURE_HEAD_SZ)
+        .and_then(|head_end| bytes.get(offset..head_end))
+        .ok_or_else(cut_off)?;
+    let head = MipTextureHead::try_from(
+        <[u8; MIP_TEXTURE_HEAD_SZ]>::try_from(head_bytes).unwrap(),
+    )?;
+
+    let mip0_length = u64::from(head.width) * u64::from(head.height);
+    let available = (bytes.len() - offset) as u64;
+    let offsets = head.offsets;
+
+    for (mip, pixel_offset) in offsets.into_iter().enumerate() {
+        let mip_end = u64::from(pixel_offset) + (mip0_length >> (mip * 2));
+
+        if mip_end > available {
+            return Err(cut_off());
+        }
     }
+
+    Ok(())
+}
+
+pub(crate) fn check_palettes(version: u32) -> BinParseResult<()> {
+    if version != HL_BSP_VERSION {
+        return Err(BinParseError::Parse(format!(
+            "BSP version {version} textures have no palettes"
+        )));
+    }
+
+    Ok(())
+}
+
+// Offsets of each mip texture within the textures lump.  Textures which are
+// missing, or which only have a header because they are loaded from a WAD
+// (Half-Life), have no offset.
+pub(super) fn mip_texture_offsets(
+    bytes: &[u8],
+    version: u32,
+) -> BinParseResult<Vec<Option<usize>>> {
+    if bytes.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let count_bytes = bytes
+        .get(..4)
+        .ok_or(BinParseError::Parse(String::from("Missing texture count")))?;
+    let count = i32::from_le_bytes(count_bytes.try_into().unwrap());
+    let count = usize::try_from(count).map_err(|_| {
+        BinParseError::Parse(format!("Bad texture count {count}"))
+    })?;
+
+    let offsets = count
+        .checked_mul(4)
+        .and_then(|length| bytes.get(4..(4 + length)))
+        .ok_or(BinParseError::Parse(String::from(
+            "Texture offsets cut off",
+        )))?;
+
+    Ok(offsets
+        .chunks_exact(4)
+        .map(|chunk| {
+            let offset = i32::from_le_bytes(chunk.try_into().unwrap());
+            let offset = usize::try_from(offset).ok()?;
+            let pixel_offsets = bytes
+                .get((offset + 24)..(offset + MIP_TEXTURE_HEAD_SZ))
+                .unwrap_or(&[]);
+
+            if version == HL_BSP_VERSION
+                && !pixel_offsets.is_empty()
+                && pixel_offsets.iter().all(|&b| b == 0)
+            {
+                None
+            } else {
+                Some(offset)
+            }
+        })
+        .collect())
 }
 
 struct IterReader<I>
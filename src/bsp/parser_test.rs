@@ -1,5 +1,10 @@
+use super::test_fixtures::textures_lump;
 use crate::{bsp, BinParseError};
-use bsp::EntryOffset;
+use bsp::{
+    contents, ClipNode, ClipNodeChild, Edge, EntryOffset, Face, Leaf, Node,
+    NodeChild, PlaneKind, BSP2_RMQ_VERSION, BSP2_VERSION, BSP_VERSION,
+    HL_BSP_VERSION,
+};
 use std::ffi::CString;
 use std::io::Cursor;
 use std::mem::size_of;
@@ -13,6 +18,32 @@ const ENTITIES: &str = r#"
 }"#;
 const ENTITIES_LEN: usize = ENTITIES.len() + 1;
 
+fn bsp_bytes(version: u32, lumps: &[(EntryOffset, Vec<u8>)]) -> Vec<u8> {
+    let mut bytes = vec![0u8; HEAD_SZ];
+    bytes[..4].copy_from_slice(&version.to_le_bytes());
+
+    for (entry_offset, lump) in lumps {
+        let idx = usize::from(*entry_offset);
+        let offset = bytes.len() as u32;
+        let length = lump.len() as u32;
+        bytes[(4 + idx * 8)..(8 + idx * 8)]
+            .copy_from_slice(&offset.to_le_bytes());
+        bytes[(8 + idx * 8)..(12 + idx * 8)]
+            .copy_from_slice(&length.to_le_bytes());
+        bytes.extend(lump);
+    }
+
+    bytes
+}
+
+fn plane_bytes(normal: [f32; 3], dist: f32, kind: i32) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    normal.iter().for_each(|n| bytes.extend(n.to_le_bytes()));
+    bytes.extend(dist.to_le_bytes());
+    bytes.extend(kind.to_le_bytes());
+    bytes
+}
+
 #[test]
 fn parse_good_bsp() {
     const MODELS_LEN: usize = 123;
@@ -112,3 +143,645 @@ fn parse_bad_entities() {
 
     assert!(matches!(error, BinParseError::Parse(_)));
 }
+
+#[test]
+fn parse_planes() {
+    let mut planes = plane_bytes([0.0, 0.0, 1.0], 64.0, 2);
+    planes.extend(plane_bytes([0.6, 0.8, 0.0], -8.5, 4));
+
+    for version in [BSP_VERSION, BSP2_VERSION] {
+        let bytes =
+            bsp_bytes(version, &[(EntryOffset::Planes, planes.clone())]);
+        let mut cursor = Cursor::new(bytes);
+        let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+        let planes = parser.parse_planes().unwrap();
+
+        assert_eq!(planes.len(), 2);
+        assert_eq!(planes[0].normal, [0.0, 0.0, 1.0]);
+        assert_eq!(planes[0].dist, 64.0);
+        assert_eq!(planes[0].kind, PlaneKind::Z);
+        assert_eq!(planes[1].normal, [0.6, 0.8, 0.0]);
+        assert_eq!(planes[1].dist, -8.5);
+        assert_eq!(planes[1].kind, PlaneKind::AnyY);
+    }
+}
+
+#[test]
+fn parse_planes_bad_length() {
+    let mut planes = plane_bytes([1.0, 0.0, 0.0], 0.0, 0);
+    planes.pop();
+    let bytes = bsp_bytes(BSP_VERSION, &[(EntryOffset::Planes, planes)]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let err = parser.parse_planes().unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn parse_planes_bad_kind() {
+    let planes = plane_bytes([1.0, 0.0, 0.0], 0.0, 6);
+    let bytes = bsp_bytes(BSP2_VERSION, &[(EntryOffset::Planes, planes)]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let err = parser.parse_planes().unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn parse_planes_truncated() {
+    let planes = plane_bytes([1.0, 0.0, 0.0], 0.0, 0);
+    let mut bytes = bsp_bytes(BSP_VERSION, &[(EntryOffset::Planes, planes)]);
+    bytes.truncate(bytes.len() - 4);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let err = parser.parse_planes().unwrap_err();
+
+    assert!(matches!(err, BinParseError::Io(_)));
+}
+
+#[test]
+fn parse_vertices_and_surf_edges() {
+    let mut vertices = Vec::new();
+    [1.0f32, 2.0, 3.0, -4.0, -5.0, -6.0]
+        .iter()
+        .for_each(|f| vertices.extend(f.to_le_bytes()));
+    let mut surf_edges = Vec::new();
+    [1i32, -1, 0]
+        .iter()
+        .for_each(|i| surf_edges.extend(i.to_le_bytes()));
+
+    for version in [BSP_VERSION, BSP2_VERSION] {
+        let bytes = bsp_bytes(
+            version,
+            &[
+                (EntryOffset::Vertices, vertices.clone()),
+                (EntryOffset::SurfEdges, surf_edges.clone()),
+            ],
+        );
+        let mut cursor = Cursor::new(bytes);
+        let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+
+        assert_eq!(
+            parser.parse_vertices().unwrap(),
+            vec![[1.0, 2.0, 3.0], [-4.0, -5.0, -6.0]],
+        );
+        assert_eq!(parser.parse_surf_edges().unwrap(), vec![1, -1, 0]);
+    }
+}
+
+#[test]
+fn parse_bsp29_edges() {
+    let mut edges = Vec::new();
+    [0u16, 1, 1, 65_535]
+        .iter()
+        .for_each(|i| edges.extend(i.to_le_bytes()));
+    let bytes = bsp_bytes(BSP_VERSION, &[(EntryOffset::Edges, edges)]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+
+    assert_eq!(
+        parser.parse_edges().unwrap(),
+        vec![
+            Edge { vertices: [0, 1] },
+            Edge {
+                vertices: [1, 65_535]
+            },
+        ],
+    );
+}
+
+#[test]
+fn parse_bsp2_edges() {
+    let mut edges = Vec::new();
+    [0u32, 1, 1, 70_000]
+        .iter()
+        .for_each(|i| edges.extend(i.to_le_bytes()));
+    let bytes = bsp_bytes(BSP2_VERSION, &[(EntryOffset::Edges, edges)]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+
+    assert_eq!(
+        parser.parse_edges().unwrap(),
+        vec![
+            Edge { vertices: [0, 1] },
+            Edge {
+                vertices: [1, 70_000]
+            },
+        ],
+    );
+}
+
+#[test]
+fn parse_edges_wrong_width() {
+    let edges = [0u8; 12].to_vec();
+    let bytes = bsp_bytes(BSP2_VERSION, &[(EntryOffset::Edges, edges)]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let err = parser.parse_edges().unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+fn triangle_lumps(version: u32) -> Vec<(EntryOffset, Vec<u8>)> {
+    let index = |i: u32| {
+        if version == BSP_VERSION {
+            (i as u16).to_le_bytes().to_vec()
+        } else {
+            i.to_le_bytes().to_vec()
+        }
+    };
+
+    let planes = plane_bytes([0.0, 0.0, 1.0], 0.0, 2);
+
+    let mut vertices = Vec::new();
+    [0.0f32, 0.0, 0.0, 32.0, 0.0, 0.0, 0.0, 32.0, 0.0]
+        .iter()
+        .for_each(|f| vertices.extend(f.to_le_bytes()));
+
+    let mut edges = Vec::new();
+    [0, 0, 0, 1, 2, 1, 2, 0]
+        .into_iter()
+        .for_each(|i| edges.extend(index(i)));
+
+    let mut surf_edges = Vec::new();
+    [1i32, -2, 3]
+        .iter()
+        .for_each(|i| surf_edges.extend(i.to_le_bytes()));
+
+    let mut faces = Vec::new();
+    faces.extend(index(0));
+    faces.extend(index(0));
+    faces.extend(0u32.to_le_bytes());
+    faces.extend(index(3));
+    faces.extend(index(7));
+    faces.extend([0u8, 1, 255, 255]);
+    faces.extend(128i32.to_le_bytes());
+
+    vec![
+        (EntryOffset::Planes, planes),
+        (EntryOffset::Vertices, vertices),
+        (EntryOffset::Faces, faces),
+        (EntryOffset::Edges, edges),
+        (EntryOffset::SurfEdges, surf_edges),
+    ]
+}
+
+#[test]
+fn parse_faces() {
+    for version in [BSP_VERSION, BSP2_VERSION, BSP2_RMQ_VERSION] {
+        let bytes = bsp_bytes(version, &triangle_lumps(version));
+        let mut cursor = Cursor::new(bytes);
+        let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+
+        assert_eq!(
+            parser.parse_faces().unwrap(),
+            vec![Face {
+                plane: 0,
+                side: 0,
+                first_edge: 0,
+                edge_count: 3,
+                tex_info: 7,
+                styles: [0, 1, 255, 255],
+                light_offset: 128,
+            }],
+        );
+    }
+}
+
+#[test]
+fn parse_bsp_polygons() {
+    for version in [BSP_VERSION, BSP2_VERSION, BSP2_RMQ_VERSION] {
+        let bytes = bsp_bytes(version, &triangle_lumps(version));
+        let mut cursor = Cursor::new(bytes);
+        let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+        let bsp = parser.parse_bsp().unwrap();
+        let polygon = bsp.polygons().next().unwrap().unwrap();
+
+        assert_eq!(bsp.version, version);
+        assert_eq!(
+            polygon.points,
+            vec![[0.0, 0.0, 0.0], [32.0, 0.0, 0.0], [0.0, 32.0, 0.0]],
+        );
+        assert!(!polygon.back);
+        assert_eq!(polygon.tex_info, 7);
+    }
+}
+
+fn tree_lumps(version: u32) -> Vec<(EntryOffset, Vec<u8>)> {
+    let wide = version != BSP_VERSION;
+    let index = |i: u32| {
+        if wide {
+            i.to_le_bytes().to_vec()
+        } else {
+            (i as u16).to_le_bytes().to_vec()
+        }
+    };
+    let child = |i: i32| {
+        if wide {
+            i.to_le_bytes().to_vec()
+        } else {
+            (i as i16).to_le_bytes().to_vec()
+        }
+    };
+    let bound = |f: f32| {
+        if version == BSP2_VERSION {
+            f.to_le_bytes().to_vec()
+        } else {
+            (f as i16).to_le_bytes().to_vec()
+        }
+    };
+
+    let mut nodes = Vec::new();
+    nodes.extend(3u32.to_le_bytes());
+    nodes.extend(child(1));
+    nodes.extend(child(-2));
+    [-64.0, -32.0, -16.0, 64.0, 32.0, 16.0]
+        .into_iter()
+        .for_each(|f| nodes.extend(bound(f)));
+    nodes.extend(index(5));
+    nodes.extend(index(2));
+
+    let mut clip_nodes = Vec::new();
+    clip_nodes.extend(4u32.to_le_bytes());
+    clip_nodes.extend(child(contents::SOLID));
+    clip_nodes.extend(child(0));
+
+    let mut leaves = Vec::new();
+    leaves.extend(contents::WATER.to_le_bytes());
+    leaves.extend((-1i32).to_le_bytes());
+    [-8.0, -8.0, -8.0, 8.0, 8.0, 8.0]
+        .into_iter()
+        .for_each(|f| leaves.extend(bound(f)));
+    leaves.extend(index(1));
+    leaves.extend(index(2));
+    leaves.extend([1u8, 2, 3, 4]);
+
+    let mut mark_surfaces = Vec::new();
+    [0, 1, 2]
+        .into_iter()
+        .for_each(|i| mark_surfaces.extend(index(i)));
+
+    vec![
+        (EntryOffset::Nodes, nodes),
+        (EntryOffset::ClipNodes, clip_nodes),
+        (EntryOffset::Leaves, leaves),
+        (EntryOffset::MarkSurfaces, mark_surfaces),
+    ]
+}
+
+#[test]
+fn parse_tree() {
+    for version in [BSP_VERSION, BSP2_VERSION, BSP2_RMQ_VERSION] {
+        let bytes = bsp_bytes(version, &tree_lumps(version));
+        let mut cursor = Cursor::new(bytes);
+        let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+
+        assert_eq!(
+            parser.parse_nodes().unwrap(),
+            vec![Node {
+                plane: 3,
+                children: [NodeChild::Node(1), NodeChild::Leaf(1)],
+                mins: [-64.0, -32.0, -16.0],
+                maxs: [64.0, 32.0, 16.0],
+                first_face: 5,
+                face_count: 2,
+            }],
+        );
+
+        assert_eq!(
+            parser.parse_clip_nodes().unwrap(),
+            vec![ClipNode {
+                plane: 4,
+                children: [
+                    ClipNodeChild::Contents(contents::SOLID),
+                    ClipNodeChild::Node(0),
+                ],
+            }],
+        );
+
+        assert_eq!(
+            parser.parse_leaves().unwrap(),
+            vec![Leaf {
+                contents: contents::WATER,
+                vis_offset: -1,
+                mins: [-8.0, -8.0, -8.0],
+                maxs: [8.0, 8.0, 8.0],
+                first_mark_surface: 1,
+                mark_surface_count: 2,
+                ambient_levels: [1, 2, 3, 4],
+            }],
+        );
+
+        assert_eq!(parser.parse_mark_surfaces().unwrap(), vec![0, 1, 2]);
+    }
+}
+
+#[test]
+fn parse_bsp29_nodes_as_bsp2() {
+    let mut lumps = tree_lumps(BSP_VERSION);
+    lumps.retain(|(entry_offset, _)| entry_offset == &EntryOffset::Nodes);
+    let bytes = bsp_bytes(BSP2_VERSION, &lumps);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let err = parser.parse_nodes().unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn parse_bsp2_leaves_as_bsp2_rmq() {
+    let mut lumps = tree_lumps(BSP2_VERSION);
+    lumps.retain(|(entry_offset, _)| entry_offset == &EntryOffset::Leaves);
+    let bytes = bsp_bytes(BSP2_RMQ_VERSION, &lumps);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let err = parser.parse_leaves().unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn parse_models() {
+    let mut models = Vec::new();
+    [-1.0f32, -2.0, -3.0, 1.0, 2.0, 3.0, 0.5, 0.5, 0.5]
+        .iter()
+        .for_each(|f| models.extend(f.to_le_bytes()));
+    [0i32, 1, 2, 3, 40, 5, 6]
+        .iter()
+        .for_each(|i| models.extend(i.to_le_bytes()));
+
+    for version in [BSP_VERSION, BSP2_VERSION, BSP2_RMQ_VERSION] {
+        let bytes =
+            bsp_bytes(version, &[(EntryOffset::Models, models.clone())]);
+        let mut cursor = Cursor::new(bytes);
+        let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+        let model = parser.parse_models().unwrap()[0];
+
+        assert_eq!(model.mins, [-1.0, -2.0, -3.0]);
+        assert_eq!(model.maxs, [1.0, 2.0, 3.0]);
+        assert_eq!(model.origin, [0.5, 0.5, 0.5]);
+        assert_eq!(model.head_nodes, [0, 1, 2, 3]);
+        assert_eq!(model.vis_leaf_count, 40);
+        assert_eq!(model.first_face, 5);
+        assert_eq!(model.face_count, 6);
+    }
+}
+
+#[test]
+fn parse_mip_textures() {
+    let lump = textures_lump("brick", [16, 8], 7, &[true, false, true]);
+    let bytes = bsp_bytes(BSP_VERSION, &[(EntryOffset::Textures, lump)]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let textures = parser.parse_mip_textures().unwrap();
+
+    assert_eq!(textures.len(), 3);
+    assert!(textures[1].is_none());
+
+    for texture in [&textures[0], &textures[2]] {
+        let texture = texture.as_ref().unwrap();
+        assert_eq!(texture.name_to_string().unwrap(), "brick");
+        assert_eq!(texture.mip(0).width(), 16);
+        assert_eq!(texture.mip(0).height(), 8);
+        assert_eq!(texture.mip(3).pixels(), &[7u8; 2]);
+    }
+}
+
+#[test]
+fn parse_no_mip_textures() {
+    let bytes = bsp_bytes(BSP_VERSION, &[]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+
+    assert!(parser.parse_mip_textures().unwrap().is_empty());
+}
+
+#[test]
+fn parse_mip_textures_cut_off() {
+    let mut lump = textures_lump("brick", [16, 8], 7, &[true, false, true]);
+    lump.truncate(12);
+    let bytes = bsp_bytes(BSP_VERSION, &[(EntryOffset::Textures, lump)]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let err = parser.parse_mip_textures().unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn parse_mip_textures_bad_offset() {
+    let mut lump = textures_lump("brick", [16, 8], 7, &[true, false, true]);
+    lump[4..8].copy_from_slice(&(1000i32).to_le_bytes());
+    let bytes = bsp_bytes(BSP_VERSION, &[(EntryOffset::Textures, lump)]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let err = parser.parse_mip_textures().unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn parse_mip_textures_huge() {
+    let mut lump = Vec::new();
+    lump.extend(1i32.to_le_bytes());
+    lump.extend(8i32.to_le_bytes());
+    lump.extend(b"huge\0\0\0\0\0\0\0\0\0\0\0\0");
+    lump.extend(65_528u32.to_le_bytes());
+    lump.extend(65_528u32.to_le_bytes());
+    lump.extend([40u32; 4].iter().flat_map(|o| o.to_le_bytes()));
+    lump.extend([0u8; 8]);
+
+    let bytes = bsp_bytes(BSP_VERSION, &[(EntryOffset::Textures, lump)]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let err = parser.parse_mip_textures().unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn parse_mip_textures_zero_offsets() {
+    // Pixels overlapping the header are odd, but only Half-Life treats zero
+    // offsets as a texture loaded from a WAD
+    let mut lump = textures_lump("brick", [16, 8], 7, &[true, false, true]);
+    lump[40..56].fill(0);
+
+    let bytes =
+        bsp_bytes(BSP_VERSION, &[(EntryOffset::Textures, lump.clone())]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let textures = parser.parse_mip_textures().unwrap();
+
+    assert!(textures[0].is_some());
+    assert!(textures[2].is_some());
+
+    let bytes = bsp_bytes(HL_BSP_VERSION, &[(EntryOffset::Textures, lump)]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let textures = parser.parse_mip_textures().unwrap();
+
+    assert!(textures[0].is_none());
+    assert!(textures[2].is_none());
+}
+
+fn hl_textures_lump() -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend(3i32.to_le_bytes());
+    bytes.extend(16i32.to_le_bytes());
+    bytes.extend((-1i32).to_le_bytes());
+    bytes.extend(234i32.to_le_bytes());
+
+    let mut offset = 40u32;
+    bytes.extend(b"+0button\0\0\0\0\0\0\0\0");
+    bytes.extend(16u32.to_le_bytes());
+    bytes.extend(8u32.to_le_bytes());
+
+    for mip_sz in [128, 32, 8, 2] {
+        bytes.extend(offset.to_le_bytes());
+        offset += mip_sz;
+    }
+
+    for mip_sz in [128, 32, 8, 2] {
+        bytes.extend(vec![1u8; mip_sz]);
+    }
+
+    bytes.extend(2u16.to_le_bytes());
+    bytes.extend([255, 0, 0, 0, 0, 255]);
+
+    bytes.extend(b"{grate\0\0\0\0\0\0\0\0\0\0");
+    bytes.extend(32u32.to_le_bytes());
+    bytes.extend(32u32.to_le_bytes());
+    bytes.extend([0u8; 16]);
+
+    bytes
+}
+
+#[test]
+fn parse_hl_bsp() {
+    let planes = plane_bytes([0.0, 1.0, 0.0], 16.0, 1);
+    let entities = b"{\n}\n\0".to_vec();
+    let bytes = bsp_bytes(
+        HL_BSP_VERSION,
+        &[
+            (EntryOffset::Entities, entities.clone()),
+            (EntryOffset::Planes, planes.clone()),
+        ],
+    );
+
+    // GoldSrc uses the same lump order as Quake
+    assert_eq!(&bytes[8..12], &(entities.len() as u32).to_le_bytes());
+    assert_eq!(&bytes[16..20], &(planes.len() as u32).to_le_bytes());
+
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let bsp = parser.parse_bsp().unwrap();
+
+    assert_eq!(bsp.version, HL_BSP_VERSION);
+    assert_eq!(bsp.planes.len(), 1);
+    assert_eq!(bsp.planes[0].dist, 16.0);
+    assert_eq!(bsp.parse_entities().unwrap().entities.len(), 1);
+}
+
+#[test]
+fn parse_blue_shift_bsp() {
+    let planes = plane_bytes([0.0, 1.0, 0.0], 16.0, 1);
+
+    // Blue Shift swaps the entities and planes entries
+    let bytes = bsp_bytes(
+        HL_BSP_VERSION,
+        &[
+            (EntryOffset::Entities, planes),
+            (EntryOffset::Planes, b"{\n}\n\0".to_vec()),
+        ],
+    );
+
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let bsp = parser.parse_bsp().unwrap();
+
+    assert_eq!(bsp.planes.len(), 1);
+    assert_eq!(bsp.planes[0].dist, 16.0);
+    assert_eq!(bsp.parse_entities().unwrap().entities.len(), 1);
+}
+
+#[test]
+fn parse_hl_mip_textures() {
+    let bytes = bsp_bytes(
+        HL_BSP_VERSION,
+        &[(EntryOffset::Textures, hl_textures_lump())],
+    );
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let textures = parser.parse_mip_textures().unwrap();
+
+    assert_eq!(textures.len(), 3);
+    assert_eq!(
+        textures[0].as_ref().unwrap().name_to_string().unwrap(),
+        "+0button"
+    );
+    assert!(textures[1].is_none());
+    assert!(textures[2].is_none());
+
+    let palettes = parser.parse_mip_texture_palettes().unwrap();
+
+    assert_eq!(palettes.len(), 3);
+    let palette = palettes[0].as_ref().unwrap();
+    assert_eq!(palette[0], [255, 0, 0]);
+    assert_eq!(palette[1], [0, 0, 255]);
+    assert_eq!(palette[2], [0, 0, 0]);
+    assert!(palettes[1].is_none());
+    assert!(palettes[2].is_none());
+}
+
+#[test]
+fn parse_hl_palette_cut_off() {
+    let mut lump = hl_textures_lump();
+    lump.truncate(234 - 4);
+    let bytes = bsp_bytes(HL_BSP_VERSION, &[(EntryOffset::Textures, lump)]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let err = parser.parse_mip_texture_palettes().unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn parse_quake_palettes() {
+    let bytes = bsp_bytes(
+        BSP_VERSION,
+        &[(
+            EntryOffset::Textures,
+            textures_lump("brick", [16, 8], 7, &[true, false, true]),
+        )],
+    );
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let err = parser.parse_mip_texture_palettes().unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn parse_tex_info() {
+    let mut tex_info = Vec::new();
+    [1.0f32, 0.0, 0.0, 8.0, 0.0, 1.0, 0.0, -8.0]
+        .iter()
+        .for_each(|f| tex_info.extend(f.to_le_bytes()));
+    tex_info.extend(2u32.to_le_bytes());
+    tex_info.extend(bsp::tex_flags::SPECIAL.to_le_bytes());
+
+    let bytes = bsp_bytes(BSP2_VERSION, &[(EntryOffset::TexInfo, tex_info)]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+
+    assert_eq!(
+        parser.parse_tex_info().unwrap(),
+        vec![bsp::TexInfo {
+            vecs: [[1.0, 0.0, 0.0, 8.0], [0.0, 1.0, 0.0, -8.0]],
+            mip_texture: 2,
+            flags: bsp::tex_flags::SPECIAL,
+        }],
+    );
+}
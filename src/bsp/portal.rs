@@ -0,0 +1,640 @@
+use super::Bsp;
+use crate::{BinParseError, BinParseResult, TextParseError, TextParseResult};
+use std::io::Read;
+use std::num::NonZeroU64;
+use std::string::String;
+use std::vec::Vec;
+
+/// Distance within which points are considered on a plane, as used by vis
+const ON_EPSILON: f64 = 0.1;
+
+/// Tolerance for comparing plane normals
+const EQUAL_EPSILON: f64 = 0.001;
+
+/// Portal between two leaves, as written to a `.prt` file by qbsp
+#[derive(Clone, Debug, PartialEq)]
+pub struct Portal {
+    /// Indices of the leaves on either side, not counting leaf 0 of the BSP
+    /// (i.e. BSP leaf `n + 1` is portal leaf `n`).  The plane of the winding
+    /// faces the first leaf.
+    pub leaves: [u32; 2],
+
+    pub winding: Vec<[f32; 3]>,
+}
+
+/// Contents of a qbsp `.prt` portal file in the `PRT1` format
+#[derive(Clone, Debug, PartialEq)]
+pub struct PortalFile {
+    /// Number of leaves of the world model that visibility is computed for
+    pub leaf_count: u32,
+
+    pub portals: Vec<Portal>,
+}
+
+impl PortalFile {
+    /// Attempts to parse a portal file
+    pub fn parse<R: Read>(reader: &mut R) -> TextParseResult<PortalFile> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(idx, line)| (NonZeroU64::new(idx as u64 + 1).unwrap(), line))
+            .filter(|(_, line)| !line.trim().is_empty());
+
+        let mut next_line = || lines.next().ok_or_else(TextParseError::eof);
+
+        let (line_number, format) = next_line()?;
+
+        if format.trim() != "PRT1" {
+            return Err(TextParseError::from_parser(
+                format!("Unsupported portal file format `{}`", format.trim()),
+                line_number,
+            ));
+        }
+
+        let (line_number, leaf_count) = next_line()?;
+        let leaf_count = parse_number(leaf_count, line_number)?;
+        let (line_number, portal_count) = next_line()?;
+        let portal_count: usize = parse_number(portal_count, line_number)?;
+        let mut portals = Vec::new();
+
+        for _ in 0..portal_count {
+            let (line_number, line) = next_line()?;
+            portals.push(parse_portal(line, line_number, leaf_count)?);
+        }
+
+        Ok(PortalFile {
+            leaf_count,
+            portals,
+        })
+    }
+
+    /// Computes the leaves visible from each leaf by flooding through portals,
+    /// clipping each portal to the region visible through the ones before it,
+    /// as vis does.  Yields an uncompressed row per leaf, where bit `i` is set
+    /// if leaf `i` is visible (with leaves indexed as in `Portal::leaves`).
+    /// Fails if a portal leads to a leaf beyond the leaf count.
+    pub fn compute_vis(&self) -> BinParseResult<Vec<Vec<u8>>> {
+        let leaf_count = self.leaf_count as usize;
+
+        for (idx, portal) in self.portals.iter().enumerate() {
+            if portal.leaves.iter().any(|&leaf| leaf >= self.leaf_count) {
+                return Err(BinParseError::Parse(format!(
+                    "Portal {idx} has bad leaves {} {}",
+                    portal.leaves[0], portal.leaves[1],
+                )));
+            }
+        }
+
+        let mut leaf_portals = vec![Vec::new(); leaf_count];
+        let mut flow_portals = Vec::with_capacity(self.portals.len() * 2);
+
+        for portal in &self.portals {
+            let winding: Vec<_> =
+                portal.winding.iter().map(|p| p.map(f64::from)).collect();
+            let Some(plane) = plane_from_winding(&winding) else {
+                continue;
+            };
+
+            let [front, back] = portal.leaves.map(|leaf| leaf as usize);
+            let mut reversed = winding.clone();
+            reversed.reverse();
+
+            // Each flow portal's plane faces the leaf it leads into
+            leaf_portals[front].push(flow_portals.len());
+            flow_portals.push(FlowPortal {
+                plane: plane.flipped(),
+                winding,
+                leaf: back,
+            });
+
+            leaf_portals[back].push(flow_portals.len());
+            flow_portals.push(FlowPortal {
+                plane,
+                winding: reversed,
+                leaf: front,
+            });
+        }
+
+        let mut flow = Flow {
+            portals: flow_portals,
+            leaf_portals,
+            leaf_count,
+            might_see: Vec::new(),
+        };
+
+        flow.might_see = (0..flow.portals.len())
+            .map(|portal| flow.might_see(portal))
+            .collect();
+
+        let portal_vis: Vec<_> = (0..flow.portals.len())
+            .map(|portal| flow.portal_vis(portal))
+            .collect();
+
+        Ok((0..leaf_count)
+            .map(|leaf| {
+                let mut row = vec![0u8; leaf_count.div_ceil(8)];
+                set_bit(&mut row, leaf);
+
+                for &portal in &flow.leaf_portals[leaf] {
+                    for (byte, vis) in row.iter_mut().zip(&portal_vis[portal]) {
+                        *byte |= vis;
+                    }
+                }
+
+                row
+            })
+            .collect())
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(
+    text: &str,
+    line_number: NonZeroU64,
+) -> TextParseResult<T> {
+    text.trim().parse().map_err(|_| {
+        TextParseError::from_parser(
+            format!("Expected number, found `{}`", text.trim()),
+            line_number,
+        )
+    })
+}
+
+// Portal lines hold the point count, the two leaves, then each point in
+// parentheses, e.g. `4 0 1 (0 0 0 ) (0 64 0 ) (0 64 64 ) (0 0 64 )`
+fn parse_portal(
+    line: &str,
+    line_number: NonZeroU64,
+    leaf_count: u32,
+) -> TextParseResult<Portal> {
+    let spaced = line.replace(['(', ')'], " ");
+    let mut tokens = spaced.split_whitespace();
+    let mut next_token = || tokens.next().unwrap_or("");
+
+    let point_count: usize = parse_number(next_token(), line_number)?;
+    let leaves: [u32; 2] = [
+        parse_number(next_token(), line_number)?,
+        parse_number(next_token(), line_number)?,
+    ];
+
+    if point_count < 3 {
+        return Err(TextParseError::from_parser(
+            format!("Bad portal point count {point_count}"),
+            line_number,
+        ));
+    }
+
+    if leaves.iter().any(|&leaf| leaf >= leaf_count) {
+        return Err(TextParseError::from_parser(
+            format!("Bad portal leaves {} {}", leaves[0], leaves[1]),
+            line_number,
+        ));
+    }
+
+    let mut winding = Vec::with_capacity(point_count.min(64));
+
+    for _ in 0..point_count {
+        let mut coord = || parse_number(next_token(), line_number);
+        winding.push([coord()?, coord()?, coord()?]);
+    }
+
+    if !next_token().is_empty() {
+        return Err(TextParseError::from_parser(
+            String::from("Unexpected tokens after portal points"),
+            line_number,
+        ));
+    }
+
+    Ok(Portal { leaves, winding })
+}
+
+type Point = [f64; 3];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct FlowPlane {
+    normal: Point,
+    dist: f64,
+}
+
+impl FlowPlane {
+    fn flipped(self) -> Self {
+        Self {
+            normal: self.normal.map(|n| -n),
+            dist: -self.dist,
+        }
+    }
+
+    fn distance(&self, point: Point) -> f64 {
+        dot(self.normal, point) - self.dist
+    }
+}
+
+struct FlowPortal {
+    plane: FlowPlane,
+    winding: Vec<Point>,
+
+    /// Leaf the portal leads into
+    leaf: usize,
+}
+
+struct Flow {
+    portals: Vec<FlowPortal>,
+    leaf_portals: Vec<Vec<usize>>,
+    leaf_count: usize,
+
+    /// Rough visibility of each portal, used to prune the full flow
+    might_see: Vec<Vec<u8>>,
+}
+
+// Part of a chain of portals being flowed through
+struct FlowStep<'a> {
+    plane: FlowPlane,
+
+    /// Part of the source portal which can see through this step
+    source: Vec<Point>,
+
+    /// Part of this step's portal visible through the chain, or `None` for
+    /// the source portal itself
+    pass: Option<Vec<Point>>,
+
+    might_see: &'a [u8],
+}
+
+impl Flow {
+    fn portal_vis(&self, source: usize) -> Vec<u8> {
+        let portal = &self.portals[source];
+        let mut vis = vec![0u8; self.leaf_count.div_ceil(8)];
+        let step = FlowStep {
+            plane: portal.plane,
+            source: portal.winding.clone(),
+            pass: None,
+            might_see: &self.might_see[source],
+        };
+
+        self.recursive_flow(portal.leaf, &step, portal.plane, &mut vis);
+        vis
+    }
+
+    // Leaves reachable through portals which are at least partly in front of
+    // the source portal, each with the source portal partly behind it
+    fn might_see(&self, source: usize) -> Vec<u8> {
+        let portal = &self.portals[source];
+        let in_view: Vec<bool> = self
+            .portals
+            .iter()
+            .enumerate()
+            .map(|(idx, other)| {
+                idx != source
+                    && other
+                        .winding
+                        .iter()
+                        .any(|&p| portal.plane.distance(p) > ON_EPSILON)
+                    && portal
+                        .winding
+                        .iter()
+                        .any(|&p| other.plane.distance(p) < -ON_EPSILON)
+            })
+            .collect();
+
+        let mut might_see = vec![0u8; self.leaf_count.div_ceil(8)];
+        let mut stack = vec![portal.leaf];
+
+        while let Some(leaf) = stack.pop() {
+            if get_bit(&might_see, leaf) {
+                continue;
+            }
+
+            set_bit(&mut might_see, leaf);
+
+            for &next in &self.leaf_portals[leaf] {
+                if in_view[next] {
+                    stack.push(self.portals[next].leaf);
+                }
+            }
+        }
+
+        might_see
+    }
+
+    fn recursive_flow(
+        &self,
+        leaf: usize,
+        previous: &FlowStep,
+        source_plane: FlowPlane,
+        vis: &mut [u8],
+    ) {
+        set_bit(vis, leaf);
+
+        for &next in &self.leaf_portals[leaf] {
+            let portal = &self.portals[next];
+
+            if !get_bit(previous.might_see, portal.leaf) {
+                continue;
+            }
+
+            let might_see: Vec<u8> = previous
+                .might_see
+                .iter()
+                .zip(&self.might_see[next])
+                .map(|(previous, next)| previous & next)
+                .collect();
+
+            let more = might_see
+                .iter()
+                .zip(&*vis)
+                .any(|(might, vis)| might & !vis != 0);
+
+            // Nothing new can be seen through this portal
+            if !more && get_bit(vis, portal.leaf) {
+                continue;
+            }
+
+            let back_plane = portal.plane.flipped();
+
+            // Can't go back out through a coplanar portal
+            if (0..3).all(|i| {
+                (previous.plane.normal[i] - back_plane.normal[i]).abs()
+                    < EQUAL_EPSILON
+            }) {
+                continue;
+            }
+
+            let Some(pass) = clip_winding(&portal.winding, source_plane) else {
+                continue;
+            };
+
+            let Some(source) = clip_winding(&previous.source, back_plane)
+            else {
+                continue;
+            };
+
+            let pass = match &previous.pass {
+                // The first portal past the source can only be blocked by
+                // being coplanar
+                None => Some(pass),
+                Some(previous_pass) => {
+                    clip_to_separators(&source, previous_pass, pass, false)
+                        .and_then(|pass| {
+                            clip_to_separators(
+                                previous_pass,
+                                &source,
+                                pass,
+                                true,
+                            )
+                        })
+                }
+            };
+
+            let Some(pass) = pass else {
+                continue;
+            };
+
+            let step = FlowStep {
+                plane: portal.plane,
+                source,
+                pass: Some(pass),
+                might_see: &might_see,
+            };
+
+            self.recursive_flow(portal.leaf, &step, source_plane, vis);
+        }
+    }
+}
+
+// Clip `target` to the planes separating `source` from `pass`, leaving the
+// part of `target` which can be seen from `source` through `pass`.  With
+// `flip_clip`, the roles are reversed.
+fn clip_to_separators(
+    source: &[Point],
+    pass: &[Point],
+    mut target: Vec<Point>,
+    flip_clip: bool,
+) -> Option<Vec<Point>> {
+    for (i, &start) in source.iter().enumerate() {
+        let l = (i + 1) % source.len();
+        let edge = sub(source[l], start);
+
+        for (j, &pass_point) in pass.iter().enumerate() {
+            let normal = cross(edge, sub(pass_point, start));
+            let length = dot(normal, normal).sqrt();
+
+            if length < ON_EPSILON {
+                continue;
+            }
+
+            let normal = normal.map(|n| n / length);
+            let mut plane = FlowPlane {
+                normal,
+                dist: dot(pass_point, normal),
+            };
+
+            // Orient the plane so the source portal is behind it
+            let source_side = source
+                .iter()
+                .enumerate()
+                .filter(|&(k, _)| k != i && k != l)
+                .map(|(_, &p)| plane.distance(p))
+                .find(|d| d.abs() > ON_EPSILON);
+
+            match source_side {
+                Some(d) if d < 0.0 => {}
+                Some(_) => plane = plane.flipped(),
+                // Source portal lies on the plane
+                None => continue,
+            }
+
+            // Separating planes have the whole pass portal on the front
+            let mut any_front = false;
+            let mut any_back = false;
+
+            for (k, &p) in pass.iter().enumerate() {
+                if k == j {
+                    continue;
+                }
+
+                let d = plane.distance(p);
+
+                if d < -ON_EPSILON {
+                    any_back = true;
+                    break;
+                } else if d > ON_EPSILON {
+                    any_front = true;
+                }
+            }
+
+            if any_back || !any_front {
+                continue;
+            }
+
+            if flip_clip {
+                plane = plane.flipped();
+            }
+
+            target = clip_winding(&target, plane)?;
+        }
+    }
+
+    Some(target)
+}
+
+// Keep the part of a winding in front of a plane, or `None` if nothing is
+// left.  Windings lying on the plane are discarded.
+fn clip_winding(winding: &[Point], plane: FlowPlane) -> Option<Vec<Point>> {
+    let dists: Vec<f64> = winding.iter().map(|&p| plane.distance(p)).collect();
+
+    if dists.iter().all(|&d| d <= ON_EPSILON) {
+        return None;
+    }
+
+    if dists.iter().all(|&d| d >= -ON_EPSILON) {
+        return Some(winding.to_vec());
+    }
+
+    let mut clipped = Vec::with_capacity(winding.len() + 1);
+
+    for (i, &point) in winding.iter().enumerate() {
+        let next = (i + 1) % winding.len();
+        let (d, next_d) = (dists[i], dists[next]);
+
+        if d >= -ON_EPSILON {
+            clipped.push(point);
+        }
+
+        if (d > ON_EPSILON && next_d < -ON_EPSILON)
+            || (d < -ON_EPSILON && next_d > ON_EPSILON)
+        {
+            let t = d / (d - next_d);
+            let delta = sub(winding[next], point);
+            clipped.push([
+                point[0] + t * delta[0],
+                point[1] + t * delta[1],
+                point[2] + t * delta[2],
+            ]);
+        }
+    }
+
+    (clipped.len() >= 3).then_some(clipped)
+}
+
+// Plane of a winding the way vis computes it, or `None` if degenerate
+fn plane_from_winding(winding: &[Point]) -> Option<FlowPlane> {
+    if winding.len() < 3 {
+        return None;
+    }
+
+    let normal =
+        cross(sub(winding[0], winding[1]), sub(winding[2], winding[1]));
+    let length = dot(normal, normal).sqrt();
+
+    if length == 0.0 {
+        return None;
+    }
+
+    let normal = normal.map(|n| n / length);
+
+    Some(FlowPlane {
+        normal,
+        dist: dot(winding[0], normal),
+    })
+}
+
+fn get_bit(bits: &[u8], idx: usize) -> bool {
+    bits[idx >> 3] & (1 << (idx & 7)) != 0
+}
+
+fn set_bit(bits: &mut [u8], idx: usize) {
+    bits[idx >> 3] |= 1 << (idx & 7);
+}
+
+fn sub(a: Point, b: Point) -> Point {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: Point, b: Point) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: Point, b: Point) -> Point {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+/// Run-length encode a row of visibility data, the inverse of
+/// `decompress_vis`.  Runs of zero bytes are written as a zero followed by the
+/// length of the run, up to 255.
+pub fn compress_vis(row: &[u8]) -> Vec<u8> {
+    let mut compressed = Vec::with_capacity(row.len());
+    let mut bytes = row.iter().peekable();
+
+    while let Some(&byte) = bytes.next() {
+        compressed.push(byte);
+
+        if byte != 0 {
+            continue;
+        }
+
+        let mut run = 1u8;
+
+        while run < u8::MAX && bytes.next_if_eq(&&0).is_some() {
+            run += 1;
+        }
+
+        compressed.push(run);
+    }
+
+    compressed
+}
+
+impl Bsp {
+    /// Replaces the visibility lump with visibility computed from a portal
+    /// file (see `PortalFile::compute_vis`), and points each leaf of the world
+    /// at its compressed row.  Other leaves are left without visibility.  Fails if the portal file's leaf count does not
+    /// match the world's, or a portal leads to a leaf beyond it.
+    pub fn vis_from_portals(
+        &mut self,
+        portals: &PortalFile,
+    ) -> BinParseResult<()> {
+        let vis_leaf_count =
+            self.models.first().map_or(0, |world| world.vis_leaf_count);
+
+        if portals.leaf_count != vis_leaf_count
+            || portals.leaf_count as usize >= self.leaves.len()
+        {
+            return Err(BinParseError::Parse(format!(
+                "Portal file has {} leaves, but BSP has {vis_leaf_count} \
+                 visible leaves and {} leaves in total",
+                portals.leaf_count,
+                self.leaves.len(),
+            )));
+        }
+
+        let mut visibility = Vec::new();
+
+        for (idx, row) in portals.compute_vis()?.into_iter().enumerate() {
+            let offset = i32::try_from(visibility.len()).map_err(|_| {
+                BinParseError::Parse(String::from("Visibility lump too large"))
+            })?;
+
+            self.leaves[idx + 1].vis_offset = offset;
+            visibility.extend(compress_vis(&row));
+        }
+
+        // Leaf 0 and leaves outside the world's visible leaves (e.g. those of
+        // submodels) have no visibility
+        let covered = 1..=(portals.leaf_count as usize);
+
+        for (idx, leaf) in self.leaves.iter_mut().enumerate() {
+            if !covered.contains(&idx) {
+                leaf.vis_offset = -1;
+            }
+        }
+
+        self.visibility = visibility;
+        Ok(())
+    }
+}
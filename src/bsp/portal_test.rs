@@ -0,0 +1,232 @@
+use super::test_fixtures::leaf;
+use crate::{bsp, BinParseError, TextParseError};
+use bsp::{
+    compress_vis, contents, decompress_vis, Bsp, Model, Portal, PortalFile,
+};
+use std::vec::Vec;
+
+// Cells of 64 x 64 x 64 units forming a U-shaped corridor, indexed by their
+// minimum corner in units of 64
+const CELLS: [[f32; 2]; 7] = [
+    [0.0, 0.0],
+    [1.0, 0.0],
+    [2.0, 0.0],
+    [2.0, 1.0],
+    [2.0, 2.0],
+    [1.0, 2.0],
+    [0.0, 2.0],
+];
+
+fn cell_center(leaf: usize) -> [f32; 3] {
+    let [x, y] = CELLS[leaf];
+    [x * 64.0 + 32.0, y * 64.0 + 32.0, 32.0]
+}
+
+// Portal between adjacent cells, with the winding facing the first leaf the
+// way qbsp writes them
+fn portal(leaves: [u32; 2]) -> Portal {
+    let [a, b] = leaves.map(|leaf| cell_center(leaf as usize));
+    let mid = [(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0];
+
+    let mut winding = if a[0] != b[0] {
+        vec![
+            [mid[0], mid[1] - 32.0, 0.0],
+            [mid[0], mid[1] + 32.0, 0.0],
+            [mid[0], mid[1] + 32.0, 64.0],
+            [mid[0], mid[1] - 32.0, 64.0],
+        ]
+    } else {
+        vec![
+            [mid[0] - 32.0, mid[1], 0.0],
+            [mid[0] + 32.0, mid[1], 0.0],
+            [mid[0] + 32.0, mid[1], 64.0],
+            [mid[0] - 32.0, mid[1], 64.0],
+        ]
+    };
+
+    let edge = |from: [f32; 3], to: [f32; 3]| {
+        [to[0] - from[0], to[1] - from[1], to[2] - from[2]]
+    };
+    let u = edge(winding[1], winding[0]);
+    let v = edge(winding[1], winding[2]);
+    let normal = [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ];
+    let toward_first = edge(winding[0], a);
+
+    if (0..3).map(|i| normal[i] * toward_first[i]).sum::<f32>() < 0.0 {
+        winding.reverse();
+    }
+
+    Portal { leaves, winding }
+}
+
+fn corridor() -> PortalFile {
+    PortalFile {
+        leaf_count: 7,
+        portals: (0..6).map(|leaf| portal([leaf, leaf + 1])).collect(),
+    }
+}
+
+fn visible(row: &[u8]) -> Vec<usize> {
+    (0..7)
+        .filter(|&i| row[i >> 3] & (1 << (i & 7)) != 0)
+        .collect()
+}
+
+#[test]
+fn parse_portal_file() {
+    let text = b"PRT1\n3\n2\n\
+        4 0 1 (64 0 0 ) (64 64 0 ) (64 64 64 ) (64 0 64 )\n\
+        3 1 2 (128 0 0 ) (128 64 0 ) (128 64 64 )\n";
+
+    let portals = PortalFile::parse(&mut &text[..]).unwrap();
+
+    assert_eq!(portals.leaf_count, 3);
+    assert_eq!(
+        portals.portals,
+        [
+            Portal {
+                leaves: [0, 1],
+                winding: vec![
+                    [64.0, 0.0, 0.0],
+                    [64.0, 64.0, 0.0],
+                    [64.0, 64.0, 64.0],
+                    [64.0, 0.0, 64.0],
+                ],
+            },
+            Portal {
+                leaves: [1, 2],
+                winding: vec![
+                    [128.0, 0.0, 0.0],
+                    [128.0, 64.0, 0.0],
+                    [128.0, 64.0, 64.0],
+                ],
+            },
+        ]
+    );
+}
+
+#[test]
+fn parse_bad_portal_files() {
+    let bad_files: [&[u8]; 5] = [
+        b"PRT2\n1\n0\n",
+        b"PRT1\n2\n1\n",
+        b"PRT1\n2\n1\n4 0 2 (0 0 0) (0 1 0) (0 1 1) (0 0 1)\n",
+        b"PRT1\n2\n1\n2 0 1 (0 0 0) (0 1 0)\n",
+        b"PRT1\n2\n1\n3 0 1 (0 0 0) (0 1 0) (0 1 1) (0 0 1)\n",
+    ];
+
+    for text in bad_files {
+        let err = PortalFile::parse(&mut &text[..]).unwrap_err();
+        assert!(matches!(err, TextParseError::Parser(_)));
+    }
+}
+
+#[test]
+fn corridor_vis() {
+    let rows = corridor().compute_vis().unwrap();
+    assert_eq!(rows.len(), 7);
+
+    // Around the first corner, but not the second
+    let first = visible(&rows[0]);
+    assert!([0, 1, 2, 3].iter().all(|leaf| first.contains(leaf)));
+    assert!(!first.contains(&5));
+    assert!(!first.contains(&6));
+
+    // Everything is visible from the middle of the U
+    assert_eq!(visible(&rows[3]), [0, 1, 2, 3, 4, 5, 6]);
+
+    let last = visible(&rows[6]);
+    assert!([3, 4, 5, 6].iter().all(|leaf| last.contains(leaf)));
+    assert!(!last.contains(&0));
+    assert!(!last.contains(&1));
+}
+
+#[test]
+fn compress_vis_runs() {
+    let mut row = vec![0x81, 0, 0, 0x02];
+    row.extend([0; 300]);
+    row.push(0x10);
+
+    let compressed = compress_vis(&row);
+
+    assert_eq!(&compressed[..5], &[0x81, 0x00, 0x02, 0x02, 0x00]);
+    assert_eq!(&compressed[5..], &[0xff, 0x00, 45, 0x10]);
+    assert_eq!(decompress_vis(&compressed, row.len()).unwrap(), row);
+}
+
+fn corridor_bsp() -> Bsp {
+    let mut leaves = vec![leaf(contents::SOLID)];
+    leaves.extend((0..7).map(|_| leaf(contents::EMPTY)));
+
+    Bsp {
+        leaves,
+        models: vec![Model {
+            mins: [0.0; 3],
+            maxs: [192.0, 192.0, 64.0],
+            origin: [0.0; 3],
+            head_nodes: [0, 0, 0, 0],
+            vis_leaf_count: 7,
+            first_face: 0,
+            face_count: 0,
+        }],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn bsp_vis_from_portals() {
+    let mut bsp = corridor_bsp();
+    let portals = corridor();
+    bsp.vis_from_portals(&portals).unwrap();
+
+    let rows = portals.compute_vis().unwrap();
+    let pvs = bsp.pvs().unwrap();
+
+    assert_eq!(bsp.leaves[0].vis_offset, -1);
+
+    for (idx, row) in rows.iter().enumerate() {
+        assert_eq!(pvs.row(idx as u32 + 1).unwrap(), &row[..]);
+    }
+
+    assert!(pvs.is_visible(1, 2));
+    assert!(!pvs.is_visible(1, 7));
+}
+
+#[test]
+fn bsp_vis_extra_leaves() {
+    let mut bsp = corridor_bsp();
+    let mut submodel_leaf = leaf(contents::EMPTY);
+    submodel_leaf.vis_offset = 1000;
+    bsp.leaves.push(submodel_leaf);
+    bsp.leaves[0].vis_offset = 1000;
+
+    bsp.vis_from_portals(&corridor()).unwrap();
+
+    assert_eq!(bsp.leaves[0].vis_offset, -1);
+    assert_eq!(bsp.leaves[8].vis_offset, -1);
+    assert!(bsp.leaves[1..8].iter().all(|leaf| leaf.vis_offset >= 0));
+}
+
+#[test]
+fn bsp_vis_leaf_count_mismatch() {
+    let mut bsp = corridor_bsp();
+    bsp.models[0].vis_leaf_count = 6;
+
+    assert!(bsp.vis_from_portals(&corridor()).is_err());
+}
+
+#[test]
+fn vis_bad_portal_leaves() {
+    let mut portals = corridor();
+    portals.portals[2].leaves[1] = portals.leaf_count;
+
+    let err = portals.compute_vis().unwrap_err();
+    assert!(matches!(err, BinParseError::Parse(_)));
+
+    let mut bsp = corridor_bsp();
+    assert!(bsp.vis_from_portals(&portals).is_err());
+}
@@ -0,0 +1,22 @@
+mod repr;
+
+mod parser;
+
+mod vis;
+
+pub use repr::{
+    contents, surf_flags, Brush, BrushSide, EntryOffset, Leaf, TexInfo,
+    ENTRY_COUNT, MAGIC, VERSION,
+};
+
+pub(crate) use repr::Head;
+
+pub use parser::Parser;
+
+pub use vis::ClusterVis;
+
+#[cfg(test)]
+mod parser_test;
+
+#[cfg(test)]
+mod vis_test;
@@ -0,0 +1,115 @@
+use super::{Brush, BrushSide, ClusterVis, EntryOffset, Head, Leaf, TexInfo};
+use crate::bsp::parser::{decode_records, parse_entities_lump, read_exact};
+use crate::bsp::repr::{Layout, Record};
+use crate::bsp::{Entry, Plane, Vertex};
+use crate::qmap::QuakeMap;
+use crate::{BinParseError, BinParseResult};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::mem::size_of;
+use std::string::String;
+use std::vec::Vec;
+
+/// Quake II BSP parser
+#[derive(Debug)]
+pub struct Parser<'a, Reader: Seek + Read> {
+    cursor: &'a mut Reader,
+    start: u64,
+    header: Head,
+}
+
+impl<'a, Reader: Seek + Read> Parser<'a, Reader> {
+    pub fn new(cursor: &'a mut Reader) -> BinParseResult<Self> {
+        let start = cursor.stream_position()?;
+        let mut header_bytes = [0u8; size_of::<Head>()];
+        cursor.read_exact(&mut header_bytes[..])?;
+        let header = header_bytes.try_into()?;
+
+        Ok(Self {
+            cursor,
+            start,
+            header,
+        })
+    }
+
+    pub fn version(&self) -> u32 {
+        self.header.version()
+    }
+
+    pub fn lump_reader(
+        &mut self,
+        entry_offset: EntryOffset,
+    ) -> BinParseResult<io::Take<&mut Reader>> {
+        let Entry { offset, length } = self.header.entry(entry_offset);
+
+        let abs_offset = self
+            .start
+            .checked_add(offset.into())
+            .ok_or(BinParseError::Parse(String::from("Bad offset")))?;
+
+        self.cursor.seek(SeekFrom::Start(abs_offset))?;
+
+        Ok(self.cursor.take(length.into()))
+    }
+
+    pub fn lump_empty(&self, offset: EntryOffset) -> bool {
+        let length = self.header.entry(offset).length;
+        length == 0
+    }
+
+    /// Attempts to parse the entities lump, up to the first null byte
+    pub fn parse_entities(&mut self) -> BinParseResult<QuakeMap> {
+        let bytes = self.read_lump(EntryOffset::Entities)?;
+        parse_entities_lump(&bytes)
+    }
+
+    /// Attempts to parse the planes lump
+    pub fn parse_planes(&mut self) -> BinParseResult<Vec<Plane>> {
+        self.parse_records(EntryOffset::Planes)
+    }
+
+    /// Attempts to parse the vertices lump
+    pub fn parse_vertices(&mut self) -> BinParseResult<Vec<Vertex>> {
+        self.parse_records(EntryOffset::Vertices)
+    }
+
+    /// Attempts to parse the texture info lump
+    pub fn parse_tex_info(&mut self) -> BinParseResult<Vec<TexInfo>> {
+        self.parse_records(EntryOffset::TexInfo)
+    }
+
+    /// Attempts to parse the leaves lump
+    pub fn parse_leaves(&mut self) -> BinParseResult<Vec<Leaf>> {
+        self.parse_records(EntryOffset::Leaves)
+    }
+
+    /// Attempts to parse the brushes lump
+    pub fn parse_brushes(&mut self) -> BinParseResult<Vec<Brush>> {
+        self.parse_records(EntryOffset::Brushes)
+    }
+
+    /// Attempts to parse the brush sides lump
+    pub fn parse_brush_sides(&mut self) -> BinParseResult<Vec<BrushSide>> {
+        self.parse_records(EntryOffset::BrushSides)
+    }
+
+    /// Attempts to parse and decompress the visibility lump
+    pub fn parse_vis(&mut self) -> BinParseResult<ClusterVis> {
+        let bytes = self.read_lump(EntryOffset::Vis)?;
+        ClusterVis::from_bytes(&bytes)
+    }
+
+    fn read_lump(
+        &mut self,
+        entry_offset: EntryOffset,
+    ) -> BinParseResult<Vec<u8>> {
+        read_exact(self.lump_reader(entry_offset)?)
+    }
+
+    fn parse_records<R: Record>(
+        &mut self,
+        entry_offset: EntryOffset,
+    ) -> BinParseResult<Vec<R>> {
+        let bytes = self.read_lump(entry_offset)?;
+        decode_records(&bytes, Layout::Bsp29, entry_offset)
+    }
+}
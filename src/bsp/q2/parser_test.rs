@@ -0,0 +1,263 @@
+use crate::bsp::{q2, PlaneKind};
+use crate::BinParseError;
+use q2::{surf_flags, Brush, BrushSide, EntryOffset, Leaf};
+use std::io::Cursor;
+use std::mem::size_of;
+use std::vec::Vec;
+
+const HEAD_SZ: usize = size_of::<q2::Head>();
+
+fn bsp_bytes(lumps: &[(EntryOffset, Vec<u8>)]) -> Vec<u8> {
+    let mut bytes = vec![0u8; HEAD_SZ];
+    bytes[..4].copy_from_slice(&q2::MAGIC);
+    bytes[4..8].copy_from_slice(&q2::VERSION.to_le_bytes());
+
+    for (entry_offset, lump) in lumps {
+        let idx = usize::from(*entry_offset);
+        let offset = bytes.len() as u32;
+        let length = lump.len() as u32;
+        bytes[(8 + idx * 8)..(12 + idx * 8)]
+            .copy_from_slice(&offset.to_le_bytes());
+        bytes[(12 + idx * 8)..(16 + idx * 8)]
+            .copy_from_slice(&length.to_le_bytes());
+        bytes.extend(lump);
+    }
+
+    bytes
+}
+
+fn tex_info_bytes(texture: &[u8], flags: i32, value: i32) -> Vec<u8> {
+    let mut bytes = Vec::new();
+
+    for float in [1.0f32, 0.0, 0.0, 8.0, 0.0, -1.0, 0.0, 0.0] {
+        bytes.extend(float.to_le_bytes());
+    }
+
+    bytes.extend(flags.to_le_bytes());
+    bytes.extend(value.to_le_bytes());
+    let mut name = [0u8; 32];
+    name[..texture.len()].copy_from_slice(texture);
+    bytes.extend(name);
+    bytes.extend((-1i32).to_le_bytes());
+    bytes
+}
+
+#[test]
+fn head_size() {
+    assert_eq!(HEAD_SZ, 160);
+}
+
+#[test]
+fn parse_bad_magic() {
+    let mut bytes = bsp_bytes(&[]);
+    bytes[..4].copy_from_slice(b"VBSP");
+    let mut cursor = Cursor::new(bytes);
+    let err = q2::Parser::new(&mut cursor).unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn parse_bad_version() {
+    let mut bytes = bsp_bytes(&[]);
+    bytes[4] = 46;
+    let mut cursor = Cursor::new(bytes);
+    let err = q2::Parser::new(&mut cursor).unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn parse_entities() {
+    let entities = b"{\n\"classname\" \"worldspawn\"\n}\n\0".to_vec();
+    let bytes = bsp_bytes(&[(EntryOffset::Entities, entities)]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = q2::Parser::new(&mut cursor).unwrap();
+    let map = parser.parse_entities().unwrap();
+
+    assert_eq!(parser.version(), 38);
+    assert_eq!(map.entities.len(), 1);
+    assert_eq!(map.entities[0].edict[0].1.as_bytes(), b"worldspawn");
+}
+
+#[test]
+fn parse_planes() {
+    let mut planes = Vec::new();
+
+    for float in [0.0f32, 0.0, 1.0, 64.0] {
+        planes.extend(float.to_le_bytes());
+    }
+
+    planes.extend(2i32.to_le_bytes());
+
+    let bytes = bsp_bytes(&[(EntryOffset::Planes, planes)]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = q2::Parser::new(&mut cursor).unwrap();
+    let planes = parser.parse_planes().unwrap();
+
+    assert_eq!(planes.len(), 1);
+    assert_eq!(planes[0].normal, [0.0, 0.0, 1.0]);
+    assert_eq!(planes[0].dist, 64.0);
+    assert_eq!(planes[0].kind, PlaneKind::Z);
+}
+
+#[test]
+fn parse_tex_info() {
+    let mut tex_info = tex_info_bytes(b"e1u1/floor1_3", 0, 0);
+    tex_info.extend(tex_info_bytes(
+        b"e1u1/light1",
+        surf_flags::LIGHT | surf_flags::NODRAW,
+        300,
+    ));
+
+    let bytes = bsp_bytes(&[(EntryOffset::TexInfo, tex_info)]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = q2::Parser::new(&mut cursor).unwrap();
+    let tex_info = parser.parse_tex_info().unwrap();
+
+    assert_eq!(tex_info.len(), 2);
+    assert_eq!(tex_info[0].texture_to_string().unwrap(), "e1u1/floor1_3");
+    assert_eq!(tex_info[0].vecs[0], [1.0, 0.0, 0.0, 8.0]);
+    assert_eq!(tex_info[0].next, -1);
+    assert_eq!(tex_info[1].texture_to_string().unwrap(), "e1u1/light1");
+    assert_eq!(tex_info[1].flags, surf_flags::LIGHT | surf_flags::NODRAW);
+    assert_eq!(tex_info[1].value, 300);
+}
+
+#[test]
+fn parse_tex_info_bad_length() {
+    let mut tex_info = tex_info_bytes(b"e1u1/floor1_3", 0, 0);
+    tex_info.pop();
+
+    let bytes = bsp_bytes(&[(EntryOffset::TexInfo, tex_info)]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = q2::Parser::new(&mut cursor).unwrap();
+    let err = parser.parse_tex_info().unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn parse_brushes() {
+    let mut brushes = Vec::new();
+
+    for word in [0u32, 6, q2::contents::SOLID as u32] {
+        brushes.extend(word.to_le_bytes());
+    }
+
+    for word in [6u32, 5, (q2::contents::WATER | q2::contents::DETAIL) as u32] {
+        brushes.extend(word.to_le_bytes());
+    }
+
+    let mut sides = Vec::new();
+
+    for (plane, tex_info) in [(0u16, 0i16), (1, -1)] {
+        sides.extend(plane.to_le_bytes());
+        sides.extend(tex_info.to_le_bytes());
+    }
+
+    let bytes = bsp_bytes(&[
+        (EntryOffset::Brushes, brushes),
+        (EntryOffset::BrushSides, sides),
+    ]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = q2::Parser::new(&mut cursor).unwrap();
+
+    assert_eq!(
+        parser.parse_brushes().unwrap(),
+        [
+            Brush {
+                first_side: 0,
+                side_count: 6,
+                contents: q2::contents::SOLID,
+            },
+            Brush {
+                first_side: 6,
+                side_count: 5,
+                contents: q2::contents::WATER | q2::contents::DETAIL,
+            },
+        ]
+    );
+    assert_eq!(
+        parser.parse_brush_sides().unwrap(),
+        [
+            BrushSide {
+                plane: 0,
+                tex_info: 0,
+            },
+            BrushSide {
+                plane: 1,
+                tex_info: -1,
+            },
+        ]
+    );
+}
+
+#[test]
+fn parse_leaves() {
+    let mut leaves = Vec::new();
+    leaves.extend(0i32.to_le_bytes());
+    leaves.extend(3i16.to_le_bytes());
+    leaves.extend(1i16.to_le_bytes());
+
+    for coord in [-64i16, -64, 0, 64, 64, 128] {
+        leaves.extend(coord.to_le_bytes());
+    }
+
+    for index in [4u16, 2, 7, 1] {
+        leaves.extend(index.to_le_bytes());
+    }
+
+    let bytes = bsp_bytes(&[(EntryOffset::Leaves, leaves)]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = q2::Parser::new(&mut cursor).unwrap();
+
+    assert_eq!(
+        parser.parse_leaves().unwrap(),
+        [Leaf {
+            contents: 0,
+            cluster: 3,
+            area: 1,
+            mins: [-64, -64, 0],
+            maxs: [64, 64, 128],
+            first_leaf_face: 4,
+            leaf_face_count: 2,
+            first_leaf_brush: 7,
+            leaf_brush_count: 1,
+        }]
+    );
+}
+
+#[test]
+fn parse_vis() {
+    let mut vis = Vec::new();
+    vis.extend(2i32.to_le_bytes());
+
+    for offset in [20u32, 21, 22, 21] {
+        vis.extend(offset.to_le_bytes());
+    }
+
+    vis.extend([0b01, 0b11, 0b10]);
+
+    let bytes = bsp_bytes(&[(EntryOffset::Vis, vis)]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = q2::Parser::new(&mut cursor).unwrap();
+    let vis = parser.parse_vis().unwrap();
+
+    assert_eq!(vis.cluster_count(), 2);
+    assert!(vis.is_visible(0, 0));
+    assert!(!vis.is_visible(0, 1));
+    assert!(vis.is_audible(0, 1));
+    assert!(vis.is_visible(1, 1));
+    assert!(!vis.is_visible(1, 0));
+}
+
+#[test]
+fn parse_no_vis() {
+    let bytes = bsp_bytes(&[]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = q2::Parser::new(&mut cursor).unwrap();
+
+    assert!(parser.lump_empty(EntryOffset::Vis));
+    assert_eq!(parser.parse_vis().unwrap().cluster_count(), 0);
+}
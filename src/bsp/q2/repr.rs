@@ -0,0 +1,276 @@
+use crate::bsp::repr::{Layout, Record};
+use crate::bsp::Entry;
+use crate::common::LeReader;
+use crate::{slice_to_cstring, BinParseError, BinParseResult};
+use std::ffi::{CString, IntoStringError};
+use std::mem::size_of;
+use std::string::String;
+
+pub const MAGIC: [u8; 4] = *b"IBSP";
+pub const VERSION: u32 = 38;
+pub const ENTRY_COUNT: usize = 19;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(usize)]
+pub enum EntryOffset {
+    Entities = 0,
+    Planes,
+    Vertices,
+    Vis,
+    Nodes,
+    TexInfo,
+    Faces,
+    Light,
+    Leaves,
+    LeafFaces,
+    LeafBrushes,
+    Edges,
+    SurfEdges,
+    Models,
+    Brushes,
+    BrushSides,
+    Pop,
+    Areas,
+    AreaPortals,
+}
+
+impl From<EntryOffset> for usize {
+    fn from(offset: EntryOffset) -> Self {
+        offset as usize
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(C, packed)]
+pub struct Head {
+    magic: [u8; 4],
+    version: u32,
+    entries: [Entry; ENTRY_COUNT],
+}
+
+impl Head {
+    pub fn entry(&self, offset: EntryOffset) -> Entry {
+        self.entries[usize::from(offset)]
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+}
+
+impl TryFrom<[u8; size_of::<Head>()]> for Head {
+    type Error = BinParseError;
+
+    fn try_from(bytes: [u8; size_of::<Head>()]) -> BinParseResult<Head> {
+        let mut reader = LeReader::new(&bytes);
+        let magic = reader.bytes();
+
+        if magic != MAGIC {
+            return Err(BinParseError::Parse(format!(
+                "Bad magic number {magic:?}"
+            )));
+        }
+
+        let version = reader.u32();
+
+        if version != VERSION {
+            return Err(BinParseError::Parse(format!(
+                "Unrecognized Quake II BSP version {version}"
+            )));
+        }
+
+        let entries = [(); ENTRY_COUNT].map(|_| Entry {
+            offset: reader.u32(),
+            length: reader.u32(),
+        });
+
+        Ok(Head {
+            magic,
+            version,
+            entries,
+        })
+    }
+}
+
+/// Brush contents flags, as used by `Brush::contents` and in
+/// `qmap::Quake2SurfaceExtension::content_flags`
+pub mod contents {
+    pub const SOLID: i32 = 0x1;
+    pub const WINDOW: i32 = 0x2;
+    pub const AUX: i32 = 0x4;
+    pub const LAVA: i32 = 0x8;
+    pub const SLIME: i32 = 0x10;
+    pub const WATER: i32 = 0x20;
+    pub const MIST: i32 = 0x40;
+    pub const AREA_PORTAL: i32 = 0x8000;
+    pub const PLAYER_CLIP: i32 = 0x10000;
+    pub const MONSTER_CLIP: i32 = 0x20000;
+    pub const CURRENT_0: i32 = 0x40000;
+    pub const CURRENT_90: i32 = 0x80000;
+    pub const CURRENT_180: i32 = 0x100000;
+    pub const CURRENT_270: i32 = 0x200000;
+    pub const CURRENT_UP: i32 = 0x400000;
+    pub const CURRENT_DOWN: i32 = 0x800000;
+    pub const ORIGIN: i32 = 0x1000000;
+    pub const MONSTER: i32 = 0x2000000;
+    pub const DEAD_MONSTER: i32 = 0x4000000;
+    pub const DETAIL: i32 = 0x8000000;
+    pub const TRANSLUCENT: i32 = 0x10000000;
+    pub const LADDER: i32 = 0x20000000;
+}
+
+/// Surface flags, as used by `TexInfo::flags` and in
+/// `qmap::Quake2SurfaceExtension::surface_flags`
+pub mod surf_flags {
+    /// Emits light with intensity `TexInfo::value`
+    pub const LIGHT: i32 = 0x1;
+
+    /// Reduced friction
+    pub const SLICK: i32 = 0x2;
+    pub const SKY: i32 = 0x4;
+
+    /// Turbulent warping, e.g. liquids
+    pub const WARP: i32 = 0x8;
+    pub const TRANS33: i32 = 0x10;
+    pub const TRANS66: i32 = 0x20;
+
+    /// Texture scrolls
+    pub const FLOWING: i32 = 0x40;
+
+    /// Not drawn
+    pub const NODRAW: i32 = 0x80;
+}
+
+/// Texture projection and surface properties of a face
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TexInfo {
+    /// S and T vectors: the texture coordinate along each is
+    /// `dot(vec[..3], point) + vec[3]`
+    pub vecs: [[f32; 4]; 2],
+    pub flags: i32,
+
+    /// Surface value, e.g. light intensity
+    pub value: i32,
+
+    /// Texture name (32 bytes, null-terminated), relative to `textures/`
+    pub texture: [u8; 32],
+
+    /// Next texture info in an animation sequence, or -1
+    pub next: i32,
+}
+
+impl TexInfo {
+    /// Obtain the texture name as a C string
+    pub fn texture_to_cstring(&self) -> CString {
+        slice_to_cstring(&self.texture)
+    }
+
+    /// Attempt to interpret the texture name as UTF-8 encoded string
+    pub fn texture_to_string(&self) -> Result<String, IntoStringError> {
+        self.texture_to_cstring().into_string()
+    }
+}
+
+impl Record for TexInfo {
+    fn size(_layout: Layout) -> usize {
+        76
+    }
+
+    fn from_bytes(bytes: &[u8], _layout: Layout) -> BinParseResult<Self> {
+        let mut reader = LeReader::new(bytes);
+
+        Ok(TexInfo {
+            vecs: [reader.f32s(), reader.f32s()],
+            flags: reader.i32(),
+            value: reader.i32(),
+            texture: reader.bytes(),
+            next: reader.i32(),
+        })
+    }
+}
+
+/// Leaf of the BSP tree.  Leaves are grouped into clusters for visibility.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Leaf {
+    pub contents: i32,
+
+    /// Visibility cluster, or -1 if the leaf is not in one (e.g. solid)
+    pub cluster: i16,
+    pub area: i16,
+    pub mins: [i16; 3],
+    pub maxs: [i16; 3],
+    pub first_leaf_face: u16,
+    pub leaf_face_count: u16,
+    pub first_leaf_brush: u16,
+    pub leaf_brush_count: u16,
+}
+
+impl Record for Leaf {
+    fn size(_layout: Layout) -> usize {
+        28
+    }
+
+    fn from_bytes(bytes: &[u8], _layout: Layout) -> BinParseResult<Self> {
+        let mut reader = LeReader::new(bytes);
+
+        Ok(Leaf {
+            contents: reader.i32(),
+            cluster: reader.i16(),
+            area: reader.i16(),
+            mins: [reader.i16(), reader.i16(), reader.i16()],
+            maxs: [reader.i16(), reader.i16(), reader.i16()],
+            first_leaf_face: reader.u16(),
+            leaf_face_count: reader.u16(),
+            first_leaf_brush: reader.u16(),
+            leaf_brush_count: reader.u16(),
+        })
+    }
+}
+
+/// Convex volume bounded by a contiguous range of brush sides
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Brush {
+    pub first_side: u32,
+    pub side_count: u32,
+    pub contents: i32,
+}
+
+impl Record for Brush {
+    fn size(_layout: Layout) -> usize {
+        12
+    }
+
+    fn from_bytes(bytes: &[u8], _layout: Layout) -> BinParseResult<Self> {
+        let mut reader = LeReader::new(bytes);
+
+        Ok(Brush {
+            first_side: reader.u32(),
+            side_count: reader.u32(),
+            contents: reader.i32(),
+        })
+    }
+}
+
+/// Bounding plane of a brush, facing outward
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BrushSide {
+    pub plane: u16,
+
+    /// Texture info of the side, or -1 if it has none (e.g. bevels)
+    pub tex_info: i16,
+}
+
+impl Record for BrushSide {
+    fn size(_layout: Layout) -> usize {
+        4
+    }
+
+    fn from_bytes(bytes: &[u8], _layout: Layout) -> BinParseResult<Self> {
+        let mut reader = LeReader::new(bytes);
+
+        Ok(BrushSide {
+            plane: reader.u16(),
+            tex_info: reader.i16(),
+        })
+    }
+}
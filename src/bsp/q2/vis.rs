@@ -0,0 +1,108 @@
+use crate::bsp::decompress_vis;
+use crate::{BinParseError, BinParseResult};
+use std::string::String;
+use std::vec::Vec;
+
+/// Cluster-based visibility.  For each cluster there is a potentially visible
+/// set (PVS) and potentially hearable set (PHS) of clusters, where bit `i`
+/// (least significant bit first) of a row is set if cluster `i` is included.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClusterVis {
+    pvs: Vec<Vec<u8>>,
+    phs: Vec<Vec<u8>>,
+}
+
+impl ClusterVis {
+    /// Decode and decompress the visibility lump.  An empty lump yields no
+    /// clusters.
+    pub fn from_bytes(bytes: &[u8]) -> BinParseResult<Self> {
+        if bytes.is_empty() {
+            return Ok(Self {
+                pvs: Vec::new(),
+                phs: Vec::new(),
+            });
+        }
+
+        let count_bytes = bytes.get(..4).ok_or(BinParseError::Parse(
+            String::from("Missing cluster count"),
+        ))?;
+        let count = i32::from_le_bytes(count_bytes.try_into().unwrap());
+        let count = usize::try_from(count).map_err(|_| {
+            BinParseError::Parse(format!("Bad cluster count {count}"))
+        })?;
+
+        let offsets = count
+            .checked_mul(8)
+            .and_then(|length| bytes.get(4..(4 + length)))
+            .ok_or(BinParseError::Parse(String::from(
+                "Cluster offsets cut off",
+            )))?;
+
+        let row_length = count.div_ceil(8);
+        let mut pvs = Vec::with_capacity(count);
+        let mut phs = Vec::with_capacity(count);
+
+        for chunk in offsets.chunks_exact(8) {
+            for (rows, offset) in
+                [(&mut pvs, &chunk[..4]), (&mut phs, &chunk[4..])]
+            {
+                let offset = u32::from_le_bytes(offset.try_into().unwrap());
+                let compressed =
+                    bytes.get((offset as usize)..).ok_or_else(|| {
+                        BinParseError::Parse(format!(
+                            "Visibility offset {offset} out of range"
+                        ))
+                    })?;
+
+                rows.push(decompress_vis(compressed, row_length)?);
+            }
+        }
+
+        Ok(Self { pvs, phs })
+    }
+
+    pub fn cluster_count(&self) -> usize {
+        self.pvs.len()
+    }
+
+    /// Decompressed PVS row for a cluster, or `None` if the cluster does not
+    /// exist
+    pub fn pvs_row(&self, cluster: u32) -> Option<&[u8]> {
+        self.pvs.get(cluster as usize).map(|row| &row[..])
+    }
+
+    /// Decompressed PHS row for a cluster, or `None` if the cluster does not
+    /// exist
+    pub fn phs_row(&self, cluster: u32) -> Option<&[u8]> {
+        self.phs.get(cluster as usize).map(|row| &row[..])
+    }
+
+    /// Determine whether cluster `to` is potentially visible from cluster
+    /// `from`
+    pub fn is_visible(&self, from: u32, to: u32) -> bool {
+        row_contains(self.pvs_row(from), to)
+    }
+
+    /// Determine whether sounds in cluster `to` may be heard from cluster
+    /// `from`
+    pub fn is_audible(&self, from: u32, to: u32) -> bool {
+        row_contains(self.phs_row(from), to)
+    }
+
+    /// Iterate over the clusters potentially visible from a cluster
+    pub fn visible_clusters(
+        &self,
+        from: u32,
+    ) -> impl Iterator<Item = u32> + '_ {
+        (0..(self.cluster_count() as u32))
+            .filter(move |&to| self.is_visible(from, to))
+    }
+}
+
+fn row_contains(row: Option<&[u8]>, cluster: u32) -> bool {
+    let bit = cluster as usize;
+
+    row.and_then(|row| row.get(bit >> 3))
+        .map(|byte| byte & (1 << (bit & 7)) != 0)
+        .unwrap_or(false)
+}
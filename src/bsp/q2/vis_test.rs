@@ -0,0 +1,60 @@
+use crate::bsp::q2::ClusterVis;
+use crate::BinParseError;
+use std::vec::Vec;
+
+fn vis_bytes(rows: &[(&[u8], &[u8])]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend((rows.len() as i32).to_le_bytes());
+
+    let mut offset = 4 + 8 * rows.len() as u32;
+    let mut data = Vec::<u8>::new();
+
+    for (pvs, phs) in rows {
+        for row in [pvs, phs] {
+            bytes.extend(offset.to_le_bytes());
+            offset += row.len() as u32;
+            data.extend(*row);
+        }
+    }
+
+    bytes.extend(data);
+    bytes
+}
+
+#[test]
+fn compressed_rows() {
+    // 12 clusters, 2 bytes per row
+    let mut rows: Vec<(&[u8], &[u8])> = vec![(&[0, 2], &[0xff, 0x0f]); 12];
+    rows[3] = (&[0b1000, 0], &[0xff, 0x0f]);
+    rows[11] = (&[0, 1, 0b1000], &[0xff, 0x0f]);
+    let vis = ClusterVis::from_bytes(&vis_bytes(&rows)).unwrap();
+
+    assert_eq!(vis.cluster_count(), 12);
+    assert_eq!(vis.pvs_row(3), Some(&[0b1000, 0][..]));
+    assert_eq!(vis.pvs_row(11), Some(&[0, 0b1000][..]));
+    assert_eq!(vis.phs_row(0), Some(&[0xff, 0x0f][..]));
+    assert_eq!(vis.visible_clusters(3).collect::<Vec<_>>(), [3]);
+    assert_eq!(vis.visible_clusters(11).collect::<Vec<_>>(), [11]);
+    assert_eq!(vis.visible_clusters(0).count(), 0);
+    assert!(vis.is_audible(0, 11));
+    assert!(!vis.is_audible(0, 12));
+    assert!(!vis.is_visible(12, 0));
+}
+
+#[test]
+fn bad_offset() {
+    let mut bytes = vis_bytes(&[(&[1], &[1])]);
+    bytes[4..8].copy_from_slice(&100u32.to_le_bytes());
+    let err = ClusterVis::from_bytes(&bytes).unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn offsets_cut_off() {
+    let mut bytes = vis_bytes(&[(&[1], &[1])]);
+    bytes[..4].copy_from_slice(&5i32.to_le_bytes());
+    let err = ClusterVis::from_bytes(&bytes).unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
@@ -0,0 +1,15 @@
+mod repr;
+
+mod parser;
+
+pub use repr::{
+    Brush, BrushSide, DrawIndex, DrawVertex, EntryOffset, Plane, Shader,
+    Surface, SurfaceKind, ENTRY_COUNT, MAGIC, VERSION,
+};
+
+pub(crate) use repr::Head;
+
+pub use parser::Parser;
+
+#[cfg(test)]
+mod parser_test;
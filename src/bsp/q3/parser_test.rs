@@ -0,0 +1,237 @@
+use crate::bsp::q3;
+use crate::BinParseError;
+use q3::{Brush, BrushSide, EntryOffset, Plane, SurfaceKind};
+use std::io::Cursor;
+use std::mem::size_of;
+use std::vec::Vec;
+
+const HEAD_SZ: usize = size_of::<q3::Head>();
+
+fn bsp_bytes(lumps: &[(EntryOffset, Vec<u8>)]) -> Vec<u8> {
+    let mut bytes = vec![0u8; HEAD_SZ];
+    bytes[..4].copy_from_slice(&q3::MAGIC);
+    bytes[4..8].copy_from_slice(&q3::VERSION.to_le_bytes());
+
+    for (entry_offset, lump) in lumps {
+        let idx = usize::from(*entry_offset);
+        let offset = bytes.len() as u32;
+        let length = lump.len() as u32;
+        bytes[(8 + idx * 8)..(12 + idx * 8)]
+            .copy_from_slice(&offset.to_le_bytes());
+        bytes[(12 + idx * 8)..(16 + idx * 8)]
+            .copy_from_slice(&length.to_le_bytes());
+        bytes.extend(lump);
+    }
+
+    bytes
+}
+
+fn words(values: &[i32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn floats(values: &[f32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn vertex_bytes(position: [f32; 3], tex_coords: [f32; 2]) -> Vec<u8> {
+    let mut bytes = floats(&position);
+    bytes.extend(floats(&tex_coords));
+    bytes.extend(floats(&[0.25, 0.75, 0.0, 0.0, 1.0]));
+    bytes.extend([255, 128, 0, 255]);
+    bytes
+}
+
+fn surface_bytes(
+    kind: i32,
+    vertices: [i32; 2],
+    patch_size: [i32; 2],
+) -> Vec<u8> {
+    let mut bytes = words(&[2, -1, kind, vertices[0], vertices[1], 0, 0]);
+    bytes.extend(words(&[0, 16, 32, 8, 4]));
+    bytes.extend(floats(&[0.0; 12]));
+    bytes.extend(words(&patch_size));
+    bytes
+}
+
+#[test]
+fn head_size() {
+    assert_eq!(HEAD_SZ, 144);
+}
+
+#[test]
+fn parse_wrong_version() {
+    let mut bytes = bsp_bytes(&[]);
+    bytes[4] = 38;
+    let mut cursor = Cursor::new(bytes);
+    let err = q3::Parser::new(&mut cursor).unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn parse_entities() {
+    let entities = br#"
+{
+"classname" "worldspawn"
+"message" "Temple of Retribution"
+}
+{
+"classname" "info_player_deathmatch"
+"origin" "-64 128 24"
+}
+"#
+    .to_vec();
+    let bytes = bsp_bytes(&[(EntryOffset::Entities, entities)]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = q3::Parser::new(&mut cursor).unwrap();
+    let map = parser.parse_entities().unwrap();
+
+    assert_eq!(parser.version(), 46);
+    assert_eq!(map.entities.len(), 2);
+    assert_eq!(map.entities[1].edict[1].1.as_bytes(), b"-64 128 24");
+}
+
+#[test]
+fn parse_shaders() {
+    let mut shaders = Vec::new();
+    let mut name = [0u8; 64];
+    name[..22].copy_from_slice(b"textures/base_wall/c_m");
+    shaders.extend(name);
+    shaders.extend(words(&[0x2000, 1]));
+
+    let bytes = bsp_bytes(&[(EntryOffset::Shaders, shaders)]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = q3::Parser::new(&mut cursor).unwrap();
+    let shaders = parser.parse_shaders().unwrap();
+
+    assert_eq!(shaders.len(), 1);
+    assert_eq!(
+        shaders[0].name_to_string().unwrap(),
+        "textures/base_wall/c_m"
+    );
+    assert_eq!(shaders[0].surface_flags, 0x2000);
+    assert_eq!(shaders[0].content_flags, 1);
+}
+
+#[test]
+fn parse_planes_and_brushes() {
+    let planes = floats(&[1.0, 0.0, 0.0, 32.0, 0.0, -1.0, 0.0, 8.0]);
+    let brushes = words(&[0, 2, 0]);
+    let sides = words(&[0, 0, 1, 0]);
+
+    let bytes = bsp_bytes(&[
+        (EntryOffset::Planes, planes),
+        (EntryOffset::Brushes, brushes),
+        (EntryOffset::BrushSides, sides),
+    ]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = q3::Parser::new(&mut cursor).unwrap();
+
+    assert_eq!(
+        parser.parse_planes().unwrap(),
+        [
+            Plane {
+                normal: [1.0, 0.0, 0.0],
+                dist: 32.0,
+            },
+            Plane {
+                normal: [0.0, -1.0, 0.0],
+                dist: 8.0,
+            },
+        ]
+    );
+    assert_eq!(
+        parser.parse_brushes().unwrap(),
+        [Brush {
+            first_side: 0,
+            side_count: 2,
+            shader: 0,
+        }]
+    );
+    assert_eq!(
+        parser.parse_brush_sides().unwrap(),
+        [
+            BrushSide {
+                plane: 0,
+                shader: 0,
+            },
+            BrushSide {
+                plane: 1,
+                shader: 0,
+            },
+        ]
+    );
+}
+
+#[test]
+fn parse_surfaces() {
+    let mut vertices = Vec::new();
+
+    for i in 0..12 {
+        vertices.extend(vertex_bytes([i as f32, 0.0, 0.0], [0.5, 1.0]));
+    }
+
+    let mut surfaces = surface_bytes(1, [0, 3], [0, 0]);
+    surfaces[20..28].copy_from_slice(&words(&[0, 3]));
+    surfaces.extend(surface_bytes(2, [3, 9], [3, 3]));
+
+    let bytes = bsp_bytes(&[
+        (EntryOffset::DrawVertices, vertices),
+        (EntryOffset::DrawIndices, words(&[0, 1, 2])),
+        (EntryOffset::Surfaces, surfaces),
+    ]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = q3::Parser::new(&mut cursor).unwrap();
+    let vertices = parser.parse_draw_vertices().unwrap();
+    let indices = parser.parse_draw_indices().unwrap();
+    let surfaces = parser.parse_surfaces().unwrap();
+
+    assert_eq!(vertices.len(), 12);
+    assert_eq!(vertices[4].position, [4.0, 0.0, 0.0]);
+    assert_eq!(vertices[4].tex_coords, [0.5, 1.0]);
+    assert_eq!(vertices[4].lightmap_coords, [0.25, 0.75]);
+    assert_eq!(vertices[4].normal, [0.0, 0.0, 1.0]);
+    assert_eq!(vertices[4].color, [255, 128, 0, 255]);
+
+    assert_eq!(surfaces.len(), 2);
+    assert_eq!(surfaces[0].kind, SurfaceKind::Planar);
+    assert_eq!(surfaces[0].shader, 2);
+    assert_eq!(surfaces[0].fog, -1);
+    assert_eq!(surfaces[0].indices(&indices), Some(&[0, 1, 2][..]));
+    assert_eq!(surfaces[0].lightmap_offset, [16, 32]);
+    assert_eq!(surfaces[0].lightmap_size, [8, 4]);
+
+    let patch = &surfaces[1];
+    assert_eq!(patch.kind, SurfaceKind::Patch);
+    assert_eq!(patch.patch_size, [3, 3]);
+    let control_points = patch.vertices(&vertices).unwrap();
+    assert_eq!(control_points.len(), 9);
+    assert_eq!(control_points[0].position, [3.0, 0.0, 0.0]);
+    assert!(patch.indices(&indices).unwrap().is_empty());
+
+    let mut out_of_range = *patch;
+    out_of_range.vertex_count = 10;
+    assert!(out_of_range.vertices(&vertices).is_none());
+}
+
+#[test]
+fn parse_bad_surface_kind() {
+    let bytes =
+        bsp_bytes(&[(EntryOffset::Surfaces, surface_bytes(7, [0, 0], [0, 0]))]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = q3::Parser::new(&mut cursor).unwrap();
+    let err = parser.parse_surfaces().unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn parse_draw_indices_bad_length() {
+    let bytes = bsp_bytes(&[(EntryOffset::DrawIndices, vec![0; 6])]);
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = q3::Parser::new(&mut cursor).unwrap();
+    let err = parser.parse_draw_indices().unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
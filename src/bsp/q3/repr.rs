@@ -0,0 +1,358 @@
+use crate::bsp::repr::{Layout, Record};
+use crate::bsp::Entry;
+use crate::common::LeReader;
+use crate::{slice_to_cstring, BinParseError, BinParseResult};
+use std::ffi::{CString, IntoStringError};
+use std::mem::size_of;
+use std::string::String;
+
+pub const MAGIC: [u8; 4] = *b"IBSP";
+pub const VERSION: u32 = 46;
+pub const ENTRY_COUNT: usize = 17;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(usize)]
+pub enum EntryOffset {
+    Entities = 0,
+    Shaders,
+    Planes,
+    Nodes,
+    Leaves,
+    LeafSurfaces,
+    LeafBrushes,
+    Models,
+    Brushes,
+    BrushSides,
+    DrawVertices,
+    DrawIndices,
+    Fogs,
+    Surfaces,
+    Lightmaps,
+    LightGrid,
+    Vis,
+}
+
+impl From<EntryOffset> for usize {
+    fn from(offset: EntryOffset) -> Self {
+        offset as usize
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(C, packed)]
+pub struct Head {
+    magic: [u8; 4],
+    version: u32,
+    entries: [Entry; ENTRY_COUNT],
+}
+
+impl Head {
+    pub fn entry(&self, offset: EntryOffset) -> Entry {
+        self.entries[usize::from(offset)]
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+}
+
+impl TryFrom<[u8; size_of::<Head>()]> for Head {
+    type Error = BinParseError;
+
+    fn try_from(bytes: [u8; size_of::<Head>()]) -> BinParseResult<Head> {
+        let mut reader = LeReader::new(&bytes);
+        let magic = reader.bytes();
+
+        if magic != MAGIC {
+            return Err(BinParseError::Parse(format!(
+                "Bad magic number {magic:?}"
+            )));
+        }
+
+        let version = reader.u32();
+
+        if version != VERSION {
+            return Err(BinParseError::Parse(format!(
+                "Unrecognized Quake 3 BSP version {version}"
+            )));
+        }
+
+        let entries = [(); ENTRY_COUNT].map(|_| Entry {
+            offset: reader.u32(),
+            length: reader.u32(),
+        });
+
+        Ok(Head {
+            magic,
+            version,
+            entries,
+        })
+    }
+}
+
+/// Shader referenced by surfaces and brush sides, along with the flags it was
+/// compiled with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shader {
+    /// Shader name (64 bytes, null-terminated)
+    pub name: [u8; 64],
+    pub surface_flags: i32,
+    pub content_flags: i32,
+}
+
+impl Shader {
+    /// Obtain the name as a C string
+    pub fn name_to_cstring(&self) -> CString {
+        slice_to_cstring(&self.name)
+    }
+
+    /// Attempt to interpret the name as UTF-8 encoded string
+    pub fn name_to_string(&self) -> Result<String, IntoStringError> {
+        self.name_to_cstring().into_string()
+    }
+}
+
+impl Record for Shader {
+    fn size(_layout: Layout) -> usize {
+        72
+    }
+
+    fn from_bytes(bytes: &[u8], _layout: Layout) -> BinParseResult<Self> {
+        let mut reader = LeReader::new(bytes);
+
+        Ok(Shader {
+            name: reader.bytes(),
+            surface_flags: reader.i32(),
+            content_flags: reader.i32(),
+        })
+    }
+}
+
+/// Plane as stored in the planes lump.  Unlike Quake and Quake II planes,
+/// there is no axial type.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plane {
+    pub normal: [f32; 3],
+    pub dist: f32,
+}
+
+impl Record for Plane {
+    fn size(_layout: Layout) -> usize {
+        16
+    }
+
+    fn from_bytes(bytes: &[u8], _layout: Layout) -> BinParseResult<Self> {
+        let mut reader = LeReader::new(bytes);
+
+        Ok(Plane {
+            normal: reader.f32s(),
+            dist: reader.f32(),
+        })
+    }
+}
+
+/// Convex volume bounded by a contiguous range of brush sides
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Brush {
+    pub first_side: u32,
+    pub side_count: u32,
+
+    /// Shader providing the brush's contents
+    pub shader: u32,
+}
+
+impl Record for Brush {
+    fn size(_layout: Layout) -> usize {
+        12
+    }
+
+    fn from_bytes(bytes: &[u8], _layout: Layout) -> BinParseResult<Self> {
+        let mut reader = LeReader::new(bytes);
+
+        Ok(Brush {
+            first_side: reader.u32(),
+            side_count: reader.u32(),
+            shader: reader.u32(),
+        })
+    }
+}
+
+/// Bounding plane of a brush, facing outward
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BrushSide {
+    pub plane: u32,
+    pub shader: u32,
+}
+
+impl Record for BrushSide {
+    fn size(_layout: Layout) -> usize {
+        8
+    }
+
+    fn from_bytes(bytes: &[u8], _layout: Layout) -> BinParseResult<Self> {
+        let mut reader = LeReader::new(bytes);
+
+        Ok(BrushSide {
+            plane: reader.u32(),
+            shader: reader.u32(),
+        })
+    }
+}
+
+/// Vertex shared by the surfaces that reference it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DrawVertex {
+    pub position: [f32; 3],
+
+    /// Shader texture coordinates
+    pub tex_coords: [f32; 2],
+
+    /// Coordinates within the surface's lightmap
+    pub lightmap_coords: [f32; 2],
+    pub normal: [f32; 3],
+
+    /// Vertex color (RGBA), used for vertex lighting
+    pub color: [u8; 4],
+}
+
+impl Record for DrawVertex {
+    fn size(_layout: Layout) -> usize {
+        44
+    }
+
+    fn from_bytes(bytes: &[u8], _layout: Layout) -> BinParseResult<Self> {
+        let mut reader = LeReader::new(bytes);
+
+        Ok(DrawVertex {
+            position: reader.f32s(),
+            tex_coords: reader.f32s(),
+            lightmap_coords: reader.f32s(),
+            normal: reader.f32s(),
+            color: reader.bytes(),
+        })
+    }
+}
+
+/// Index into the draw vertices of a surface, relative to its first vertex
+pub type DrawIndex = u32;
+
+/// Geometry type of a surface
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(i32)]
+pub enum SurfaceKind {
+    Bad = 0,
+
+    /// Flat polygon, triangulated with draw indices
+    Planar,
+
+    /// Bezier patch defined by a grid of control points
+    Patch,
+
+    /// Arbitrary triangles, e.g. from models baked into the map
+    TriangleSoup,
+
+    /// Light flare, with no geometry
+    Flare,
+}
+
+impl From<SurfaceKind> for i32 {
+    fn from(kind: SurfaceKind) -> Self {
+        kind as i32
+    }
+}
+
+impl TryFrom<i32> for SurfaceKind {
+    type Error = BinParseError;
+
+    fn try_from(value: i32) -> BinParseResult<SurfaceKind> {
+        match value {
+            0 => Ok(SurfaceKind::Bad),
+            1 => Ok(SurfaceKind::Planar),
+            2 => Ok(SurfaceKind::Patch),
+            3 => Ok(SurfaceKind::TriangleSoup),
+            4 => Ok(SurfaceKind::Flare),
+            _ => Err(BinParseError::Parse(format!(
+                "Unrecognized surface type {value}"
+            ))),
+        }
+    }
+}
+
+/// Drawable surface.  Vertices and indices are contiguous ranges of the draw
+/// vertices and draw indices lumps.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Surface {
+    pub shader: u32,
+
+    /// Fog volume, or -1 if none
+    pub fog: i32,
+    pub kind: SurfaceKind,
+    pub first_vertex: u32,
+    pub vertex_count: u32,
+    pub first_index: u32,
+    pub index_count: u32,
+
+    /// Lightmap image, or a negative value if the surface is not lightmapped
+    pub lightmap: i32,
+
+    /// Position of the surface's lightmap within the lightmap image, in luxels
+    pub lightmap_offset: [u32; 2],
+    pub lightmap_size: [u32; 2],
+    pub lightmap_origin: [f32; 3],
+
+    /// S and T lightmap vectors, followed by the surface normal for planar
+    /// surfaces
+    pub lightmap_vecs: [[f32; 3]; 3],
+
+    /// Width and height of a patch's control point grid, 0 for other kinds
+    pub patch_size: [u32; 2],
+}
+
+impl Surface {
+    /// Vertices of the surface.  For patches these are the control points in
+    /// row-major order, `patch_size[0]` per row.  Returns `None` if the range
+    /// is out of bounds.
+    pub fn vertices<'a>(
+        &self,
+        vertices: &'a [DrawVertex],
+    ) -> Option<&'a [DrawVertex]> {
+        let start = self.first_vertex as usize;
+        vertices.get(start..start.checked_add(self.vertex_count as usize)?)
+    }
+
+    /// Triangle indices of the surface, relative to its first vertex.  Returns
+    /// `None` if the range is out of bounds.
+    pub fn indices<'a>(
+        &self,
+        indices: &'a [DrawIndex],
+    ) -> Option<&'a [DrawIndex]> {
+        let start = self.first_index as usize;
+        indices.get(start..start.checked_add(self.index_count as usize)?)
+    }
+}
+
+impl Record for Surface {
+    fn size(_layout: Layout) -> usize {
+        104
+    }
+
+    fn from_bytes(bytes: &[u8], _layout: Layout) -> BinParseResult<Self> {
+        let mut reader = LeReader::new(bytes);
+
+        Ok(Surface {
+            shader: reader.u32(),
+            fog: reader.i32(),
+            kind: reader.i32().try_into()?,
+            first_vertex: reader.u32(),
+            vertex_count: reader.u32(),
+            first_index: reader.u32(),
+            index_count: reader.u32(),
+            lightmap: reader.i32(),
+            lightmap_offset: [reader.u32(), reader.u32()],
+            lightmap_size: [reader.u32(), reader.u32()],
+            lightmap_origin: reader.f32s(),
+            lightmap_vecs: [reader.f32s(), reader.f32s(), reader.f32s()],
+            patch_size: [reader.u32(), reader.u32()],
+        })
+    }
+}
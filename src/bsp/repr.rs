@@ -1,8 +1,23 @@
+use crate::common::LeReader;
+use crate::{BinParseResult, WriteAttempt, WriteError};
+use std::fmt::Display;
 use std::mem::size_of;
 use std::mem::MaybeUninit;
+use std::vec::Vec;
 
 pub const BSP_VERSION: u32 = 29;
 pub const BSP2_VERSION: u32 = u32::from_le_bytes(*b"BSP2");
+
+/// Earlier BSP2 variant produced by RMQ-era compilers, with 32-bit indices but
+/// 16-bit bounding boxes
+pub const BSP2_RMQ_VERSION: u32 = u32::from_le_bytes(*b"2PSB");
+
+/// Half-Life (GoldSrc) BSP, with BSP29 records but RGB lighting and textures
+/// carrying their own palettes
+pub const HL_BSP_VERSION: u32 = 30;
+
+pub(crate) const VERSIONS: [u32; 4] =
+    [BSP_VERSION, BSP2_VERSION, BSP2_RMQ_VERSION, HL_BSP_VERSION];
 pub const ENTRY_COUNT: usize = 15;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -46,9 +61,28 @@ pub struct Head {
 }
 
 impl Head {
+    pub fn layout(&self) -> Layout {
+        Layout::from_version(self.version)
+    }
+
     pub fn entry(&self, offset: EntryOffset) -> Entry {
-        let idx: usize = offset.into();
-        self.entries[idx]
+        match offset {
+            EntryOffset::Entities if self.is_blue_shift() => self.entries[1],
+            EntryOffset::Planes if self.is_blue_shift() => self.entries[0],
+            _ => self.entries[usize::from(offset)],
+        }
+    }
+
+    /// Whether the BSP is from Half-Life: Blue Shift, which uses version 30
+    /// but swaps the entities and planes entries.  Detected by the first entry
+    /// holding a whole number of planes while the second does not.
+    pub fn is_blue_shift(&self) -> bool {
+        let plane_size = Plane::size(Layout::Bsp29) as u32;
+        let [first, second] = [self.entries[0], self.entries[1]];
+
+        self.version == HL_BSP_VERSION
+            && first.length.is_multiple_of(plane_size)
+            && !second.length.is_multiple_of(plane_size)
     }
 
     pub fn version(&self) -> u32 {
@@ -63,7 +97,7 @@ impl TryFrom<[u8; size_of::<Head>()]> for Head {
         let version =
             u32::from_le_bytes(<[u8; 4]>::try_from(&bytes[..4]).unwrap());
 
-        if version != BSP_VERSION && version != BSP2_VERSION {
+        if !VERSIONS.contains(&version) {
             return Err(crate::BinParseError::Parse(format!(
                 "Unrecognized BSP version {} ({:?})",
                 version,
@@ -90,3 +124,804 @@ impl TryFrom<[u8; size_of::<Head>()]> for Head {
         Ok(Head { version, entries })
     }
 }
+
+/// Record layout used by lumps whose fields are widened in BSP2
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Layout {
+    Bsp29,
+    Bsp2,
+
+    /// 2PSB: BSP2 widths for indices, BSP29 widths for bounding boxes
+    Bsp2Rmq,
+}
+
+impl Layout {
+    pub fn from_version(version: u32) -> Self {
+        match version {
+            BSP2_VERSION => Layout::Bsp2,
+            BSP2_RMQ_VERSION => Layout::Bsp2Rmq,
+            _ => Layout::Bsp29,
+        }
+    }
+}
+
+/// Read an index which is 16 bits wide in BSP29 and 32 bits wide in BSP2
+fn read_index(reader: &mut LeReader, layout: Layout) -> u32 {
+    match layout {
+        Layout::Bsp29 => reader.u16().into(),
+        Layout::Bsp2 | Layout::Bsp2Rmq => reader.u32(),
+    }
+}
+
+/// Read a bounding box coordinate, stored as a 16-bit integer in BSP29 and 2PSB
+/// and as a float in BSP2
+fn read_bound(reader: &mut LeReader, layout: Layout) -> f32 {
+    match layout {
+        Layout::Bsp29 | Layout::Bsp2Rmq => reader.i16().into(),
+        Layout::Bsp2 => reader.f32(),
+    }
+}
+
+/// Read a node child or clip node child, stored as a 16-bit integer in BSP29
+/// and as a 32-bit integer in BSP2
+fn read_child(reader: &mut LeReader, layout: Layout) -> i32 {
+    match layout {
+        Layout::Bsp29 => reader.i16().into(),
+        Layout::Bsp2 | Layout::Bsp2Rmq => reader.i32(),
+    }
+}
+
+fn limit_error(
+    name: &str,
+    value: impl Display,
+    limit: impl Display,
+) -> WriteError {
+    WriteError::Validation(format!("{name} {value} exceeds limit of {limit}"))
+}
+
+/// Write an index which is 16 bits wide in BSP29 and 32 bits wide in BSP2
+fn write_index(
+    bytes: &mut Vec<u8>,
+    value: u32,
+    layout: Layout,
+    name: &str,
+) -> WriteAttempt {
+    match layout {
+        Layout::Bsp29 => {
+            let narrow = u16::try_from(value)
+                .map_err(|_| limit_error(name, value, u16::MAX))?;
+            bytes.extend(narrow.to_le_bytes());
+        }
+        Layout::Bsp2 | Layout::Bsp2Rmq => bytes.extend(value.to_le_bytes()),
+    }
+
+    Ok(())
+}
+
+/// Write a bounding box coordinate, rounding outward with `round` if it must
+/// be stored as an integer
+fn write_bound(
+    bytes: &mut Vec<u8>,
+    value: f32,
+    layout: Layout,
+    round: fn(f32) -> f32,
+    name: &str,
+) -> WriteAttempt {
+    match layout {
+        Layout::Bsp29 | Layout::Bsp2Rmq => {
+            let rounded = round(value);
+
+            if rounded < f32::from(i16::MIN) {
+                return Err(limit_error(name, value, i16::MIN));
+            }
+
+            if rounded > f32::from(i16::MAX) || rounded.is_nan() {
+                return Err(limit_error(name, value, i16::MAX));
+            }
+
+            bytes.extend((rounded as i16).to_le_bytes());
+        }
+        Layout::Bsp2 => bytes.extend(value.to_le_bytes()),
+    }
+
+    Ok(())
+}
+
+/// Write a node child or clip node child, which is 16 bits wide in BSP29 and
+/// 32 bits wide in BSP2
+fn write_child(
+    bytes: &mut Vec<u8>,
+    value: i32,
+    layout: Layout,
+    name: &str,
+) -> WriteAttempt {
+    match layout {
+        Layout::Bsp29 => {
+            let narrow = i16::try_from(value).map_err(|_| {
+                if value < 0 {
+                    limit_error(name, value, i16::MIN)
+                } else {
+                    limit_error(name, value, i16::MAX)
+                }
+            })?;
+            bytes.extend(narrow.to_le_bytes());
+        }
+        Layout::Bsp2 | Layout::Bsp2Rmq => bytes.extend(value.to_le_bytes()),
+    }
+
+    Ok(())
+}
+
+fn write_floats(bytes: &mut Vec<u8>, floats: &[f32]) {
+    floats.iter().for_each(|f| bytes.extend(f.to_le_bytes()));
+}
+
+/// Fixed-size record stored in a BSP lump
+pub(crate) trait Record: Sized {
+    fn size(layout: Layout) -> usize;
+
+    fn from_bytes(bytes: &[u8], layout: Layout) -> BinParseResult<Self>;
+}
+
+/// Record of a lump which `Writer` can encode
+pub(crate) trait EncodeRecord: Record {
+    /// Append the record to `bytes`, failing if a field does not fit within
+    /// the layout
+    fn write_bytes(&self, bytes: &mut Vec<u8>, layout: Layout) -> WriteAttempt;
+}
+
+/// Axial type of a plane, used by the engine as a shortcut for axis-aligned
+/// planes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(i32)]
+pub enum PlaneKind {
+    /// Normal is parallel to the X axis
+    X = 0,
+
+    /// Normal is parallel to the Y axis
+    Y,
+
+    /// Normal is parallel to the Z axis
+    Z,
+
+    /// Normal is closest to the X axis
+    AnyX,
+
+    /// Normal is closest to the Y axis
+    AnyY,
+
+    /// Normal is closest to the Z axis
+    AnyZ,
+}
+
+impl From<PlaneKind> for i32 {
+    fn from(kind: PlaneKind) -> Self {
+        kind as i32
+    }
+}
+
+impl TryFrom<i32> for PlaneKind {
+    type Error = crate::BinParseError;
+
+    fn try_from(value: i32) -> crate::BinParseResult<PlaneKind> {
+        match value {
+            0 => Ok(PlaneKind::X),
+            1 => Ok(PlaneKind::Y),
+            2 => Ok(PlaneKind::Z),
+            3 => Ok(PlaneKind::AnyX),
+            4 => Ok(PlaneKind::AnyY),
+            5 => Ok(PlaneKind::AnyZ),
+            _ => Err(crate::BinParseError::Parse(format!(
+                "Unrecognized plane type {value}"
+            ))),
+        }
+    }
+}
+
+/// Plane as stored in the planes lump.  Points `p` on the plane satisfy
+/// `dot(normal, p) == dist`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plane {
+    pub normal: [f32; 3],
+    pub dist: f32,
+    pub kind: PlaneKind,
+}
+
+impl Record for Plane {
+    fn size(_layout: Layout) -> usize {
+        20
+    }
+
+    fn from_bytes(bytes: &[u8], _layout: Layout) -> BinParseResult<Self> {
+        let mut reader = LeReader::new(bytes);
+        let normal = reader.f32s();
+        let dist = reader.f32();
+        let kind = reader.i32().try_into()?;
+
+        Ok(Plane { normal, dist, kind })
+    }
+}
+
+impl EncodeRecord for Plane {
+    fn write_bytes(
+        &self,
+        bytes: &mut Vec<u8>,
+        _layout: Layout,
+    ) -> WriteAttempt {
+        write_floats(bytes, &self.normal);
+        bytes.extend(self.dist.to_le_bytes());
+        bytes.extend(i32::from(self.kind).to_le_bytes());
+        Ok(())
+    }
+}
+
+/// Vertex position as stored in the vertices lump
+pub type Vertex = [f32; 3];
+
+impl Record for Vertex {
+    fn size(_layout: Layout) -> usize {
+        12
+    }
+
+    fn from_bytes(bytes: &[u8], _layout: Layout) -> BinParseResult<Self> {
+        Ok(LeReader::new(bytes).f32s())
+    }
+}
+
+impl EncodeRecord for Vertex {
+    fn write_bytes(
+        &self,
+        bytes: &mut Vec<u8>,
+        _layout: Layout,
+    ) -> WriteAttempt {
+        write_floats(bytes, self);
+        Ok(())
+    }
+}
+
+/// Pair of indices into the vertices lump.  Stored as 16-bit indices in BSP29
+/// and 32-bit indices in BSP2.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Edge {
+    pub vertices: [u32; 2],
+}
+
+impl Record for Edge {
+    fn size(layout: Layout) -> usize {
+        match layout {
+            Layout::Bsp29 => 4,
+            Layout::Bsp2 | Layout::Bsp2Rmq => 8,
+        }
+    }
+
+    fn from_bytes(bytes: &[u8], layout: Layout) -> BinParseResult<Self> {
+        let mut reader = LeReader::new(bytes);
+        let vertices = [(); 2].map(|_| read_index(&mut reader, layout));
+
+        Ok(Edge { vertices })
+    }
+}
+
+impl EncodeRecord for Edge {
+    fn write_bytes(&self, bytes: &mut Vec<u8>, layout: Layout) -> WriteAttempt {
+        for vertex in self.vertices {
+            write_index(bytes, vertex, layout, "Edge vertex index")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Signed index into the edges lump.  Negative values indicate that the edge
+/// at index `-surf_edge` is traversed from its second vertex to its first.
+pub type SurfEdge = i32;
+
+impl Record for SurfEdge {
+    fn size(_layout: Layout) -> usize {
+        4
+    }
+
+    fn from_bytes(bytes: &[u8], _layout: Layout) -> BinParseResult<Self> {
+        Ok(LeReader::new(bytes).i32())
+    }
+}
+
+impl EncodeRecord for SurfEdge {
+    fn write_bytes(
+        &self,
+        bytes: &mut Vec<u8>,
+        _layout: Layout,
+    ) -> WriteAttempt {
+        bytes.extend(self.to_le_bytes());
+        Ok(())
+    }
+}
+
+/// Face as stored in the faces lump.  Indices are 16 bits wide in BSP29 and
+/// 32 bits wide in BSP2.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Face {
+    /// Index into the planes lump
+    pub plane: u32,
+
+    /// Non-zero if the face lies on the back side of its plane
+    pub side: u32,
+
+    /// Index of the face's first surface edge
+    pub first_edge: u32,
+
+    /// Number of surface edges (and vertices) of the face
+    pub edge_count: u32,
+
+    /// Index into the texture info lump
+    pub tex_info: u32,
+
+    /// Light styles for each lightmap, terminated by 255
+    pub styles: [u8; 4],
+
+    /// Byte offset into the light lump, or -1 if the face is unlit
+    pub light_offset: i32,
+}
+
+impl Face {
+    /// Whether the face lies on the back side of its plane
+    pub fn is_back(&self) -> bool {
+        self.side != 0
+    }
+}
+
+impl Record for Face {
+    fn size(layout: Layout) -> usize {
+        match layout {
+            Layout::Bsp29 => 20,
+            Layout::Bsp2 | Layout::Bsp2Rmq => 28,
+        }
+    }
+
+    fn from_bytes(bytes: &[u8], layout: Layout) -> BinParseResult<Self> {
+        let mut reader = LeReader::new(bytes);
+        let plane = read_index(&mut reader, layout);
+        let side = read_index(&mut reader, layout);
+        let first_edge = reader.u32();
+        let edge_count = read_index(&mut reader, layout);
+        let tex_info = read_index(&mut reader, layout);
+        let styles = reader.bytes();
+        let light_offset = reader.i32();
+
+        Ok(Face {
+            plane,
+            side,
+            first_edge,
+            edge_count,
+            tex_info,
+            styles,
+            light_offset,
+        })
+    }
+}
+
+impl EncodeRecord for Face {
+    fn write_bytes(&self, bytes: &mut Vec<u8>, layout: Layout) -> WriteAttempt {
+        write_index(bytes, self.plane, layout, "Face plane index")?;
+        write_index(bytes, self.side, layout, "Face side")?;
+        bytes.extend(self.first_edge.to_le_bytes());
+        write_index(bytes, self.edge_count, layout, "Face edge count")?;
+        write_index(bytes, self.tex_info, layout, "Face texture info index")?;
+        bytes.extend(self.styles);
+        bytes.extend(self.light_offset.to_le_bytes());
+        Ok(())
+    }
+}
+
+/// Child of a node in the BSP tree
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeChild {
+    /// Index into the nodes lump
+    Node(u32),
+
+    /// Index into the leaves lump
+    Leaf(u32),
+}
+
+impl From<i32> for NodeChild {
+    /// Negative values `n` refer to the leaf at index `-(n + 1)`
+    fn from(value: i32) -> Self {
+        if value < 0 {
+            NodeChild::Leaf(!value as u32)
+        } else {
+            NodeChild::Node(value as u32)
+        }
+    }
+}
+
+impl From<NodeChild> for i32 {
+    fn from(child: NodeChild) -> Self {
+        match child {
+            NodeChild::Node(node) => node as i32,
+            NodeChild::Leaf(leaf) => !(leaf as i32),
+        }
+    }
+}
+
+/// Node as stored in the nodes lump.  In BSP29 children, bounds, and face
+/// indices are stored as 16-bit integers; BSP2 uses 32-bit integers and
+/// floating-point bounds.  2PSB uses 32-bit integers with 16-bit bounds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Node {
+    /// Index into the planes lump
+    pub plane: u32,
+
+    /// Children on the front and back sides of the plane, respectively
+    pub children: [NodeChild; 2],
+
+    pub mins: [f32; 3],
+    pub maxs: [f32; 3],
+
+    /// Index of the first face in the faces lump
+    pub first_face: u32,
+
+    pub face_count: u32,
+}
+
+impl Record for Node {
+    fn size(layout: Layout) -> usize {
+        match layout {
+            Layout::Bsp29 => 24,
+            Layout::Bsp2 => 44,
+            Layout::Bsp2Rmq => 32,
+        }
+    }
+
+    fn from_bytes(bytes: &[u8], layout: Layout) -> BinParseResult<Self> {
+        let mut reader = LeReader::new(bytes);
+        let plane = reader.u32();
+        let children = [(); 2].map(|_| read_child(&mut reader, layout).into());
+        let mins = [(); 3].map(|_| read_bound(&mut reader, layout));
+        let maxs = [(); 3].map(|_| read_bound(&mut reader, layout));
+        let first_face = read_index(&mut reader, layout);
+        let face_count = read_index(&mut reader, layout);
+
+        Ok(Node {
+            plane,
+            children,
+            mins,
+            maxs,
+            first_face,
+            face_count,
+        })
+    }
+}
+
+impl EncodeRecord for Node {
+    fn write_bytes(&self, bytes: &mut Vec<u8>, layout: Layout) -> WriteAttempt {
+        bytes.extend(self.plane.to_le_bytes());
+
+        for child in self.children {
+            match child {
+                // Leaf `n` is stored as `-(n + 1)`, so the limit is reported
+                // in terms of the leaf index rather than the stored value
+                NodeChild::Leaf(leaf)
+                    if layout == Layout::Bsp29 && leaf > i16::MAX as u32 =>
+                {
+                    return Err(limit_error(
+                        "Node child leaf index",
+                        leaf,
+                        i16::MAX,
+                    ));
+                }
+                _ => write_child(bytes, child.into(), layout, "Node child")?,
+            }
+        }
+
+        for min in self.mins {
+            write_bound(bytes, min, layout, f32::floor, "Node bound")?;
+        }
+
+        for max in self.maxs {
+            write_bound(bytes, max, layout, f32::ceil, "Node bound")?;
+        }
+
+        write_index(bytes, self.first_face, layout, "Node first face")?;
+        write_index(bytes, self.face_count, layout, "Node face count")?;
+        Ok(())
+    }
+}
+
+/// Child of a clip node in a collision hull
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClipNodeChild {
+    /// Index into the clip nodes lump
+    Node(u32),
+
+    /// Contents of the space on this side of the plane, see `bsp::contents`
+    Contents(i32),
+}
+
+impl From<ClipNodeChild> for i32 {
+    fn from(child: ClipNodeChild) -> Self {
+        match child {
+            ClipNodeChild::Node(node) => node as i32,
+            ClipNodeChild::Contents(contents) => contents,
+        }
+    }
+}
+
+impl From<i32> for ClipNodeChild {
+    fn from(value: i32) -> Self {
+        if value < 0 {
+            ClipNodeChild::Contents(value)
+        } else {
+            ClipNodeChild::Node(value as u32)
+        }
+    }
+}
+
+/// Clip node as stored in the clip nodes lump.  Children are stored as 16-bit
+/// integers in BSP29 and 32-bit integers in BSP2.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClipNode {
+    /// Index into the planes lump
+    pub plane: u32,
+
+    /// Children on the front and back sides of the plane, respectively
+    pub children: [ClipNodeChild; 2],
+}
+
+impl Record for ClipNode {
+    fn size(layout: Layout) -> usize {
+        match layout {
+            Layout::Bsp29 => 8,
+            Layout::Bsp2 | Layout::Bsp2Rmq => 12,
+        }
+    }
+
+    fn from_bytes(bytes: &[u8], layout: Layout) -> BinParseResult<Self> {
+        let mut reader = LeReader::new(bytes);
+        let plane = reader.u32();
+        let children = [(); 2].map(|_| read_child(&mut reader, layout).into());
+
+        Ok(ClipNode { plane, children })
+    }
+}
+
+impl EncodeRecord for ClipNode {
+    fn write_bytes(&self, bytes: &mut Vec<u8>, layout: Layout) -> WriteAttempt {
+        bytes.extend(self.plane.to_le_bytes());
+
+        for child in self.children {
+            write_child(bytes, child.into(), layout, "Clip node child")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Leaf as stored in the leaves lump.  In BSP29 bounds and mark surface
+/// indices are stored as 16-bit integers; BSP2 uses floating-point bounds and
+/// 32-bit indices.  2PSB uses 32-bit indices with 16-bit bounds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Leaf {
+    /// Contents of the leaf, see `bsp::contents`
+    pub contents: i32,
+
+    /// Byte offset into the visibility lump, or -1 if the leaf has no
+    /// visibility data
+    pub vis_offset: i32,
+
+    pub mins: [f32; 3],
+    pub maxs: [f32; 3],
+
+    /// Index of the first entry in the mark surfaces lump
+    pub first_mark_surface: u32,
+
+    pub mark_surface_count: u32,
+
+    /// Ambient sound volumes for water, sky, slime, and lava
+    pub ambient_levels: [u8; 4],
+}
+
+impl Record for Leaf {
+    fn size(layout: Layout) -> usize {
+        match layout {
+            Layout::Bsp29 => 28,
+            Layout::Bsp2 => 44,
+            Layout::Bsp2Rmq => 32,
+        }
+    }
+
+    fn from_bytes(bytes: &[u8], layout: Layout) -> BinParseResult<Self> {
+        let mut reader = LeReader::new(bytes);
+        let contents = reader.i32();
+        let vis_offset = reader.i32();
+        let mins = [(); 3].map(|_| read_bound(&mut reader, layout));
+        let maxs = [(); 3].map(|_| read_bound(&mut reader, layout));
+        let first_mark_surface = read_index(&mut reader, layout);
+        let mark_surface_count = read_index(&mut reader, layout);
+        let ambient_levels = reader.bytes();
+
+        Ok(Leaf {
+            contents,
+            vis_offset,
+            mins,
+            maxs,
+            first_mark_surface,
+            mark_surface_count,
+            ambient_levels,
+        })
+    }
+}
+
+impl EncodeRecord for Leaf {
+    fn write_bytes(&self, bytes: &mut Vec<u8>, layout: Layout) -> WriteAttempt {
+        bytes.extend(self.contents.to_le_bytes());
+        bytes.extend(self.vis_offset.to_le_bytes());
+
+        for min in self.mins {
+            write_bound(bytes, min, layout, f32::floor, "Leaf bound")?;
+        }
+
+        for max in self.maxs {
+            write_bound(bytes, max, layout, f32::ceil, "Leaf bound")?;
+        }
+
+        write_index(
+            bytes,
+            self.first_mark_surface,
+            layout,
+            "Leaf first mark surface",
+        )?;
+        write_index(
+            bytes,
+            self.mark_surface_count,
+            layout,
+            "Leaf mark surface count",
+        )?;
+        bytes.extend(self.ambient_levels);
+        Ok(())
+    }
+}
+
+/// Index into the faces lump, as stored in the mark surfaces lump.  Stored as
+/// a 16-bit integer in BSP29 and a 32-bit integer in BSP2.
+pub type MarkSurface = u32;
+
+impl Record for MarkSurface {
+    fn size(layout: Layout) -> usize {
+        match layout {
+            Layout::Bsp29 => 2,
+            Layout::Bsp2 | Layout::Bsp2Rmq => 4,
+        }
+    }
+
+    fn from_bytes(bytes: &[u8], layout: Layout) -> BinParseResult<Self> {
+        Ok(read_index(&mut LeReader::new(bytes), layout))
+    }
+}
+
+impl EncodeRecord for MarkSurface {
+    fn write_bytes(&self, bytes: &mut Vec<u8>, layout: Layout) -> WriteAttempt {
+        write_index(bytes, *self, layout, "Mark surface face index")
+    }
+}
+
+/// Submodel as stored in the models lump.  Model 0 is the world; brush
+/// entities refer to other models with keys such as `"model" "*1"`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Model {
+    pub mins: [f32; 3],
+    pub maxs: [f32; 3],
+    pub origin: [f32; 3],
+
+    /// Root of each hull.  Hull 0 (point) indexes the nodes lump, hulls 1
+    /// (player) through 3 index the clip nodes lump.
+    pub head_nodes: [i32; 4],
+
+    /// Number of leaves, not including leaf 0, with visibility data
+    pub vis_leaf_count: u32,
+
+    /// Index of the first face in the faces lump
+    pub first_face: u32,
+
+    pub face_count: u32,
+}
+
+impl Record for Model {
+    fn size(_layout: Layout) -> usize {
+        64
+    }
+
+    fn from_bytes(bytes: &[u8], _layout: Layout) -> BinParseResult<Self> {
+        let mut reader = LeReader::new(bytes);
+        let mins = reader.f32s();
+        let maxs = reader.f32s();
+        let origin = reader.f32s();
+        let head_nodes = [(); 4].map(|_| reader.i32());
+        let vis_leaf_count = reader.u32();
+        let first_face = reader.u32();
+        let face_count = reader.u32();
+
+        Ok(Model {
+            mins,
+            maxs,
+            origin,
+            head_nodes,
+            vis_leaf_count,
+            first_face,
+            face_count,
+        })
+    }
+}
+
+impl EncodeRecord for Model {
+    fn write_bytes(
+        &self,
+        bytes: &mut Vec<u8>,
+        _layout: Layout,
+    ) -> WriteAttempt {
+        write_floats(bytes, &self.mins);
+        write_floats(bytes, &self.maxs);
+        write_floats(bytes, &self.origin);
+        self.head_nodes
+            .iter()
+            .for_each(|node| bytes.extend(node.to_le_bytes()));
+        bytes.extend(self.vis_leaf_count.to_le_bytes());
+        bytes.extend(self.first_face.to_le_bytes());
+        bytes.extend(self.face_count.to_le_bytes());
+        Ok(())
+    }
+}
+
+/// Texture projection as stored in the texture info lump
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TexInfo {
+    /// S and T axes, each with an offset as the 4th component
+    pub vecs: [[f32; 4]; 2],
+
+    /// Index of the texture within the textures lump
+    pub mip_texture: u32,
+
+    /// Flags, see `bsp::tex_flags`
+    pub flags: u32,
+}
+
+impl TexInfo {
+    /// Project a point onto the S/T axes to obtain texture coordinates in
+    /// texels
+    pub fn tex_coords(&self, point: [f32; 3]) -> [f32; 2] {
+        self.vecs.map(|vec| {
+            point[0] * vec[0] + point[1] * vec[1] + point[2] * vec[2] + vec[3]
+        })
+    }
+}
+
+impl Record for TexInfo {
+    fn size(_layout: Layout) -> usize {
+        40
+    }
+
+    fn from_bytes(bytes: &[u8], _layout: Layout) -> BinParseResult<Self> {
+        let mut reader = LeReader::new(bytes);
+        let vecs = [(); 2].map(|_| reader.f32s());
+        let mip_texture = reader.u32();
+        let flags = reader.u32();
+
+        Ok(TexInfo {
+            vecs,
+            mip_texture,
+            flags,
+        })
+    }
+}
+
+impl EncodeRecord for TexInfo {
+    fn write_bytes(
+        &self,
+        bytes: &mut Vec<u8>,
+        _layout: Layout,
+    ) -> WriteAttempt {
+        self.vecs.iter().for_each(|vec| write_floats(bytes, vec));
+        bytes.extend(self.mip_texture.to_le_bytes());
+        bytes.extend(self.flags.to_le_bytes());
+        Ok(())
+    }
+}
@@ -1,5 +1,9 @@
 use crate::{bsp, BinParseError};
-use bsp::{Entry, EntryOffset, BSP2_VERSION, BSP_VERSION};
+use bsp::repr::Layout;
+use bsp::{
+    ClipNodeChild, Edge, Entry, EntryOffset, NodeChild, PlaneKind, Record,
+    BSP2_RMQ_VERSION, BSP2_VERSION, BSP_VERSION, HL_BSP_VERSION,
+};
 use std::mem::size_of;
 
 #[test]
@@ -33,6 +37,16 @@ fn bsp2_head_from_bytes() {
     assert_eq!(head.version(), BSP2_VERSION);
 }
 
+#[test]
+fn bsp2_rmq_head_from_bytes() {
+    let mut bytes = [0u8; size_of::<bsp::Head>()];
+    bytes[..4].copy_from_slice(b"2PSB");
+
+    let head: bsp::Head = bytes.try_into().unwrap();
+    assert_eq!(head.version(), BSP2_RMQ_VERSION);
+    assert_eq!(head.layout(), Layout::Bsp2Rmq);
+}
+
 #[test]
 fn bsp29_head_from_bytes() {
     let mut bytes = [0u8; size_of::<bsp::Head>()];
@@ -73,6 +87,76 @@ fn get_bsp_entries() {
     );
 }
 
+fn head_bytes(version: u32, lengths: [u32; 2]) -> [u8; size_of::<bsp::Head>()] {
+    let mut bytes = [0u8; size_of::<bsp::Head>()];
+    bytes[..4].copy_from_slice(&version.to_le_bytes());
+    bytes[4..8].copy_from_slice(&(300u32).to_le_bytes());
+    bytes[8..12].copy_from_slice(&lengths[0].to_le_bytes());
+    bytes[12..16].copy_from_slice(&(400u32).to_le_bytes());
+    bytes[16..20].copy_from_slice(&lengths[1].to_le_bytes());
+    bytes
+}
+
+#[test]
+fn get_hl_bsp_entries() {
+    let head: bsp::Head = head_bytes(30, [37, 40]).try_into().unwrap();
+
+    assert_eq!(head.version(), HL_BSP_VERSION);
+    assert_eq!(head.layout(), Layout::Bsp29);
+    assert!(!head.is_blue_shift());
+    assert_eq!(
+        head.entry(EntryOffset::Entities),
+        Entry {
+            offset: 300,
+            length: 37
+        }
+    );
+    assert_eq!(
+        head.entry(EntryOffset::Planes),
+        Entry {
+            offset: 400,
+            length: 40
+        }
+    );
+}
+
+#[test]
+fn get_blue_shift_entries() {
+    let head: bsp::Head = head_bytes(30, [40, 37]).try_into().unwrap();
+
+    assert!(head.is_blue_shift());
+    assert_eq!(
+        head.entry(EntryOffset::Planes),
+        Entry {
+            offset: 300,
+            length: 40
+        }
+    );
+    assert_eq!(
+        head.entry(EntryOffset::Entities),
+        Entry {
+            offset: 400,
+            length: 37
+        }
+    );
+    assert_eq!(
+        head.entry(EntryOffset::Textures),
+        Entry {
+            offset: 0,
+            length: 0
+        }
+    );
+
+    // Only version 30 files can be Blue Shift
+    for version in [BSP_VERSION, BSP2_VERSION, BSP2_RMQ_VERSION] {
+        let head: bsp::Head = head_bytes(version, [40, 37]).try_into().unwrap();
+
+        assert!(!head.is_blue_shift());
+        let offset = head.entry(EntryOffset::Entities).offset;
+        assert_eq!(offset, 300);
+    }
+}
+
 #[test]
 fn bad_version_head() {
     let mut bytes = [0u8; size_of::<bsp::Head>()];
@@ -81,3 +165,74 @@ fn bad_version_head() {
     let err = bsp::Head::try_from(bytes).unwrap_err();
     assert!(matches!(err, BinParseError::Parse(_)));
 }
+
+#[test]
+fn plane_kind_from_integer() {
+    assert_eq!(PlaneKind::try_from(0).unwrap(), PlaneKind::X);
+    assert_eq!(PlaneKind::try_from(5).unwrap(), PlaneKind::AnyZ);
+    assert_eq!(i32::from(PlaneKind::AnyX), 3);
+
+    let err = PlaneKind::try_from(-1).unwrap_err();
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn plane_from_bytes() {
+    let mut bytes = [0u8; 20];
+    bytes[0..4].copy_from_slice(&(1.0f32).to_le_bytes());
+    bytes[12..16].copy_from_slice(&(-32.0f32).to_le_bytes());
+    bytes[16..20].copy_from_slice(&(3i32).to_le_bytes());
+
+    let plane = bsp::Plane::from_bytes(&bytes, Layout::Bsp29).unwrap();
+    assert_eq!(plane.normal, [1.0, 0.0, 0.0]);
+    assert_eq!(plane.dist, -32.0);
+    assert_eq!(plane.kind, PlaneKind::AnyX);
+}
+
+#[test]
+fn layout_from_version() {
+    assert_eq!(Layout::from_version(BSP_VERSION), Layout::Bsp29);
+    assert_eq!(Layout::from_version(BSP2_VERSION), Layout::Bsp2);
+    assert_eq!(Layout::from_version(BSP2_RMQ_VERSION), Layout::Bsp2Rmq);
+}
+
+#[test]
+fn edge_from_bytes() {
+    let bsp29_bytes = [0x01, 0x02, 0xff, 0xff];
+    let edge = Edge::from_bytes(&bsp29_bytes, Layout::Bsp29).unwrap();
+    assert_eq!(edge.vertices, [0x0201, 0xffff]);
+    assert_eq!(Edge::size(Layout::Bsp29), bsp29_bytes.len());
+
+    let bsp2_bytes = [0x01, 0x02, 0x03, 0x04, 0xff, 0xff, 0x01, 0x00];
+    let edge = Edge::from_bytes(&bsp2_bytes, Layout::Bsp2).unwrap();
+    assert_eq!(edge.vertices, [0x04030201, 0x0001ffff]);
+    assert_eq!(Edge::size(Layout::Bsp2), bsp2_bytes.len());
+}
+
+#[test]
+fn node_child_from_integer() {
+    assert_eq!(NodeChild::from(0), NodeChild::Node(0));
+    assert_eq!(NodeChild::from(12), NodeChild::Node(12));
+    assert_eq!(NodeChild::from(-1), NodeChild::Leaf(0));
+    assert_eq!(NodeChild::from(-13), NodeChild::Leaf(12));
+}
+
+#[test]
+fn clip_node_child_from_integer() {
+    assert_eq!(ClipNodeChild::from(7), ClipNodeChild::Node(7));
+    assert_eq!(
+        ClipNodeChild::from(bsp::contents::SOLID),
+        ClipNodeChild::Contents(bsp::contents::SOLID)
+    );
+}
+
+#[test]
+fn tex_info_tex_coords() {
+    let tex_info = bsp::TexInfo {
+        vecs: [[0.5, 0.0, 0.0, 4.0], [0.0, 0.0, -1.0, -2.0]],
+        mip_texture: 0,
+        flags: bsp::tex_flags::SPECIAL,
+    };
+
+    assert_eq!(tex_info.tex_coords([8.0, 100.0, 3.0]), [8.0, -5.0]);
+}
@@ -0,0 +1,66 @@
+use super::{Leaf, Model};
+use std::vec::Vec;
+
+/// Leaf without bounds, surfaces or visibility
+pub(super) fn leaf(contents: i32) -> Leaf {
+    Leaf {
+        contents,
+        vis_offset: -1,
+        mins: [0.0; 3],
+        maxs: [0.0; 3],
+        first_mark_surface: 0,
+        mark_surface_count: 0,
+        ambient_levels: [0; 4],
+    }
+}
+
+/// Model without bounds, with every hull starting at node 0
+pub(super) fn model(first_face: u32, face_count: u32) -> Model {
+    Model {
+        mins: [0.0; 3],
+        maxs: [0.0; 3],
+        origin: [0.0; 3],
+        head_nodes: [0, 0, 0, 0],
+        vis_leaf_count: 0,
+        first_face,
+        face_count,
+    }
+}
+
+/// Texture lump with one mip texture filled with `fill`.  Each slot either
+/// points to the texture or is missing (-1).
+pub(super) fn textures_lump(
+    name: &str,
+    size: [u32; 2],
+    fill: u8,
+    slots: &[bool],
+) -> Vec<u8> {
+    let texture_offset = 4 * (slots.len() as i32 + 1);
+    let mut bytes = Vec::new();
+    bytes.extend((slots.len() as i32).to_le_bytes());
+
+    for &present in slots {
+        let offset = if present { texture_offset } else { -1 };
+        bytes.extend(offset.to_le_bytes());
+    }
+
+    let mut name_bytes = [0u8; 16];
+    name_bytes[..name.len()].copy_from_slice(name.as_bytes());
+    bytes.extend(name_bytes);
+    bytes.extend(size[0].to_le_bytes());
+    bytes.extend(size[1].to_le_bytes());
+
+    let mip_sizes = [1, 4, 16, 64].map(|scale| size[0] * size[1] / scale);
+    let mut offset = 40u32;
+
+    for mip_sz in mip_sizes {
+        bytes.extend(offset.to_le_bytes());
+        offset += mip_sz;
+    }
+
+    for mip_sz in mip_sizes {
+        bytes.extend(vec![fill; mip_sz as usize]);
+    }
+
+    bytes
+}
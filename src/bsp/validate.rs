@@ -0,0 +1,574 @@
+use super::parser::{check_mip_texture, decode_records, mip_texture_offsets};
+use super::repr::{Layout, ENTRY_COUNT};
+use super::writer::LUMP_ORDER;
+use super::{
+    Bsp, ClipNode, ClipNodeChild, Edge, Entry, EntryOffset, Face, Head, Leaf,
+    MarkSurface, Model, Node, NodeChild, Parser, Plane, Record, SurfEdge,
+    TexInfo, Vertex,
+};
+use crate::BinParseResult;
+use std::fmt;
+use std::io::{Read, Seek};
+use std::mem::size_of;
+use std::string::String;
+use std::vec::Vec;
+
+/// Structural problem found by `Parser::validate`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationIssue {
+    /// Lump overlaps the header or extends past the end of the file
+    LumpOutOfBounds {
+        lump: EntryOffset,
+        offset: u32,
+        length: u32,
+        file_length: u64,
+    },
+
+    /// Two lumps share some of the same bytes
+    LumpsOverlap { lumps: [EntryOffset; 2] },
+
+    /// Lump length is not a multiple of the size of its records
+    BadLumpLength {
+        lump: EntryOffset,
+        length: u32,
+        record_size: usize,
+    },
+
+    /// Lump could not be decoded, e.g. because of an unrecognized plane type
+    BadLump { lump: EntryOffset, message: String },
+
+    /// Record refers to an element past the end of another lump.  `index` is
+    /// a record index, or a byte offset for the light and visibility lumps.
+    IndexOutOfRange {
+        lump: EntryOffset,
+        record: usize,
+        target: EntryOffset,
+        index: i64,
+        count: usize,
+    },
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::LumpOutOfBounds {
+                lump,
+                offset,
+                length,
+                file_length,
+            } => write!(
+                f,
+                "{lump:?} lump at offset {offset} with length {length} is \
+                 outside of the {file_length}-byte file",
+            ),
+            Self::LumpsOverlap {
+                lumps: [first, second],
+            } => {
+                write!(f, "{first:?} lump overlaps {second:?} lump")
+            }
+            Self::BadLumpLength {
+                lump,
+                length,
+                record_size,
+            } => write!(
+                f,
+                "{lump:?} lump length {length} is not a multiple of \
+                 {record_size}",
+            ),
+            Self::BadLump { lump, message } => {
+                write!(f, "{lump:?} lump is malformed: {message}")
+            }
+            Self::IndexOutOfRange {
+                lump,
+                record,
+                target,
+                index,
+                count,
+            } => write!(
+                f,
+                "{lump:?} record {record} refers to {target:?} index {index} \
+                 but there are only {count}",
+            ),
+        }
+    }
+}
+
+/// Result of validating a BSP.  The BSP is well-formed if no issues were found.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl<Reader: Seek + Read> Parser<'_, Reader> {
+    /// Checks the structure of the BSP without trusting any of its contents:
+    /// that lumps lie within the file and do not overlap, that record lumps
+    /// have a whole number of records, that embedded textures and lightmaps
+    /// lie within their lumps, and that indices into other lumps are in range.  Lumps which fail the earlier checks are not read or decoded,
+    /// so the indices referring to them are not checked.  BSPX lumps are not
+    /// validated.  Only errors reading the stream itself are returned as
+    /// `Err`.
+    pub fn validate(&mut self) -> BinParseResult<ValidationReport> {
+        let file_length = self.stream_length()?;
+        let head = *self.header();
+        let mut validator = Validator {
+            version: head.version(),
+            layout: head.layout(),
+            issues: Vec::new(),
+        };
+
+        let in_bounds = validator.check_bounds(&head, file_length);
+        let mut lumps: [Option<Vec<u8>>; ENTRY_COUNT] = Default::default();
+
+        for entry_offset in in_bounds {
+            lumps[usize::from(entry_offset)] =
+                Some(self.read_lump(entry_offset)?);
+        }
+
+        validator.check_records(&lumps);
+
+        Ok(ValidationReport {
+            issues: validator.issues,
+        })
+    }
+}
+
+// Lumps which could not be read or decoded have an unknown count rather than
+// being treated as empty
+fn count_of<T>(records: &Option<Vec<T>>) -> Option<usize> {
+    records.as_ref().map(Vec::len)
+}
+
+struct Validator {
+    version: u32,
+    layout: Layout,
+    issues: Vec<ValidationIssue>,
+}
+
+impl Validator {
+    // Reports lumps out of bounds or overlapping, returning the lumps which
+    // are safe to read
+    fn check_bounds(
+        &mut self,
+        head: &Head,
+        file_length: u64,
+    ) -> Vec<EntryOffset> {
+        let head_length = size_of::<Head>() as u64;
+        let mut spans = Vec::new();
+
+        for entry_offset in LUMP_ORDER {
+            let Entry { offset, length } = head.entry(entry_offset);
+            let start = u64::from(offset);
+            let end = start + u64::from(length);
+
+            if length == 0 {
+                continue;
+            }
+
+            if start < head_length || end > file_length {
+                self.issues.push(ValidationIssue::LumpOutOfBounds {
+                    lump: entry_offset,
+                    offset,
+                    length,
+                    file_length,
+                });
+            } else {
+                spans.push((start, end, entry_offset));
+            }
+        }
+
+        spans.sort_by_key(|&(start, end, _)| (start, end));
+        let mut furthest: Option<(u64, EntryOffset)> = None;
+
+        for &(start, end, entry_offset) in &spans {
+            match furthest {
+                Some((furthest_end, other)) if start < furthest_end => {
+                    self.issues.push(ValidationIssue::LumpsOverlap {
+                        lumps: [other, entry_offset],
+                    });
+
+                    if end > furthest_end {
+                        furthest = Some((end, entry_offset));
+                    }
+                }
+                _ => furthest = Some((end, entry_offset)),
+            }
+        }
+
+        spans
+            .into_iter()
+            .map(|(_, _, entry_offset)| entry_offset)
+            .collect()
+    }
+
+    fn check_records(&mut self, lumps: &[Option<Vec<u8>>; ENTRY_COUNT]) {
+        let bytes = |entry_offset: EntryOffset| {
+            lumps[usize::from(entry_offset)].as_deref()
+        };
+
+        let planes: Option<Vec<Plane>> =
+            self.decode(EntryOffset::Planes, bytes(EntryOffset::Planes));
+        let vertices: Option<Vec<Vertex>> =
+            self.decode(EntryOffset::Vertices, bytes(EntryOffset::Vertices));
+        let nodes: Option<Vec<Node>> =
+            self.decode(EntryOffset::Nodes, bytes(EntryOffset::Nodes));
+        let tex_info: Option<Vec<TexInfo>> =
+            self.decode(EntryOffset::TexInfo, bytes(EntryOffset::TexInfo));
+        let faces: Option<Vec<Face>> =
+            self.decode(EntryOffset::Faces, bytes(EntryOffset::Faces));
+        let clip_nodes: Option<Vec<ClipNode>> =
+            self.decode(EntryOffset::ClipNodes, bytes(EntryOffset::ClipNodes));
+        let leaves: Option<Vec<Leaf>> =
+            self.decode(EntryOffset::Leaves, bytes(EntryOffset::Leaves));
+        let mark_surfaces: Option<Vec<MarkSurface>> = self.decode(
+            EntryOffset::MarkSurfaces,
+            bytes(EntryOffset::MarkSurfaces),
+        );
+        let edges: Option<Vec<Edge>> =
+            self.decode(EntryOffset::Edges, bytes(EntryOffset::Edges));
+        let surf_edges: Option<Vec<SurfEdge>> =
+            self.decode(EntryOffset::SurfEdges, bytes(EntryOffset::SurfEdges));
+        let models: Option<Vec<Model>> =
+            self.decode(EntryOffset::Models, bytes(EntryOffset::Models));
+        let texture_count = self.texture_count(bytes(EntryOffset::Textures));
+
+        let byte_count = |entry_offset| bytes(entry_offset).map(<[u8]>::len);
+
+        let plane_count = count_of(&planes);
+        let node_count = count_of(&nodes);
+        let face_count = count_of(&faces);
+        let clip_node_count = count_of(&clip_nodes);
+        let leaf_count = count_of(&leaves);
+        let light_length = byte_count(EntryOffset::Light);
+        let vis_length = byte_count(EntryOffset::Vis);
+
+        for (idx, edge) in edges.iter().flatten().enumerate() {
+            for vertex in edge.vertices {
+                self.check_index(
+                    (EntryOffset::Edges, idx),
+                    EntryOffset::Vertices,
+                    vertex.into(),
+                    count_of(&vertices),
+                );
+            }
+        }
+
+        for (idx, &surf_edge) in surf_edges.iter().flatten().enumerate() {
+            self.check_index(
+                (EntryOffset::SurfEdges, idx),
+                EntryOffset::Edges,
+                surf_edge.unsigned_abs().into(),
+                count_of(&edges),
+            );
+        }
+
+        for (idx, info) in tex_info.iter().flatten().enumerate() {
+            self.check_index(
+                (EntryOffset::TexInfo, idx),
+                EntryOffset::Textures,
+                info.mip_texture.into(),
+                texture_count,
+            );
+        }
+
+        for (idx, face) in faces.iter().flatten().enumerate() {
+            let record = (EntryOffset::Faces, idx);
+            let plane = face.plane.into();
+            self.check_index(record, EntryOffset::Planes, plane, plane_count);
+            self.check_range(
+                record,
+                EntryOffset::SurfEdges,
+                (face.first_edge, face.edge_count),
+                count_of(&surf_edges),
+            );
+            self.check_index(
+                record,
+                EntryOffset::TexInfo,
+                face.tex_info.into(),
+                count_of(&tex_info),
+            );
+
+            if face.light_offset >= 0 {
+                self.check_index(
+                    record,
+                    EntryOffset::Light,
+                    face.light_offset.into(),
+                    light_length,
+                );
+            }
+        }
+
+        if let (Some(faces), Some(light_length)) = (&faces, light_length) {
+            self.check_lightmaps(
+                Bsp {
+                    version: self.version,
+                    vertices: vertices.unwrap_or_default(),
+                    tex_info: tex_info.unwrap_or_default(),
+                    edges: edges.unwrap_or_default(),
+                    surf_edges: surf_edges.unwrap_or_default(),
+                    ..Default::default()
+                },
+                faces,
+                light_length,
+            );
+        }
+
+        for (idx, node) in nodes.iter().flatten().enumerate() {
+            let record = (EntryOffset::Nodes, idx);
+            let plane = node.plane.into();
+            self.check_index(record, EntryOffset::Planes, plane, plane_count);
+
+            for child in node.children {
+                self.check_node_child(record, child, node_count, leaf_count);
+            }
+
+            self.check_range(
+                record,
+                EntryOffset::Faces,
+                (node.first_face, node.face_count),
+                face_count,
+            );
+        }
+
+        for (idx, clip_node) in clip_nodes.iter().flatten().enumerate() {
+            let record = (EntryOffset::ClipNodes, idx);
+            let plane = clip_node.plane.into();
+            self.check_index(record, EntryOffset::Planes, plane, plane_count);
+
+            for child in clip_node.children {
+                if let ClipNodeChild::Node(child) = child {
+                    self.check_index(
+                        record,
+                        EntryOffset::ClipNodes,
+                        child.into(),
+                        clip_node_count,
+                    );
+                }
+            }
+        }
+
+        for (idx, leaf) in leaves.iter().flatten().enumerate() {
+            let record = (EntryOffset::Leaves, idx);
+            self.check_range(
+                record,
+                EntryOffset::MarkSurfaces,
+                (leaf.first_mark_surface, leaf.mark_surface_count),
+                count_of(&mark_surfaces),
+            );
+
+            if leaf.vis_offset >= 0 {
+                self.check_index(
+                    record,
+                    EntryOffset::Vis,
+                    leaf.vis_offset.into(),
+                    vis_length,
+                );
+            }
+        }
+
+        for (idx, &face) in mark_surfaces.iter().flatten().enumerate() {
+            self.check_index(
+                (EntryOffset::MarkSurfaces, idx),
+                EntryOffset::Faces,
+                face.into(),
+                face_count,
+            );
+        }
+
+        for (idx, model) in models.iter().flatten().enumerate() {
+            let record = (EntryOffset::Models, idx);
+            let [head_node, clip_head_nodes @ ..] = model.head_nodes;
+            self.check_node_child(
+                record,
+                head_node.into(),
+                node_count,
+                leaf_count,
+            );
+
+            for head_node in clip_head_nodes {
+                if let ClipNodeChild::Node(head_node) = head_node.into() {
+                    self.check_index(
+                        record,
+                        EntryOffset::ClipNodes,
+                        head_node.into(),
+                        clip_node_count,
+                    );
+                }
+            }
+
+            self.check_range(
+                record,
+                EntryOffset::Faces,
+                (model.first_face, model.face_count),
+                face_count,
+            );
+        }
+    }
+
+    fn decode<R: Record>(
+        &mut self,
+        lump: EntryOffset,
+        bytes: Option<&[u8]>,
+    ) -> Option<Vec<R>> {
+        let bytes = bytes?;
+        let record_size = R::size(self.layout);
+
+        if !bytes.len().is_multiple_of(record_size) {
+            self.issues.push(ValidationIssue::BadLumpLength {
+                lump,
+                length: bytes.len() as u32,
+                record_size,
+            });
+
+            return None;
+        }
+
+        decode_records(bytes, self.layout, lump)
+            .map_err(|err| self.bad_lump(lump, err))
+            .ok()
+    }
+
+    // Checks each embedded texture, returning the number of textures
+    fn texture_count(&mut self, bytes: Option<&[u8]>) -> Option<usize> {
+        let bytes = bytes?;
+        let offsets = mip_texture_offsets(bytes, self.version)
+            .map_err(|err| self.bad_lump(EntryOffset::Textures, err))
+            .ok()?;
+
+        for &offset in offsets.iter().flatten() {
+            if let Err(err) = check_mip_texture(bytes, offset) {
+                self.bad_lump(EntryOffset::Textures, err);
+            }
+        }
+
+        Some(offsets.len())
+    }
+
+    // Checks that the lightmaps of each face, in every style, end within the
+    // light lump.  Faces whose extents can't be computed because of issues
+    // already reported are skipped.
+    fn check_lightmaps(
+        &mut self,
+        bsp: Bsp,
+        faces: &[Face],
+        light_length: usize,
+    ) {
+        let reported = !self.issues.is_empty();
+
+        for (idx, face) in faces.iter().enumerate() {
+            let Ok(light_offset) = usize::try_from(face.light_offset) else {
+                continue;
+            };
+
+            if light_offset >= light_length {
+                continue;
+            }
+
+            let extents = match bsp.lightmap_extents(face) {
+                Ok(Some(extents)) => extents,
+                Ok(None) => continue,
+                Err(_) if reported => continue,
+                Err(err) => {
+                    self.bad_lump(EntryOffset::Faces, err);
+                    continue;
+                }
+            };
+
+            let style_count = face
+                .styles
+                .iter()
+                .take_while(|&&style| style != 255)
+                .count();
+            let length = (extents.luxel_count() as u64)
+                * (bsp.light_channels() * style_count) as u64;
+
+            self.check_range(
+                (EntryOffset::Faces, idx),
+                EntryOffset::Light,
+                (light_offset as u64, length),
+                Some(light_length),
+            );
+        }
+    }
+
+    fn bad_lump(&mut self, lump: EntryOffset, err: crate::BinParseError) {
+        let message = match err {
+            crate::BinParseError::Parse(message) => message,
+            err => format!("{err}"),
+        };
+
+        self.issues.push(ValidationIssue::BadLump { lump, message });
+    }
+
+    // Checks an index into a lump with `count` elements, if the lump was read
+    fn check_index(
+        &mut self,
+        (lump, record): (EntryOffset, usize),
+        target: EntryOffset,
+        index: i64,
+        count: Option<usize>,
+    ) {
+        let Some(count) = count else {
+            return;
+        };
+
+        if index < 0 || index >= count as i64 {
+            self.issues.push(ValidationIssue::IndexOutOfRange {
+                lump,
+                record,
+                target,
+                index,
+                count,
+            });
+        }
+    }
+
+    // Checks that the last element of a (first, count) range is in range
+    fn check_range(
+        &mut self,
+        record: (EntryOffset, usize),
+        target: EntryOffset,
+        (first, range_count): (impl Into<u64>, impl Into<u64>),
+        count: Option<usize>,
+    ) {
+        let (first, range_count) = (first.into(), range_count.into());
+
+        if range_count > 0 {
+            let last = first.saturating_add(range_count - 1);
+            let last = i64::try_from(last).unwrap_or(i64::MAX);
+            self.check_index(record, target, last, count);
+        }
+    }
+
+    fn check_node_child(
+        &mut self,
+        record: (EntryOffset, usize),
+        child: NodeChild,
+        node_count: Option<usize>,
+        leaf_count: Option<usize>,
+    ) {
+        match child {
+            NodeChild::Node(node) => {
+                self.check_index(
+                    record,
+                    EntryOffset::Nodes,
+                    node.into(),
+                    node_count,
+                );
+            }
+            NodeChild::Leaf(leaf) => {
+                self.check_index(
+                    record,
+                    EntryOffset::Leaves,
+                    leaf.into(),
+                    leaf_count,
+                );
+            }
+        }
+    }
+}
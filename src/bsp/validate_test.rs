@@ -0,0 +1,338 @@
+use crate::bsp;
+use bsp::{
+    contents, Bsp, ClipNode, ClipNodeChild, Edge, EntryOffset, Face, Leaf,
+    Model, Node, NodeChild, Plane, PlaneKind, TexInfo, ValidationIssue,
+    ValidationReport, Writer, BSP2_RMQ_VERSION, BSP2_VERSION, BSP_VERSION,
+    HL_BSP_VERSION,
+};
+use std::io::Cursor;
+use std::vec::Vec;
+
+fn valid_bsp(version: u32) -> Bsp {
+    Bsp {
+        version,
+        entities: b"{\n\"classname\" \"worldspawn\"\n}\n\0".to_vec(),
+        planes: vec![Plane {
+            normal: [0.0, 0.0, 1.0],
+            dist: 0.0,
+            kind: PlaneKind::Z,
+        }],
+        textures: [1i32, -1].iter().flat_map(|i| i.to_le_bytes()).collect(),
+        vertices: vec![[0.0, 0.0, 0.0], [64.0, 0.0, 0.0], [0.0, 64.0, 0.0]],
+        visibility: vec![0x01, 0x00, 0x02],
+        nodes: vec![Node {
+            plane: 0,
+            children: [NodeChild::Leaf(1), NodeChild::Leaf(0)],
+            mins: [-64.0, -64.0, -64.0],
+            maxs: [64.0, 64.0, 64.0],
+            first_face: 0,
+            face_count: 1,
+        }],
+        tex_info: vec![TexInfo {
+            vecs: [[1.0, 0.0, 0.0, 0.0], [0.0, -1.0, 0.0, 0.0]],
+            mip_texture: 0,
+            flags: 0,
+        }],
+        faces: vec![Face {
+            plane: 0,
+            side: 0,
+            first_edge: 0,
+            edge_count: 3,
+            tex_info: 0,
+            styles: [0, 255, 255, 255],
+            light_offset: 0,
+        }],
+        // 5 x 5 luxels, in RGB for Half-Life
+        lighting: vec![200; if version == HL_BSP_VERSION { 75 } else { 25 }],
+        clip_nodes: vec![ClipNode {
+            plane: 0,
+            children: [
+                ClipNodeChild::Contents(contents::EMPTY),
+                ClipNodeChild::Contents(contents::SOLID),
+            ],
+        }],
+        leaves: vec![
+            Leaf {
+                contents: contents::SOLID,
+                vis_offset: -1,
+                mins: [0.0; 3],
+                maxs: [0.0; 3],
+                first_mark_surface: 0,
+                mark_surface_count: 0,
+                ambient_levels: [0; 4],
+            },
+            Leaf {
+                contents: contents::EMPTY,
+                vis_offset: 0,
+                mins: [-64.0, -64.0, 0.0],
+                maxs: [64.0, 64.0, 64.0],
+                first_mark_surface: 0,
+                mark_surface_count: 1,
+                ambient_levels: [0; 4],
+            },
+        ],
+        mark_surfaces: vec![0],
+        edges: vec![
+            Edge { vertices: [0, 0] },
+            Edge { vertices: [0, 1] },
+            Edge { vertices: [1, 2] },
+            Edge { vertices: [2, 0] },
+        ],
+        surf_edges: vec![1, 2, 3],
+        models: vec![Model {
+            mins: [-64.0, -64.0, -64.0],
+            maxs: [64.0, 64.0, 64.0],
+            origin: [0.0; 3],
+            head_nodes: [0, 0, -1, -1],
+            vis_leaf_count: 1,
+            first_face: 0,
+            face_count: 1,
+        }],
+    }
+}
+
+fn bsp_bytes(bsp: &Bsp) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bsp.write_to(&mut bytes).unwrap();
+    bytes
+}
+
+fn validate(bytes: &[u8]) -> ValidationReport {
+    let mut cursor = Cursor::new(bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    parser.validate().unwrap()
+}
+
+fn set_entry(bytes: &mut [u8], lump: EntryOffset, offset: u32, length: u32) {
+    let idx = usize::from(lump);
+    bytes[(4 + idx * 8)..(8 + idx * 8)].copy_from_slice(&offset.to_le_bytes());
+    bytes[(8 + idx * 8)..(12 + idx * 8)].copy_from_slice(&length.to_le_bytes());
+}
+
+#[test]
+fn validate_good_bsp() {
+    for version in [BSP_VERSION, BSP2_VERSION, BSP2_RMQ_VERSION, HL_BSP_VERSION]
+    {
+        let report = validate(&bsp_bytes(&valid_bsp(version)));
+        assert_eq!(report.issues, []);
+        assert!(report.is_valid());
+    }
+}
+
+#[test]
+fn validate_empty_bsp() {
+    let report = validate(&bsp_bytes(&Bsp::default()));
+    assert!(report.is_valid());
+}
+
+#[test]
+fn validate_lumps_out_of_bounds() {
+    let mut bytes = bsp_bytes(&valid_bsp(BSP_VERSION));
+    let file_length = bytes.len() as u64;
+    set_entry(&mut bytes, EntryOffset::Models, 124, u32::MAX);
+    set_entry(&mut bytes, EntryOffset::Vis, 8, 4);
+
+    let report = validate(&bytes);
+
+    assert_eq!(
+        report.issues,
+        [
+            ValidationIssue::LumpOutOfBounds {
+                lump: EntryOffset::Models,
+                offset: 124,
+                length: u32::MAX,
+                file_length,
+            },
+            ValidationIssue::LumpOutOfBounds {
+                lump: EntryOffset::Vis,
+                offset: 8,
+                length: 4,
+                file_length,
+            },
+        ]
+    );
+}
+
+#[test]
+fn validate_overlapping_lumps() {
+    let mut bytes = bsp_bytes(&valid_bsp(BSP_VERSION));
+
+    // Planes are written first, directly after the header
+    set_entry(&mut bytes, EntryOffset::Vertices, 128, 12);
+
+    let report = validate(&bytes);
+
+    assert!(report.issues.contains(&ValidationIssue::LumpsOverlap {
+        lumps: [EntryOffset::Planes, EntryOffset::Vertices],
+    }));
+}
+
+#[test]
+fn validate_bad_lump_length() {
+    let mut bsp = valid_bsp(BSP_VERSION);
+    bsp.planes.clear();
+    let mut writer = bsp.to_writer().unwrap();
+    writer.set_lump(EntryOffset::Planes, vec![0; 19]);
+    let mut bytes = Vec::new();
+    writer.write_to(&mut bytes).unwrap();
+
+    let report = validate(&bytes);
+
+    // Indices into the planes lump can't be checked
+    assert_eq!(
+        report.issues,
+        [ValidationIssue::BadLumpLength {
+            lump: EntryOffset::Planes,
+            length: 19,
+            record_size: 20,
+        }]
+    );
+}
+
+#[test]
+fn validate_bad_lumps() {
+    let mut planes = Vec::new();
+    [0.0f32, 0.0, 1.0, 0.0]
+        .iter()
+        .for_each(|f| planes.extend(f.to_le_bytes()));
+    planes.extend(9i32.to_le_bytes());
+
+    let mut writer = Writer::new(BSP_VERSION);
+    writer.set_lump(EntryOffset::Planes, planes);
+    writer.set_lump(EntryOffset::Textures, 2i32.to_le_bytes().to_vec());
+    let mut bytes = Vec::new();
+    writer.write_to(&mut bytes).unwrap();
+
+    let report = validate(&bytes);
+
+    assert_eq!(
+        report.issues,
+        [
+            ValidationIssue::BadLump {
+                lump: EntryOffset::Planes,
+                message: "Unrecognized plane type 9".into(),
+            },
+            ValidationIssue::BadLump {
+                lump: EntryOffset::Textures,
+                message: "Texture offsets cut off".into(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn validate_indices() {
+    let mut bsp = valid_bsp(BSP2_VERSION);
+    bsp.edges[3].vertices = [2, 3];
+    bsp.surf_edges[2] = -4;
+    bsp.tex_info[0].mip_texture = 1;
+    bsp.faces[0].light_offset = 25;
+    bsp.nodes[0].children[1] = NodeChild::Leaf(2);
+    bsp.clip_nodes[0].children[0] = ClipNodeChild::Node(1);
+    bsp.leaves[1].mark_surface_count = 2;
+    bsp.mark_surfaces[0] = 1;
+    bsp.models[0].face_count = 2;
+
+    let report = validate(&bsp_bytes(&bsp));
+    let out_of_range =
+        |lump, record, target, index, count| ValidationIssue::IndexOutOfRange {
+            lump,
+            record,
+            target,
+            index,
+            count,
+        };
+
+    assert_eq!(
+        report.issues,
+        [
+            out_of_range(EntryOffset::Edges, 3, EntryOffset::Vertices, 3, 3),
+            out_of_range(EntryOffset::SurfEdges, 2, EntryOffset::Edges, 4, 4),
+            out_of_range(EntryOffset::TexInfo, 0, EntryOffset::Textures, 1, 1),
+            out_of_range(EntryOffset::Faces, 0, EntryOffset::Light, 25, 25),
+            out_of_range(EntryOffset::Nodes, 0, EntryOffset::Leaves, 2, 2),
+            out_of_range(
+                EntryOffset::ClipNodes,
+                0,
+                EntryOffset::ClipNodes,
+                1,
+                1
+            ),
+            out_of_range(
+                EntryOffset::Leaves,
+                1,
+                EntryOffset::MarkSurfaces,
+                1,
+                1
+            ),
+            out_of_range(
+                EntryOffset::MarkSurfaces,
+                0,
+                EntryOffset::Faces,
+                1,
+                1
+            ),
+            out_of_range(EntryOffset::Models, 0, EntryOffset::Faces, 1, 1),
+        ]
+    );
+}
+
+#[test]
+fn validate_bad_textures() {
+    let mut bsp = valid_bsp(BSP_VERSION);
+    bsp.textures = [1i32, 8].iter().flat_map(|i| i.to_le_bytes()).collect();
+    bsp.textures.extend(b"huge\0\0\0\0\0\0\0\0\0\0\0\0");
+    bsp.textures.extend(65_528u32.to_le_bytes());
+    bsp.textures.extend(65_528u32.to_le_bytes());
+    bsp.textures
+        .extend([40u32; 4].iter().flat_map(|o| o.to_le_bytes()));
+
+    let report = validate(&bsp_bytes(&bsp));
+
+    assert_eq!(
+        report.issues,
+        [ValidationIssue::BadLump {
+            lump: EntryOffset::Textures,
+            message: "Texture at offset 8 cut off".into(),
+        }]
+    );
+}
+
+#[test]
+fn validate_lightmap_cut_off() {
+    let mut bsp = valid_bsp(BSP_VERSION);
+    bsp.faces[0].styles = [0, 1, 255, 255];
+    let report = validate(&bsp_bytes(&bsp));
+
+    assert_eq!(
+        report.issues,
+        [ValidationIssue::IndexOutOfRange {
+            lump: EntryOffset::Faces,
+            record: 0,
+            target: EntryOffset::Light,
+            index: 49,
+            count: 25,
+        }]
+    );
+
+    let mut bsp = valid_bsp(HL_BSP_VERSION);
+    bsp.lighting.truncate(25);
+
+    assert!(!validate(&bsp_bytes(&bsp)).is_valid());
+}
+
+#[test]
+fn validate_issue_display() {
+    let issue = ValidationIssue::IndexOutOfRange {
+        lump: EntryOffset::Faces,
+        record: 3,
+        target: EntryOffset::TexInfo,
+        index: 7,
+        count: 2,
+    };
+
+    assert_eq!(
+        format!("{issue}"),
+        "Faces record 3 refers to TexInfo index 7 but there are only 2"
+    );
+}
@@ -0,0 +1,177 @@
+use super::decoded::out_of_range;
+use super::{Bsp, NodeChild};
+use crate::{BinParseError, BinParseResult};
+use std::string::String;
+use std::vec::Vec;
+
+/// Decompress a single run-length encoded row of visibility data.  Non-zero
+/// bytes are copied verbatim, while a zero byte is followed by a count of zero
+/// bytes to output.
+pub fn decompress_vis(
+    compressed: &[u8],
+    row_length: usize,
+) -> BinParseResult<Vec<u8>> {
+    let mut row = Vec::with_capacity(row_length);
+    let mut bytes = compressed.iter();
+
+    while row.len() < row_length {
+        match bytes.next() {
+            Some(0) => {
+                let count = bytes.next().ok_or(BinParseError::Parse(
+                    String::from("Visibility run cut off"),
+                ))?;
+                let count = usize::from(*count).min(row_length - row.len());
+                row.resize(row.len() + count, 0);
+            }
+            Some(&byte) => {
+                row.push(byte);
+            }
+            None => {
+                return Err(BinParseError::Parse(String::from(
+                    "Visibility row cut off",
+                )));
+            }
+        }
+    }
+
+    Ok(row)
+}
+
+/// Potentially visible set: for each leaf, the set of leaves that may be seen
+/// from within it.  Leaf 0 is the solid space outside the map and is never
+/// visible.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pvs {
+    rows: Vec<Vec<u8>>,
+    vis_leaf_count: usize,
+}
+
+impl Pvs {
+    /// Number of leaves, not including leaf 0, covered by each row
+    pub fn vis_leaf_count(&self) -> usize {
+        self.vis_leaf_count
+    }
+
+    /// Decompressed visibility row for a leaf, where bit `i` (least
+    /// significant bit first) is set if leaf `i + 1` is visible.  Returns
+    /// `None` if the leaf does not exist.
+    pub fn row(&self, leaf: u32) -> Option<&[u8]> {
+        self.rows.get(leaf as usize).map(|row| &row[..])
+    }
+
+    /// Determine whether leaf `to` is potentially visible from leaf `from`
+    pub fn is_visible(&self, from: u32, to: u32) -> bool {
+        if to == 0 || to as usize > self.vis_leaf_count {
+            return false;
+        }
+
+        let bit = to as usize - 1;
+
+        self.row(from)
+            .map(|row| row[bit >> 3] & (1 << (bit & 7)) != 0)
+            .unwrap_or(false)
+    }
+
+    /// Iterate over the leaves potentially visible from a leaf
+    pub fn visible_leaves(&self, from: u32) -> impl Iterator<Item = u32> + '_ {
+        (1..=(self.vis_leaf_count as u32))
+            .filter(move |&to| self.is_visible(from, to))
+    }
+}
+
+impl Bsp {
+    /// Decompress the visibility lump for every leaf.  Leaves without
+    /// visibility data (including leaf 0) see every leaf, as in the engine.
+    pub fn pvs(&self) -> BinParseResult<Pvs> {
+        let vis_leaf_count = self
+            .models
+            .first()
+            .map_or(0, |world| world.vis_leaf_count as usize)
+            .min(self.leaves.len().saturating_sub(1));
+        let row_length = vis_leaf_count.div_ceil(8);
+
+        let rows = self
+            .leaves
+            .iter()
+            .enumerate()
+            .map(|(index, leaf)| {
+                let offset = usize::try_from(leaf.vis_offset)
+                    .ok()
+                    .filter(|_| index != 0 && !self.visibility.is_empty());
+
+                match offset {
+                    Some(offset) => {
+                        let compressed =
+                            self.visibility.get(offset..).ok_or_else(|| {
+                                BinParseError::Parse(format!(
+                                    "Visibility offset {offset} out of range"
+                                ))
+                            })?;
+
+                        decompress_vis(compressed, row_length)
+                    }
+                    None => Ok(vec![0xff; row_length]),
+                }
+            })
+            .collect::<BinParseResult<Vec<_>>>()?;
+
+        Ok(Pvs {
+            rows,
+            vis_leaf_count,
+        })
+    }
+
+    /// Find the leaf of the world model containing a point
+    pub fn leaf_at(&self, point: [f32; 3]) -> BinParseResult<u32> {
+        let world =
+            self.models
+                .first()
+                .ok_or(BinParseError::Parse(String::from(
+                    "BSP has no world model",
+                )))?;
+        let mut child = NodeChild::from(world.head_nodes[0]);
+
+        // Bail out on cyclic trees rather than looping forever
+        for _ in 0..=self.nodes.len() {
+            let node_idx = match child {
+                NodeChild::Leaf(leaf) => {
+                    return if (leaf as usize) < self.leaves.len() {
+                        Ok(leaf)
+                    } else {
+                        Err(out_of_range("Leaf", leaf))
+                    };
+                }
+                NodeChild::Node(node_idx) => node_idx,
+            };
+
+            let node = self
+                .nodes
+                .get(node_idx as usize)
+                .ok_or_else(|| out_of_range("Node", node_idx))?;
+            let plane = self
+                .planes
+                .get(node.plane as usize)
+                .ok_or_else(|| out_of_range("Plane", node.plane))?;
+            let dist = (0..3).map(|i| plane.normal[i] * point[i]).sum::<f32>()
+                - plane.dist;
+
+            child = if dist > 0.0 {
+                node.children[0]
+            } else {
+                node.children[1]
+            };
+        }
+
+        Err(BinParseError::Parse(String::from("Cycle in BSP tree")))
+    }
+
+    /// Iterate over the leaves potentially visible from a point
+    pub fn visible_leaves_from<'a>(
+        &self,
+        pvs: &'a Pvs,
+        point: [f32; 3],
+    ) -> BinParseResult<impl Iterator<Item = u32> + 'a> {
+        let leaf = self.leaf_at(point)?;
+        Ok(pvs.visible_leaves(leaf))
+    }
+}
@@ -0,0 +1,139 @@
+use super::test_fixtures::leaf;
+use crate::{bsp, BinParseError};
+use bsp::{
+    contents, decompress_vis, Bsp, Leaf, Model, Node, NodeChild, Plane,
+    PlaneKind,
+};
+use std::vec::Vec;
+
+fn split_bsp() -> Bsp {
+    Bsp {
+        planes: vec![Plane {
+            normal: [1.0, 0.0, 0.0],
+            dist: 0.0,
+            kind: PlaneKind::X,
+        }],
+        visibility: vec![0x01, 0x00, 0x01],
+        nodes: vec![Node {
+            plane: 0,
+            children: [NodeChild::Leaf(1), NodeChild::Leaf(2)],
+            mins: [-64.0; 3],
+            maxs: [64.0; 3],
+            first_face: 0,
+            face_count: 0,
+        }],
+        leaves: vec![
+            leaf(contents::SOLID),
+            Leaf {
+                vis_offset: 0,
+                ..leaf(contents::EMPTY)
+            },
+            Leaf {
+                vis_offset: 1,
+                ..leaf(contents::WATER)
+            },
+        ],
+        models: vec![Model {
+            mins: [-64.0; 3],
+            maxs: [64.0; 3],
+            origin: [0.0; 3],
+            head_nodes: [0, 0, 0, 0],
+            vis_leaf_count: 2,
+            first_face: 0,
+            face_count: 0,
+        }],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn decompress_literal_row() {
+    let row = decompress_vis(&[0x12, 0x34, 0x56], 3).unwrap();
+    assert_eq!(row, vec![0x12, 0x34, 0x56]);
+}
+
+#[test]
+fn decompress_zero_runs() {
+    let row = decompress_vis(&[0x80, 0x00, 0x03, 0x01, 0x00, 0x02], 7).unwrap();
+    assert_eq!(row, vec![0x80, 0, 0, 0, 0x01, 0, 0]);
+}
+
+#[test]
+fn decompress_overlong_run() {
+    let row = decompress_vis(&[0x00, 0x10], 4).unwrap();
+    assert_eq!(row, vec![0, 0, 0, 0]);
+}
+
+#[test]
+fn decompress_cut_off() {
+    let err = decompress_vis(&[0xff, 0x00], 4).unwrap_err();
+    assert!(matches!(err, BinParseError::Parse(_)));
+
+    let err = decompress_vis(&[0xff, 0xff], 4).unwrap_err();
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn leaf_visibility() {
+    let bsp = split_bsp();
+    let pvs = bsp.pvs().unwrap();
+
+    assert_eq!(pvs.vis_leaf_count(), 2);
+    assert!(pvs.is_visible(1, 1));
+    assert!(!pvs.is_visible(1, 2));
+    assert!(!pvs.is_visible(2, 1));
+    assert!(!pvs.is_visible(2, 2));
+    assert!(!pvs.is_visible(1, 0));
+    assert!(!pvs.is_visible(1, 3));
+    assert!(!pvs.is_visible(3, 1));
+    assert_eq!(pvs.visible_leaves(0).collect::<Vec<_>>(), vec![1, 2]);
+}
+
+#[test]
+fn unvised_leaf_sees_all() {
+    let mut bsp = split_bsp();
+    bsp.leaves[2].vis_offset = -1;
+    let pvs = bsp.pvs().unwrap();
+
+    assert_eq!(pvs.visible_leaves(2).collect::<Vec<_>>(), vec![1, 2]);
+    assert_eq!(pvs.row(2), Some(&[0xff][..]));
+}
+
+#[test]
+fn bad_vis_offset() {
+    let mut bsp = split_bsp();
+    bsp.leaves[2].vis_offset = 4;
+    let err = bsp.pvs().unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn find_leaf() {
+    let bsp = split_bsp();
+
+    assert_eq!(bsp.leaf_at([10.0, 0.0, 0.0]).unwrap(), 1);
+    assert_eq!(bsp.leaf_at([-10.0, 5.0, 5.0]).unwrap(), 2);
+    assert_eq!(bsp.leaf_at([0.0, 0.0, 0.0]).unwrap(), 2);
+}
+
+#[test]
+fn find_leaf_in_cyclic_tree() {
+    let mut bsp = split_bsp();
+    bsp.nodes[0].children[1] = NodeChild::Node(0);
+    let err = bsp.leaf_at([-1.0, 0.0, 0.0]).unwrap_err();
+
+    assert!(matches!(err, BinParseError::Parse(_)));
+}
+
+#[test]
+fn visible_from_point() {
+    let bsp = split_bsp();
+    let pvs = bsp.pvs().unwrap();
+    let visible = bsp
+        .visible_leaves_from(&pvs, [32.0, 0.0, 0.0])
+        .unwrap()
+        .collect::<Vec<_>>();
+
+    assert_eq!(visible, vec![1]);
+}
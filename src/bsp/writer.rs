@@ -0,0 +1,397 @@
+use super::bspx::{BSPX_ENTRY_SZ, BSPX_MAGIC, BSPX_NAME_SZ};
+use super::repr::{EncodeRecord, Layout, ENTRY_COUNT, VERSIONS};
+use super::{Bsp, EntryOffset, BSP2_RMQ_VERSION, BSP2_VERSION, BSP_VERSION};
+use crate::{qmap, slice_to_cstring, WriteAttempt, WriteError};
+use qmap::QuakeMap;
+use std::io;
+use std::string::String;
+use std::vec::Vec;
+
+/// Order lumps are laid out in, as written by qbsp
+pub(super) const LUMP_ORDER: [EntryOffset; ENTRY_COUNT] = [
+    EntryOffset::Planes,
+    EntryOffset::Leaves,
+    EntryOffset::Vertices,
+    EntryOffset::Nodes,
+    EntryOffset::TexInfo,
+    EntryOffset::Faces,
+    EntryOffset::ClipNodes,
+    EntryOffset::MarkSurfaces,
+    EntryOffset::SurfEdges,
+    EntryOffset::Edges,
+    EntryOffset::Models,
+    EntryOffset::Light,
+    EntryOffset::Vis,
+    EntryOffset::Entities,
+    EntryOffset::Textures,
+];
+
+/// BSP writer.  Lumps are provided as raw bytes and are laid out after the
+/// header in the same order as qbsp writes them, or in the order of the source
+/// file for writers from `Parser::parse_lumps`, each padded with zeroes to a
+/// multiple of 4 bytes.  BSPX lumps, if any, follow in a BSPX directory.
+///
+/// Writing the lumps of a parsed file unchanged gives back the same bytes
+/// when its lumps are contiguous and 4-byte aligned, as compilers write them.
+/// Gaps between lumps, other padding, and the offsets of empty lumps are not
+/// preserved.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Writer {
+    version: u32,
+    lumps: [Vec<u8>; ENTRY_COUNT],
+    lump_order: [EntryOffset; ENTRY_COUNT],
+    bspx_lumps: Vec<([u8; BSPX_NAME_SZ], Vec<u8>)>,
+}
+
+impl Writer {
+    /// Create a writer for the given BSP version with all lumps empty
+    pub fn new(version: u32) -> Self {
+        Self {
+            version,
+            lumps: Default::default(),
+            lump_order: LUMP_ORDER,
+            bspx_lumps: Vec::new(),
+        }
+    }
+
+    /// BSP version written to the header
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Replace the contents of a lump
+    pub fn set_lump(&mut self, entry_offset: EntryOffset, bytes: Vec<u8>) {
+        self.lumps[usize::from(entry_offset)] = bytes;
+    }
+
+    /// Replace the entities lump with the text form of a map, terminated with
+    /// a null byte as qbsp does.  Fails if the map does not pass validation.
+    pub fn set_entities(&mut self, map: &QuakeMap) -> WriteAttempt {
+        let mut bytes = Vec::new();
+        map.write_to(&mut bytes)?;
+        bytes.push(0);
+        self.set_lump(EntryOffset::Entities, bytes);
+        Ok(())
+    }
+
+    /// Bytes of a lump
+    pub fn lump(&self, entry_offset: EntryOffset) -> &[u8] {
+        &self.lumps[usize::from(entry_offset)]
+    }
+
+    /// Add or replace a BSPX lump.  Fails if the name contains a null byte or
+    /// does not fit in the 24 byte name field with a null terminator.
+    pub fn set_bspx_lump(
+        &mut self,
+        name: &str,
+        bytes: Vec<u8>,
+    ) -> WriteAttempt {
+        if name.len() >= BSPX_NAME_SZ || name.contains('\0') {
+            return Err(WriteError::Validation(format!(
+                "Bad BSPX lump name `{name}`"
+            )));
+        }
+
+        let mut raw_name = [0u8; BSPX_NAME_SZ];
+        raw_name[..name.len()].copy_from_slice(name.as_bytes());
+        self.set_bspx_lump_raw(raw_name, bytes);
+
+        Ok(())
+    }
+
+    pub(crate) fn set_lump_order(&mut self, order: [EntryOffset; ENTRY_COUNT]) {
+        self.lump_order = order;
+    }
+
+    pub(crate) fn set_bspx_lump_raw(
+        &mut self,
+        name: [u8; BSPX_NAME_SZ],
+        bytes: Vec<u8>,
+    ) {
+        match self.bspx_lumps.iter_mut().find(|(n, _)| n == &name) {
+            Some((_, lump)) => *lump = bytes,
+            None => self.bspx_lumps.push((name, bytes)),
+        }
+    }
+
+    /// Bytes of a BSPX lump, if present
+    pub fn bspx_lump(&self, name: &str) -> Option<&[u8]> {
+        self.bspx_lumps
+            .iter()
+            .find(|(n, _)| slice_to_cstring(n).as_bytes() == name.as_bytes())
+            .map(|(_, bytes)| &bytes[..])
+    }
+
+    /// Remove a BSPX lump, returning its bytes if it was present
+    pub fn remove_bspx_lump(&mut self, name: &str) -> Option<Vec<u8>> {
+        let idx = self.bspx_lumps.iter().position(|(n, _)| {
+            slice_to_cstring(n).as_bytes() == name.as_bytes()
+        })?;
+
+        Some(self.bspx_lumps.remove(idx).1)
+    }
+
+    /// Writes the header and lumps, failing if the version is not recognized,
+    /// the BSP is too large for 32-bit offsets, or an I/O error occurs
+    pub fn write_to<W: io::Write>(&self, writer: &mut W) -> WriteAttempt {
+        if !VERSIONS.contains(&self.version) {
+            return Err(WriteError::Validation(format!(
+                "Unrecognized BSP version {}",
+                self.version
+            )));
+        }
+
+        let mut entries = [(0u32, 0u32); ENTRY_COUNT];
+        let mut position = 4 + 8 * ENTRY_COUNT;
+
+        for entry_offset in self.lump_order {
+            entries[usize::from(entry_offset)] =
+                place(&mut position, self.lump(entry_offset).len())?;
+        }
+
+        let mut bspx_entries = Vec::with_capacity(self.bspx_lumps.len());
+
+        if !self.bspx_lumps.is_empty() {
+            place(&mut position, 8 + BSPX_ENTRY_SZ * self.bspx_lumps.len())?;
+
+            for (_, lump) in &self.bspx_lumps {
+                bspx_entries.push(place(&mut position, lump.len())?);
+            }
+        }
+
+        u32::try_from(position).map_err(|_| too_large())?;
+
+        writer.write_all(&self.version.to_le_bytes())?;
+
+        for (offset, length) in entries {
+            writer.write_all(&offset.to_le_bytes())?;
+            writer.write_all(&length.to_le_bytes())?;
+        }
+
+        for entry_offset in self.lump_order {
+            write_padded(writer, self.lump(entry_offset))?;
+        }
+
+        if self.bspx_lumps.is_empty() {
+            return Ok(());
+        }
+
+        writer.write_all(&BSPX_MAGIC)?;
+        writer.write_all(&(self.bspx_lumps.len() as u32).to_le_bytes())?;
+
+        for ((name, _), (offset, length)) in
+            self.bspx_lumps.iter().zip(&bspx_entries)
+        {
+            writer.write_all(name)?;
+            writer.write_all(&offset.to_le_bytes())?;
+            writer.write_all(&length.to_le_bytes())?;
+        }
+
+        for (_, lump) in &self.bspx_lumps {
+            write_padded(writer, lump)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Allocates space for a lump at the current position, yielding its offset and
+// length
+fn place(
+    position: &mut usize,
+    length: usize,
+) -> Result<(u32, u32), WriteError> {
+    let offset = u32::try_from(*position).map_err(|_| too_large())?;
+    let length_u32 = u32::try_from(length).map_err(|_| too_large())?;
+    *position = position
+        .checked_add(padded_length(length))
+        .ok_or_else(too_large)?;
+    Ok((offset, length_u32))
+}
+
+fn too_large() -> WriteError {
+    WriteError::Validation(String::from("BSP too large"))
+}
+
+fn write_padded<W: io::Write>(writer: &mut W, lump: &[u8]) -> WriteAttempt {
+    writer.write_all(lump)?;
+    writer.write_all(&[0u8; 3][..(padded_length(lump.len()) - lump.len())])?;
+    Ok(())
+}
+
+fn padded_length(length: usize) -> usize {
+    length.next_multiple_of(4)
+}
+
+fn round_bounds(mins: &mut [f32; 3], maxs: &mut [f32; 3]) {
+    *mins = mins.map(f32::floor);
+    *maxs = maxs.map(f32::ceil);
+}
+
+// Errors name the lump and index of the record which failed to encode
+fn encode_records<R: EncodeRecord>(
+    records: &[R],
+    layout: Layout,
+    lump: EntryOffset,
+) -> Result<Vec<u8>, WriteError> {
+    let mut bytes = Vec::with_capacity(records.len() * R::size(layout));
+
+    for (idx, record) in records.iter().enumerate() {
+        record
+            .write_bytes(&mut bytes, layout)
+            .map_err(|err| record_error(err, lump, idx))?;
+    }
+
+    Ok(bytes)
+}
+
+// Checks that every record fits in a layout, without keeping the bytes
+fn check_records<R: EncodeRecord>(
+    records: &[R],
+    layout: Layout,
+    lump: EntryOffset,
+) -> WriteAttempt {
+    let mut scratch = Vec::with_capacity(R::size(layout));
+
+    for (idx, record) in records.iter().enumerate() {
+        scratch.clear();
+        record
+            .write_bytes(&mut scratch, layout)
+            .map_err(|err| record_error(err, lump, idx))?;
+    }
+
+    Ok(())
+}
+
+fn record_error(err: WriteError, lump: EntryOffset, idx: usize) -> WriteError {
+    match err {
+        WriteError::Validation(msg) => {
+            WriteError::Validation(format!("{lump:?} record {idx}: {msg}"))
+        }
+        err => err,
+    }
+}
+
+impl Bsp {
+    /// Encode every lump into a writer for this BSP's version, failing if a
+    /// record does not fit within the version's limits
+    pub fn to_writer(&self) -> Result<Writer, WriteError> {
+        let layout = Layout::from_version(self.version);
+        let mut writer = Writer::new(self.version);
+
+        writer.set_lump(EntryOffset::Entities, self.entities.clone());
+        writer.set_lump(
+            EntryOffset::Planes,
+            encode_records(&self.planes, layout, EntryOffset::Planes)?,
+        );
+        writer.set_lump(EntryOffset::Textures, self.textures.clone());
+        writer.set_lump(
+            EntryOffset::Vertices,
+            encode_records(&self.vertices, layout, EntryOffset::Vertices)?,
+        );
+        writer.set_lump(EntryOffset::Vis, self.visibility.clone());
+        writer.set_lump(
+            EntryOffset::Nodes,
+            encode_records(&self.nodes, layout, EntryOffset::Nodes)?,
+        );
+        writer.set_lump(
+            EntryOffset::TexInfo,
+            encode_records(&self.tex_info, layout, EntryOffset::TexInfo)?,
+        );
+        writer.set_lump(
+            EntryOffset::Faces,
+            encode_records(&self.faces, layout, EntryOffset::Faces)?,
+        );
+        writer.set_lump(EntryOffset::Light, self.lighting.clone());
+        writer.set_lump(
+            EntryOffset::ClipNodes,
+            encode_records(&self.clip_nodes, layout, EntryOffset::ClipNodes)?,
+        );
+        writer.set_lump(
+            EntryOffset::Leaves,
+            encode_records(&self.leaves, layout, EntryOffset::Leaves)?,
+        );
+        writer.set_lump(
+            EntryOffset::MarkSurfaces,
+            encode_records(
+                &self.mark_surfaces,
+                layout,
+                EntryOffset::MarkSurfaces,
+            )?,
+        );
+        writer.set_lump(
+            EntryOffset::Edges,
+            encode_records(&self.edges, layout, EntryOffset::Edges)?,
+        );
+        writer.set_lump(
+            EntryOffset::SurfEdges,
+            encode_records(&self.surf_edges, layout, EntryOffset::SurfEdges)?,
+        );
+        writer.set_lump(
+            EntryOffset::Models,
+            encode_records(&self.models, layout, EntryOffset::Models)?,
+        );
+
+        Ok(writer)
+    }
+
+    /// Converts between BSP29 and the BSP2 variants, e.g. to upgrade a BSP29
+    /// file to BSP2.  Node and leaf bounds are rounded outward when narrowed to
+    /// 16-bit integers.  Fails with an error naming the lump, record, and limit
+    /// if a record does not fit in the new version, or if either version is not
+    /// BSP29, BSP2, or 2PSB.
+    ///
+    /// BSPX lumps, such as LIT or DECOUPLED_LM lighting extensions, are not
+    /// part of `Bsp` and so are not carried over.  Conversion keeps records in
+    /// place, so they remain valid and can be copied to the converted BSP's
+    /// writer with `Writer::set_bspx_lump`.
+    pub fn convert(&self, version: u32) -> Result<Bsp, WriteError> {
+        const CONVERTIBLE: [u32; 3] =
+            [BSP_VERSION, BSP2_VERSION, BSP2_RMQ_VERSION];
+
+        if !CONVERTIBLE.contains(&self.version)
+            || !CONVERTIBLE.contains(&version)
+        {
+            return Err(WriteError::Validation(format!(
+                "Cannot convert BSP version {} to version {version}",
+                self.version,
+            )));
+        }
+
+        let mut converted = Bsp {
+            version,
+            ..self.clone()
+        };
+
+        let layout = Layout::from_version(version);
+
+        if layout != Layout::Bsp2 {
+            for node in &mut converted.nodes {
+                round_bounds(&mut node.mins, &mut node.maxs);
+            }
+
+            for leaf in &mut converted.leaves {
+                round_bounds(&mut leaf.mins, &mut leaf.maxs);
+            }
+        }
+
+        // Only these lumps have records laid out differently between versions
+        check_records(&converted.nodes, layout, EntryOffset::Nodes)?;
+        check_records(&converted.faces, layout, EntryOffset::Faces)?;
+        check_records(&converted.clip_nodes, layout, EntryOffset::ClipNodes)?;
+        check_records(&converted.leaves, layout, EntryOffset::Leaves)?;
+        check_records(
+            &converted.mark_surfaces,
+            layout,
+            EntryOffset::MarkSurfaces,
+        )?;
+        check_records(&converted.edges, layout, EntryOffset::Edges)?;
+
+        Ok(converted)
+    }
+
+    /// Writes the BSP in its version's format, see `Writer`
+    pub fn write_to<W: io::Write>(&self, writer: &mut W) -> WriteAttempt {
+        self.to_writer()?.write_to(writer)
+    }
+}
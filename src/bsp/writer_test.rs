@@ -0,0 +1,432 @@
+use crate::{bsp, qmap, WriteError};
+use bsp::{
+    contents, Bsp, ClipNode, ClipNodeChild, Edge, EntryOffset, Face, Leaf,
+    Model, Node, NodeChild, Plane, PlaneKind, TexInfo, Writer,
+    BSP2_RMQ_VERSION, BSP2_VERSION, BSP_VERSION, HL_BSP_VERSION,
+};
+use qmap::{Entity, QuakeMap};
+use std::ffi::CString;
+use std::io::{Cursor, Read};
+use std::vec::Vec;
+
+const HEAD_SZ: usize = 4 + 8 * 15;
+
+fn full_bsp(version: u32) -> Bsp {
+    Bsp {
+        version,
+        entities: b"{\n\"classname\" \"worldspawn\"\n}\n\0".to_vec(),
+        planes: vec![Plane {
+            normal: [0.0, 0.0, 1.0],
+            dist: 0.0,
+            kind: PlaneKind::Z,
+        }],
+        textures: vec![0, 0, 0, 0],
+        vertices: vec![[0.0, 0.0, 0.0], [64.0, 0.0, 0.0], [0.0, 64.0, 0.0]],
+        visibility: vec![0x01, 0x00, 0x02],
+        nodes: vec![Node {
+            plane: 0,
+            children: [NodeChild::Leaf(1), NodeChild::Leaf(0)],
+            mins: [-64.0, -64.0, -64.0],
+            maxs: [64.0, 64.0, 64.0],
+            first_face: 0,
+            face_count: 1,
+        }],
+        tex_info: vec![TexInfo {
+            vecs: [[1.0, 0.0, 0.0, 0.0], [0.0, -1.0, 0.0, 0.0]],
+            mip_texture: 0,
+            flags: 0,
+        }],
+        faces: vec![Face {
+            plane: 0,
+            side: 0,
+            first_edge: 0,
+            edge_count: 3,
+            tex_info: 0,
+            styles: [0, 255, 255, 255],
+            light_offset: 0,
+        }],
+        lighting: vec![200; 25],
+        clip_nodes: vec![ClipNode {
+            plane: 0,
+            children: [
+                ClipNodeChild::Contents(contents::EMPTY),
+                ClipNodeChild::Contents(contents::SOLID),
+            ],
+        }],
+        leaves: vec![
+            Leaf {
+                contents: contents::SOLID,
+                vis_offset: -1,
+                mins: [0.0; 3],
+                maxs: [0.0; 3],
+                first_mark_surface: 0,
+                mark_surface_count: 0,
+                ambient_levels: [0; 4],
+            },
+            Leaf {
+                contents: contents::EMPTY,
+                vis_offset: 0,
+                mins: [-64.0, -64.0, 0.0],
+                maxs: [64.0, 64.0, 64.0],
+                first_mark_surface: 0,
+                mark_surface_count: 1,
+                ambient_levels: [0, 0, 0, 0],
+            },
+        ],
+        mark_surfaces: vec![0],
+        edges: vec![
+            Edge { vertices: [0, 0] },
+            Edge { vertices: [0, 1] },
+            Edge { vertices: [1, 2] },
+            Edge { vertices: [2, 0] },
+        ],
+        surf_edges: vec![1, 2, 3],
+        models: vec![Model {
+            mins: [-64.0, -64.0, -64.0],
+            maxs: [64.0, 64.0, 64.0],
+            origin: [0.0; 3],
+            head_nodes: [0, 0, -1, -1],
+            vis_leaf_count: 1,
+            first_face: 0,
+            face_count: 1,
+        }],
+    }
+}
+
+#[test]
+fn roundtrip() {
+    for version in [BSP_VERSION, BSP2_VERSION, BSP2_RMQ_VERSION, HL_BSP_VERSION]
+    {
+        let bsp = full_bsp(version);
+        let mut bytes = Vec::new();
+        bsp.write_to(&mut bytes).unwrap();
+
+        let mut cursor = Cursor::new(&bytes);
+        let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+        let parsed = parser.parse_bsp().unwrap();
+        assert_eq!(parsed, bsp);
+
+        let mut rewritten = Vec::new();
+        parsed.write_to(&mut rewritten).unwrap();
+        assert_eq!(rewritten, bytes);
+    }
+}
+
+#[test]
+fn write_aligned_lumps() {
+    let mut writer = Writer::new(BSP_VERSION);
+    writer.set_lump(EntryOffset::Planes, vec![1; 5]);
+    writer.set_lump(EntryOffset::Leaves, vec![2; 3]);
+    writer.set_lump(EntryOffset::Textures, vec![3; 4]);
+
+    let mut bytes = Vec::new();
+    writer.write_to(&mut bytes).unwrap();
+
+    let mut cursor = Cursor::new(&bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let entries = [
+        EntryOffset::Planes,
+        EntryOffset::Leaves,
+        EntryOffset::Vertices,
+        EntryOffset::Textures,
+    ]
+    .map(|entry_offset| {
+        let length = parser.lump_reader(entry_offset).unwrap().limit();
+        let offset = cursor_offset(&bytes, entry_offset);
+        (offset, length)
+    });
+
+    assert_eq!(
+        entries,
+        [
+            (HEAD_SZ as u32, 5),
+            (HEAD_SZ as u32 + 8, 3),
+            (HEAD_SZ as u32 + 12, 0),
+            (HEAD_SZ as u32 + 12, 4),
+        ]
+    );
+    assert_eq!(bytes.len(), HEAD_SZ + 16);
+    assert_eq!(&bytes[HEAD_SZ..(HEAD_SZ + 8)], &[1, 1, 1, 1, 1, 0, 0, 0]);
+    assert_eq!(&bytes[(HEAD_SZ + 8)..(HEAD_SZ + 12)], &[2, 2, 2, 0]);
+}
+
+fn cursor_offset(bytes: &[u8], entry_offset: EntryOffset) -> u32 {
+    let idx = 4 + 8 * usize::from(entry_offset);
+    u32::from_le_bytes(bytes[idx..(idx + 4)].try_into().unwrap())
+}
+
+#[test]
+fn write_bad_version() {
+    let writer = Writer::new(31);
+    let err = writer.write_to(&mut Vec::new()).unwrap_err();
+
+    assert!(matches!(err, WriteError::Validation(_)));
+}
+
+#[test]
+fn write_bsp29_over_limit() {
+    let mut bsp = full_bsp(BSP_VERSION);
+    bsp.edges[0].vertices[1] = 70_000;
+    let err = bsp.write_to(&mut Vec::new()).unwrap_err();
+
+    match err {
+        WriteError::Validation(msg) => {
+            assert!(msg.contains("Edge vertex index 70000"));
+            assert!(msg.contains("65535"));
+        }
+        _ => panic!("Expected validation error"),
+    }
+
+    bsp.version = BSP2_VERSION;
+    bsp.write_to(&mut Vec::new()).unwrap();
+}
+
+#[test]
+fn write_bounds_over_limit() {
+    let mut bsp = full_bsp(BSP2_RMQ_VERSION);
+    bsp.leaves[1].mins[0] = -40_000.0;
+    let err = bsp.write_to(&mut Vec::new()).unwrap_err();
+
+    assert!(matches!(err, WriteError::Validation(_)));
+}
+
+#[test]
+fn convert_bsp29_to_bsp2() {
+    let bsp = full_bsp(BSP_VERSION);
+    let converted = bsp.convert(BSP2_VERSION).unwrap();
+    assert_eq!(converted.version, BSP2_VERSION);
+
+    let mut bytes = Vec::new();
+    converted.write_to(&mut bytes).unwrap();
+    let mut cursor = Cursor::new(&bytes);
+    let parsed = bsp::Parser::new(&mut cursor).unwrap().parse_bsp().unwrap();
+    assert_eq!(parsed, converted);
+
+    assert_eq!(converted.convert(BSP_VERSION).unwrap(), bsp);
+}
+
+#[test]
+fn convert_rounds_bounds() {
+    let mut bsp = full_bsp(BSP2_VERSION);
+    bsp.nodes[0].mins = [-0.5, -64.0, 1.5];
+    bsp.leaves[1].maxs = [63.25, 64.0, -0.5];
+
+    let converted = bsp.convert(BSP2_RMQ_VERSION).unwrap();
+
+    assert_eq!(converted.nodes[0].mins, [-1.0, -64.0, 1.0]);
+    assert_eq!(converted.leaves[1].maxs, [64.0, 64.0, -0.0]);
+}
+
+#[test]
+fn convert_over_limit() {
+    let mut bsp = full_bsp(BSP2_VERSION);
+    bsp.faces.push(bsp.faces[0]);
+    bsp.faces[1].tex_info = 65_536;
+
+    match bsp.convert(BSP_VERSION).unwrap_err() {
+        WriteError::Validation(msg) => assert_eq!(
+            msg,
+            "Faces record 1: Face texture info index 65536 exceeds limit of \
+             65535"
+        ),
+        _ => panic!("Expected validation error"),
+    }
+
+    bsp.faces.pop();
+    bsp.nodes[0].children[0] = NodeChild::Leaf(40_000);
+
+    match bsp.convert(BSP_VERSION).unwrap_err() {
+        WriteError::Validation(msg) => assert_eq!(
+            msg,
+            "Nodes record 0: Node child leaf index 40000 exceeds limit of \
+             32767"
+        ),
+        _ => panic!("Expected validation error"),
+    }
+
+    bsp.convert(BSP2_RMQ_VERSION).unwrap();
+}
+
+#[test]
+fn convert_without_bspx_lumps() {
+    let mut writer = full_bsp(BSP_VERSION).to_writer().unwrap();
+    writer.set_bspx_lump("LMSHIFT", vec![4]).unwrap();
+    let mut bytes = Vec::new();
+    writer.write_to(&mut bytes).unwrap();
+
+    let mut cursor = Cursor::new(&bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let converted = parser.parse_bsp().unwrap().convert(BSP2_VERSION).unwrap();
+    let mut converted_writer = converted.to_writer().unwrap();
+
+    assert_eq!(converted_writer.bspx_lump("LMSHIFT"), None);
+
+    // BSPX lumps can be copied over from the source
+    for entry in parser.bspx_entries().unwrap() {
+        let mut lump = Vec::new();
+        parser
+            .bspx_lump_reader(&entry)
+            .unwrap()
+            .read_to_end(&mut lump)
+            .unwrap();
+        converted_writer
+            .set_bspx_lump(&entry.name_to_string().unwrap(), lump)
+            .unwrap();
+    }
+
+    assert_eq!(converted_writer.bspx_lump("LMSHIFT"), Some(&[4u8][..]));
+}
+
+#[test]
+fn convert_hl_bsp() {
+    let err = full_bsp(HL_BSP_VERSION).convert(BSP2_VERSION).unwrap_err();
+    assert!(matches!(err, WriteError::Validation(_)));
+
+    let err = full_bsp(BSP_VERSION).convert(HL_BSP_VERSION).unwrap_err();
+    assert!(matches!(err, WriteError::Validation(_)));
+}
+
+#[test]
+fn bsp_entities() {
+    let bsp = full_bsp(BSP_VERSION);
+    let map = bsp.parse_entities().unwrap();
+
+    assert_eq!(map.entities.len(), 1);
+    assert_eq!(map.entities[0].edict[0].1.as_bytes(), b"worldspawn");
+}
+
+#[test]
+fn copy_lumps_verbatim() {
+    let mut bytes = Vec::new();
+    full_bsp(BSP2_VERSION).write_to(&mut bytes).unwrap();
+
+    let mut cursor = Cursor::new(&bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let writer = parser.parse_lumps().unwrap();
+    let mut copied = Vec::new();
+    writer.write_to(&mut copied).unwrap();
+
+    assert_eq!(writer.version(), BSP2_VERSION);
+    assert_eq!(copied, bytes);
+}
+
+// Lays out the lumps of a BSP29 file in header order, rather than qbsp order,
+// with `gap` bytes before each lump
+fn header_ordered_bytes(writer: &Writer, gap: usize) -> Vec<u8> {
+    const HEADER_ORDER: [EntryOffset; 15] = [
+        EntryOffset::Entities,
+        EntryOffset::Planes,
+        EntryOffset::Textures,
+        EntryOffset::Vertices,
+        EntryOffset::Vis,
+        EntryOffset::Nodes,
+        EntryOffset::TexInfo,
+        EntryOffset::Faces,
+        EntryOffset::Light,
+        EntryOffset::ClipNodes,
+        EntryOffset::Leaves,
+        EntryOffset::MarkSurfaces,
+        EntryOffset::Edges,
+        EntryOffset::SurfEdges,
+        EntryOffset::Models,
+    ];
+
+    let mut head = BSP_VERSION.to_le_bytes().to_vec();
+    let mut lumps = Vec::new();
+
+    for entry_offset in HEADER_ORDER {
+        let lump = writer.lump(entry_offset);
+        lumps.extend(vec![0u8; gap]);
+        head.extend(((HEAD_SZ + lumps.len()) as u32).to_le_bytes());
+        head.extend((lump.len() as u32).to_le_bytes());
+        lumps.extend(lump);
+        lumps.extend(vec![0u8; lump.len().next_multiple_of(4) - lump.len()]);
+    }
+
+    head.extend(lumps);
+    head
+}
+
+#[test]
+fn copy_lumps_in_source_order() {
+    let writer = full_bsp(BSP_VERSION).to_writer().unwrap();
+    let bytes = header_ordered_bytes(&writer, 0);
+    assert_eq!(cursor_offset(&bytes, EntryOffset::Entities), HEAD_SZ as u32);
+
+    let mut cursor = Cursor::new(&bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let mut copied = Vec::new();
+    parser.parse_lumps().unwrap().write_to(&mut copied).unwrap();
+
+    assert_eq!(copied, bytes);
+}
+
+#[test]
+fn copy_lumps_without_gaps() {
+    let writer = full_bsp(BSP_VERSION).to_writer().unwrap();
+    let bytes = header_ordered_bytes(&writer, 4);
+
+    let mut cursor = Cursor::new(&bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let mut copied = Vec::new();
+    parser.parse_lumps().unwrap().write_to(&mut copied).unwrap();
+
+    // Same lumps in the same order, but packed together
+    assert_eq!(copied, header_ordered_bytes(&writer, 0));
+}
+
+#[test]
+fn replace_entities() {
+    let bsp = full_bsp(BSP_VERSION);
+    let mut bytes = Vec::new();
+    bsp.write_to(&mut bytes).unwrap();
+
+    let mut light = Entity::new();
+    light.edict = vec![
+        (
+            CString::new("classname").unwrap(),
+            CString::new("light").unwrap(),
+        ),
+        (CString::new("style").unwrap(), CString::new("10").unwrap()),
+    ];
+    let mut map = bsp.parse_entities().unwrap();
+    map.entities.push(light);
+
+    let mut cursor = Cursor::new(&bytes);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let mut writer = parser.parse_lumps().unwrap();
+    writer.set_entities(&map).unwrap();
+    let mut patched = Vec::new();
+    writer.write_to(&mut patched).unwrap();
+
+    let mut cursor = Cursor::new(&patched);
+    let mut parser = bsp::Parser::new(&mut cursor).unwrap();
+    let patched_bsp = parser.parse_bsp().unwrap();
+    let patched_map = patched_bsp.parse_entities().unwrap();
+
+    assert_eq!(patched_bsp.entities.last(), Some(&0));
+    assert_eq!(patched_map.entities.len(), 2);
+    assert_eq!(patched_map.entities[1].edict, map.entities[1].edict);
+    assert_eq!(
+        Bsp {
+            entities: bsp.entities.clone(),
+            ..patched_bsp
+        },
+        bsp
+    );
+}
+
+#[test]
+fn replace_entities_invalid() {
+    let mut entity = Entity::new();
+    entity.edict =
+        vec![(CString::new("\n").unwrap(), CString::new("oops").unwrap())];
+    let map = QuakeMap {
+        entities: vec![entity],
+    };
+    let mut writer = Writer::new(BSP_VERSION);
+    let err = writer.set_entities(&map).unwrap_err();
+
+    assert!(matches!(err, WriteError::Validation(_)));
+    assert!(writer.lump(EntryOffset::Entities).is_empty());
+}
@@ -0,0 +1,41 @@
+/// Sequential reader for little-endian fields within a byte slice.  Panics if
+/// the slice is exhausted, so record sizes must be checked ahead of time.
+pub struct LeReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> LeReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    pub fn bytes<const N: usize>(&mut self) -> [u8; N] {
+        let (head, rest) = self.bytes.split_at(N);
+        self.bytes = rest;
+        <[u8; N]>::try_from(head).unwrap()
+    }
+
+    pub fn u16(&mut self) -> u16 {
+        u16::from_le_bytes(self.bytes())
+    }
+
+    pub fn i16(&mut self) -> i16 {
+        i16::from_le_bytes(self.bytes())
+    }
+
+    pub fn u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.bytes())
+    }
+
+    pub fn i32(&mut self) -> i32 {
+        i32::from_le_bytes(self.bytes())
+    }
+
+    pub fn f32(&mut self) -> f32 {
+        f32::from_le_bytes(self.bytes())
+    }
+
+    pub fn f32s<const N: usize>(&mut self) -> [f32; N] {
+        [(); N].map(|_| self.f32())
+    }
+}
@@ -1,8 +1,10 @@
 use std::ffi::CString;
 
 mod ext_traits;
+mod le_reader;
 
 pub use ext_traits::CellOptionExt;
+pub use le_reader::LeReader;
 
 pub type Palette = [[u8; 3]; 256];
 
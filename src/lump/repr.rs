@@ -60,7 +60,7 @@ impl Image {
             panic!("Image with pixels must have width > 0");
         }
 
-        if pixel_ct % width != 0 {
+        if !pixel_ct.is_multiple_of(width) {
             panic!("Incomplete pixel row");
         }
 
@@ -65,6 +65,7 @@ pub struct TokenIterator<R: io::Read> {
 }
 
 impl<R: io::Read> TokenIterator<R> {
+    #[allow(clippy::unbuffered_bytes)]
     pub fn new(reader: R) -> TokenIterator<R> {
         TokenIterator {
             text: RefCell::new(None),
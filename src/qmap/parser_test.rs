@@ -14,7 +14,7 @@ impl ErroringReader {
 
 impl io::Read for ErroringReader {
     fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
-        Err(io::Error::new(io::ErrorKind::Other, "Generic test error"))
+        Err(io::Error::other("Generic test error"))
     }
 }
 
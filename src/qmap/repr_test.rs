@@ -66,18 +66,14 @@ fn panic_expected_error() {
 }
 
 fn simple_edict() -> Edict {
-    let mut edict = Edict::new();
-    edict.push((
+    vec![(
         CString::new("classname").unwrap(),
         CString::new("worldspawn").unwrap(),
-    ));
-    edict
+    )]
 }
 
 fn bad_edict_key() -> Edict {
-    let mut edict = Edict::new();
-    edict.push((CString::new("\n").unwrap(), CString::new("oops").unwrap()));
-    edict
+    vec![(CString::new("\n").unwrap(), CString::new("oops").unwrap())]
 }
 
 fn simple_surface() -> Surface {
@@ -200,8 +196,7 @@ fn check_bad_entities() {
     for (bad_char, (key, value)) in trials {
         let key = CString::new(key).unwrap();
         let value = CString::new(value).unwrap();
-        let mut edict = Edict::new();
-        edict.push((key, value));
+        let edict = vec![(key, value)];
         let ent = Entity {
             edict,
             brushes: vec![],
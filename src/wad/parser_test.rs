@@ -2,7 +2,7 @@ use crate::error;
 use crate::lump::{kind, Lump};
 use crate::wad;
 use std::io::Cursor;
-use std::iter::repeat;
+use std::iter::repeat_n;
 use std::mem::{size_of, size_of_val};
 use std::vec::Vec;
 use wad::repr::Head;
@@ -15,7 +15,7 @@ fn image_bytes() -> Vec<u8> {
     let mut image = Vec::new();
     image.extend(width.to_le_bytes());
     image.extend(height.to_le_bytes());
-    image.extend(repeat(0u8).take(pix_ct));
+    image.extend(repeat_n(0u8, pix_ct));
 
     image
 }
@@ -40,7 +40,7 @@ fn miptex_bytes(name: [u8; 16]) -> Vec<u8> {
 
     for i in 0..4 {
         let mip_sz = mip0_sz >> (2 * i);
-        miptex.extend(repeat(0u8).take(mip_sz.try_into().unwrap()));
+        miptex.extend(repeat_n(0u8, mip_sz.try_into().unwrap()));
     }
 
     miptex